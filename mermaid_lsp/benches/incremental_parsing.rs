@@ -0,0 +1,99 @@
+//! Compares parsing a large diagram from scratch against editing it like `textDocument/didChange` does.
+//!
+//! Run it with `cargo bench --bench incremental_parsing`.
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use mermaid_lsp::{
+    host::HostDocument,
+    mermaid::{MermaidAST, TextSpan},
+};
+
+/// Generates a flowchart with the given amount of lines, grouped in subgraphs.
+fn generate_flowchart(lines: usize) -> String {
+    let mut diagram = String::from("---\ntitle: Generated\n---\nflowchart TD\n");
+    let mut line = 0;
+    let mut subgraph = 0;
+
+    while line < lines {
+        diagram.push_str(&format!(
+            "    subgraph S{} [Subgraph {}]\n",
+            subgraph, subgraph
+        ));
+        for i in 0..20 {
+            let id = subgraph * 20 + i;
+            diagram.push_str(&format!(
+                "        N{}[Node {}] -- label --> N{}((Other))\n",
+                id,
+                id,
+                id + 1
+            ));
+        }
+        diagram.push_str("    end\n    %% Comment between subgraphs\n");
+        line += 23;
+        subgraph += 1;
+    }
+
+    diagram
+}
+
+/// Runs `routine` the given amount of times and returns its mean duration.
+///
+/// Each run gets a fresh input from `setup`, which isn't timed, and the output is dropped
+/// after the clock stops, so only the work of `routine` is measured.
+fn measure<I, O>(
+    iterations: u32,
+    mut setup: impl FnMut() -> I,
+    mut routine: impl FnMut(I) -> O,
+) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..iterations {
+        let input = setup();
+        let start = Instant::now();
+        let output = routine(black_box(input));
+        total += start.elapsed();
+        drop(black_box(output));
+    }
+    total / iterations
+}
+
+fn main() {
+    const ITERATIONS: u32 = 50;
+
+    for lines in [500, 2_000, 10_000] {
+        let diagram = generate_flowchart(lines);
+        let document = HostDocument::mermaid(diagram.clone());
+        let tree = &document.diagrams[0].ast.cst;
+        let middle = diagram.len() / 2;
+        let middle = diagram[middle..].find("label").unwrap() + middle;
+        let range = tree.range(TextSpan::new(middle, middle));
+
+        let full = measure(ITERATIONS, || diagram.clone(), MermaidAST::from_content);
+
+        let edit = measure(
+            ITERATIONS,
+            || document.clone(),
+            |mut document| {
+                document.edit(Some(range), "x");
+                document
+            },
+        );
+
+        let cst = measure(
+            ITERATIONS,
+            || tree.clone(),
+            |mut tree| {
+                tree.edit(TextSpan::new(middle, middle), "x");
+                tree
+            },
+        );
+
+        let ast = measure(ITERATIONS, || tree.clone(), MermaidAST::from_cst);
+
+        println!("{} lines ({} bytes)", lines, diagram.len());
+        println!("  full parse:          {:?}", full);
+        println!("  document edit:       {:?}", edit);
+        println!("    syntax tree edit:  {:?}", cst);
+        println!("    typed AST:         {:?}", ast);
+    }
+}
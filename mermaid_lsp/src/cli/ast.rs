@@ -0,0 +1,67 @@
+use serde::Serialize;
+
+use crate::{
+    host::{HostDocument, HostLanguage},
+    mermaid::MermaidAST,
+};
+
+use super::CliErrors;
+
+/// A diagram embedded in another document, with the line where it starts.
+#[derive(Debug, Serialize)]
+struct EmbeddedAST<'a> {
+    line: u32,
+    ast: &'a MermaidAST,
+}
+
+/// Dumps the AST of a document as pretty printed JSON.
+///
+/// A Mermaid file is a single AST, documents that embed diagrams are a list with the AST
+/// of each diagram and the zero-based line where it starts.
+pub fn ast_json(document: &HostDocument) -> Result<String, CliErrors> {
+    let json = match document.language {
+        HostLanguage::Mermaid => match document.diagrams.first() {
+            Some(diagram) => serde_json::to_string_pretty(&diagram.ast),
+            None => serde_json::to_string_pretty(&MermaidAST::default()),
+        },
+        _ => {
+            let diagrams: Vec<_> = document
+                .diagrams
+                .iter()
+                .map(|d| EmbeddedAST {
+                    line: d.map.host_line(0),
+                    ast: &d.ast,
+                })
+                .collect();
+            serde_json::to_string_pretty(&diagrams)
+        }
+    };
+
+    json.map_err(CliErrors::InvalidJson)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ast_json_of_hosts() {
+        let document = HostDocument::mermaid("flowchart LR\n    A --> B\n".to_string());
+        let value: serde_json::Value = serde_json::from_str(&ast_json(&document).unwrap()).unwrap();
+
+        assert_eq!(value["diagram"]["d_type"], "Flowchart");
+        assert_eq!(
+            value["diagram"]["data"]["Flowchart"]["edges"][0]["to"]["content"],
+            "B"
+        );
+
+        let document = HostDocument::new(
+            HostLanguage::Markdown,
+            "# Flow\n\n```mermaid\npie\n```\n".to_string(),
+        );
+        let value: serde_json::Value = serde_json::from_str(&ast_json(&document).unwrap()).unwrap();
+
+        assert_eq!(value[0]["line"], 3);
+        assert_eq!(value[0]["ast"]["diagram"]["d_type"], "Pie");
+    }
+}
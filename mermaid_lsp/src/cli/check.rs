@@ -0,0 +1,279 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{
+    host::HostDocument,
+    mermaid::{diagnostics, lint::LintConfig, lint::RULES},
+    notifications::text_document::{Diagnostic, DiagnosticSeverity, DIAGNOSTIC_SOURCE},
+};
+
+/// The format of the report printed by `check`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// One line per diagnostic, like a compiler.
+    #[default]
+    Human,
+    /// The LSP diagnostics of every file.
+    Json,
+    /// A SARIF 2.1.0 log, understood by code scanning tools.
+    Sarif,
+}
+
+/// The diagnostics of a checked file.
+#[derive(Debug, Serialize)]
+pub struct FileDiagnostics {
+    pub path: PathBuf,
+    pub diagnostics: Vec<Diagnostic>,
+
+    /// Why the file couldn't be checked, it has no diagnostics then.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl FileDiagnostics {
+    /// Computes the diagnostics of every diagram of a document.
+    pub fn new(path: PathBuf, document: &HostDocument, config: &LintConfig) -> Self {
+        let diagnostics = document
+            .diagrams
+            .iter()
+            .flat_map(|diagram| {
+                diagnostics(&diagram.ast, config)
+                    .iter()
+                    .map(|d| Diagnostic::new(diagram, d))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        FileDiagnostics {
+            path,
+            diagnostics,
+            error: None,
+        }
+    }
+
+    /// A file that couldn't be read, the rest of the files are still checked.
+    pub fn unreadable(path: PathBuf, error: String) -> Self {
+        FileDiagnostics {
+            path,
+            diagnostics: vec![],
+            error: Some(error),
+        }
+    }
+}
+
+/// Checks if some of the files couldn't be read.
+pub fn has_unreadable(files: &[FileDiagnostics]) -> bool {
+    files.iter().any(|f| f.error.is_some())
+}
+
+/// Checks if some diagnostic of the files is an error.
+pub fn has_errors(files: &[FileDiagnostics]) -> bool {
+    files
+        .iter()
+        .flat_map(|f| f.diagnostics.iter())
+        .any(|d| d.severity == Some(DiagnosticSeverity::Error as u8))
+}
+
+fn severity_name(severity: Option<u8>) -> &'static str {
+    match severity {
+        Some(1) => "error",
+        Some(2) => "warning",
+        Some(3) => "info",
+        _ => "hint",
+    }
+}
+
+fn code(diagnostic: &Diagnostic) -> &str {
+    diagnostic
+        .code
+        .as_ref()
+        .and_then(|c| c.as_str())
+        .unwrap_or_default()
+}
+
+/// Reports each diagnostic as `path:line:column: severity[code]: message`, with one-based positions.
+///
+/// Files that couldn't be read get a `path: error: reason` line instead.
+pub fn human_report(files: &[FileDiagnostics]) -> String {
+    files
+        .iter()
+        .flat_map(|file| {
+            let error = file
+                .error
+                .as_ref()
+                .map(|e| format!("{}: error: {}\n", file.path.display(), e));
+            error.into_iter().chain(file.diagnostics.iter().map(|d| {
+                format!(
+                    "{}:{}:{}: {}[{}]: {}\n",
+                    file.path.display(),
+                    d.range.start.line + 1,
+                    d.range.start.character + 1,
+                    severity_name(d.severity),
+                    code(d),
+                    d.message
+                )
+            }))
+        })
+        .collect()
+}
+
+/// Reports the diagnostics of every file as JSON.
+pub fn json_report(files: &[FileDiagnostics]) -> serde_json::Value {
+    serde_json::to_value(files).expect("Diagnostics couldn't be serialized into a value!")
+}
+
+/// Reports the diagnostics of every file as a SARIF 2.1.0 log.
+///
+/// Columns are counted in UTF-16 code units, the default of SARIF and of the LSP. Files that couldn't
+/// be read are reported as notifications of the invocation, which then isn't successful.
+pub fn sarif_report(files: &[FileDiagnostics]) -> serde_json::Value {
+    let rules: Vec<_> = RULES
+        .iter()
+        .map(|rule| json!({ "id": rule.code, "shortDescription": { "text": rule.description } }))
+        .collect();
+    let results: Vec<_> = files
+        .iter()
+        .flat_map(|file| {
+            file.diagnostics.iter().map(|d| {
+                let level = match d.severity {
+                    Some(1) => "error",
+                    Some(2) => "warning",
+                    _ => "note",
+                };
+                json!({
+                    "ruleId": code(d),
+                    "level": level,
+                    "message": { "text": d.message },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": file.path.to_string_lossy() },
+                            "region": {
+                                "startLine": d.range.start.line + 1,
+                                "startColumn": d.range.start.character + 1,
+                                "endLine": d.range.end.line + 1,
+                                "endColumn": d.range.end.character + 1,
+                            }
+                        }
+                    }]
+                })
+            })
+        })
+        .collect();
+    let notifications: Vec<_> = files
+        .iter()
+        .filter_map(|file| {
+            let error = file.error.as_ref()?;
+            Some(json!({
+                "level": "error",
+                "message": { "text": error },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": file.path.to_string_lossy() }
+                    }
+                }]
+            }))
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": DIAGNOSTIC_SOURCE,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }
+            },
+            "invocations": [{
+                "executionSuccessful": notifications.is_empty(),
+                "toolExecutionNotifications": notifications,
+            }],
+            "results": results,
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> Vec<FileDiagnostics> {
+        let document = HostDocument::mermaid("flowchart TD\n    subgraph one\n".to_string());
+        vec![FileDiagnostics::new(
+            PathBuf::from("flow.mmd"),
+            &document,
+            &LintConfig::default(),
+        )]
+    }
+
+    #[test]
+    fn check_reports() {
+        let files = files();
+
+        assert!(has_errors(&files));
+        assert_eq!(
+            human_report(&files),
+            "flow.mmd:2:5: error[unclosed-block]: This block is never closed with `end`\n"
+        );
+        assert_eq!(
+            json_report(&files)[0]["diagnostics"][0]["code"],
+            "unclosed-block"
+        );
+
+        let sarif = sarif_report(&files);
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], "unclosed-block");
+        assert_eq!(result["level"], "error");
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["region"]["startLine"],
+            2
+        );
+    }
+
+    #[test]
+    fn check_without_errors() {
+        let document = HostDocument::mermaid("flowchart TD\n    A --> A\n".to_string());
+        let files = vec![FileDiagnostics::new(
+            PathBuf::from("flow.mmd"),
+            &document,
+            &LintConfig::default(),
+        )];
+
+        assert!(!has_errors(&files));
+        assert_eq!(
+            sarif_report(&files)["runs"][0]["results"][0]["level"],
+            "note"
+        );
+    }
+
+    #[test]
+    fn check_unreadable_files() {
+        let mut files = files();
+        files.push(FileDiagnostics::unreadable(
+            PathBuf::from("missing.mmd"),
+            "missing.mmd couldn't be read: No such file".into(),
+        ));
+
+        assert!(has_unreadable(&files));
+        assert!(!has_unreadable(&files[..1]));
+        assert!(human_report(&files)
+            .ends_with("missing.mmd: error: missing.mmd couldn't be read: No such file\n"));
+        assert_eq!(
+            json_report(&files)[1]["error"],
+            "missing.mmd couldn't be read: No such file"
+        );
+        assert!(json_report(&files)[0].get("error").is_none());
+
+        let invocation = &sarif_report(&files)["runs"][0]["invocations"][0];
+        assert_eq!(invocation["executionSuccessful"], false);
+        assert_eq!(
+            invocation["toolExecutionNotifications"][0]["locations"][0]["physicalLocation"]
+                ["artifactLocation"]["uri"],
+            "missing.mmd"
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{
+    host::HostDocument,
+    jsonrpc::TextEdit,
+    mermaid::{FormatOptions, LineIndex},
+    requests::format_edits,
+};
+
+/// Applies edits that don't overlap to a text.
+pub fn apply_edits(text: &str, edits: &[TextEdit]) -> String {
+    let line_index = LineIndex::new(text);
+    let mut spans: Vec<_> = edits
+        .iter()
+        .map(|e| {
+            let start = line_index.offset(text, e.range.start);
+            let end = line_index.offset(text, e.range.end).max(start);
+            (start, end, e.new_text.as_str())
+        })
+        .collect();
+    spans.sort_by_key(|(start, end, _)| (*start, *end));
+
+    let mut formatted = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, end, new_text) in spans {
+        let start = start.max(last);
+        formatted.push_str(&text[last..start]);
+        formatted.push_str(new_text);
+        last = end.max(start);
+    }
+    formatted.push_str(&text[last..]);
+    formatted
+}
+
+/// The text of a document after formatting every diagram inside it.
+pub fn format_document(document: &HostDocument, options: &FormatOptions) -> String {
+    let edits: Vec<_> = document
+        .diagrams
+        .iter()
+        .flat_map(|diagram| {
+            format_edits(&diagram.ast.cst, options, 0, usize::MAX)
+                .into_iter()
+                .map(|e| diagram.map.host_edit(e))
+        })
+        .collect();
+
+    apply_edits(&document.text, &edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::HostLanguage;
+
+    #[test]
+    fn format_document_of_hosts() {
+        let document = HostDocument::mermaid("flowchart TD\nA-->B\n".to_string());
+
+        assert_eq!(
+            format_document(&document, &FormatOptions::default()),
+            "flowchart TD\n    A --> B\n"
+        );
+
+        let document = HostDocument::new(
+            HostLanguage::Markdown,
+            "Text  \n\n  ```mermaid\n  flowchart TD\n  A-->B\n  ```\n".to_string(),
+        );
+
+        assert_eq!(
+            format_document(&document, &FormatOptions::default()),
+            "Text  \n\n  ```mermaid\n  flowchart TD\n      A --> B\n  ```\n"
+        );
+    }
+}
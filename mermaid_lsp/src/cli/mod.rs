@@ -0,0 +1,125 @@
+mod ast;
+mod check;
+mod fmt;
+#[cfg(windows)]
+mod named_pipe;
+mod serve;
+
+use std::{
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+pub use ast::*;
+pub use check::*;
+pub use fmt::*;
+pub use serve::*;
+
+use crate::{
+    host::{HostDocument, HostLanguage},
+    mermaid::lint::{LintConfigErrors, CONFIG_FILE_NAME},
+};
+
+#[derive(Debug)]
+pub enum CliErrors {
+    ReadError(PathBuf, std::io::Error),
+    WriteError(PathBuf, std::io::Error),
+    InvalidJson(serde_json::Error),
+    /// The output of the command couldn't be written to STDOUT.
+    OutputError(std::io::Error),
+    /// The project config of the current directory exists but can't be used.
+    InvalidLintConfig(LintConfigErrors),
+    /// The server couldn't connect to the client at the address.
+    ConnectError(String, std::io::Error),
+    /// The server couldn't wait for clients at the address.
+    ListenError(String, std::io::Error),
+    AcceptError(std::io::Error),
+    /// The transport isn't available on this platform, or with these flags.
+    UnsupportedTransport(String),
+}
+
+impl std::fmt::Display for CliErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliErrors::ReadError(path, e) => {
+                write!(f, "{} couldn't be read: {}", path.display(), e)
+            }
+            CliErrors::WriteError(path, e) => {
+                write!(f, "{} couldn't be written: {}", path.display(), e)
+            }
+            CliErrors::InvalidJson(e) => write!(f, "The output couldn't be serialized: {}", e),
+            CliErrors::OutputError(e) => write!(f, "The output couldn't be written: {}", e),
+            CliErrors::InvalidLintConfig(LintConfigErrors::ReadError(e)) => {
+                write!(f, "{} couldn't be read: {}", CONFIG_FILE_NAME, e)
+            }
+            CliErrors::InvalidLintConfig(LintConfigErrors::InvalidConfig(e)) => {
+                write!(f, "{} isn't a valid config: {}", CONFIG_FILE_NAME, e)
+            }
+            CliErrors::ConnectError(address, e) => {
+                write!(f, "The server couldn't connect to {}: {}", address, e)
+            }
+            CliErrors::ListenError(address, e) => {
+                write!(f, "The server couldn't listen on {}: {}", address, e)
+            }
+            CliErrors::AcceptError(e) => write!(f, "A client couldn't connect: {}", e),
+            CliErrors::UnsupportedTransport(transport) => {
+                write!(f, "The server can't listen or connect over {}", transport)
+            }
+        }
+    }
+}
+
+/// Reads a file and parses every diagram inside it.
+///
+/// The host language comes from the extension, unknown extensions are read as Mermaid.
+pub fn read_document(path: &Path) -> Result<HostDocument, CliErrors> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| CliErrors::ReadError(path.to_path_buf(), e))?;
+    let language = HostLanguage::from_path(path).unwrap_or_default();
+
+    Ok(HostDocument::new(language, text))
+}
+
+/// Writes the output of a command.
+///
+/// A reader that stops early, like `| head`, closes the pipe and that isn't an error.
+pub fn write_output(output: &mut impl Write, text: &str) -> Result<(), CliErrors> {
+    match output
+        .write_all(text.as_bytes())
+        .and_then(|_| output.flush())
+    {
+        Err(e) if e.kind() != ErrorKind::BrokenPipe => Err(CliErrors::OutputError(e)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A pipe whose reader is gone.
+    struct ClosedPipe(ErrorKind);
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(self.0.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_output_ignores_closed_pipes() {
+        let mut output = vec![];
+        assert!(write_output(&mut output, "flow.mmd\n").is_ok());
+        assert_eq!(output, b"flow.mmd\n");
+
+        assert!(write_output(&mut ClosedPipe(ErrorKind::BrokenPipe), "flow.mmd\n").is_ok());
+        assert!(matches!(
+            write_output(&mut ClosedPipe(ErrorKind::PermissionDenied), "flow.mmd\n"),
+            Err(CliErrors::OutputError(_))
+        ));
+    }
+}
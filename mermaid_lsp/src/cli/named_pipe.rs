@@ -0,0 +1,154 @@
+use std::{
+    cell::Cell,
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    os::windows::{
+        ffi::OsStrExt,
+        io::{AsRawHandle, FromRawHandle},
+    },
+    path::Path,
+    ptr,
+};
+
+use windows_sys::Win32::{
+    Foundation::{ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE},
+    Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX},
+    System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe, PIPE_REJECT_REMOTE_CLIENTS,
+        PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    },
+};
+
+/// The size of the buffers of each instance of a pipe, Windows grows them when needed.
+const BUFFER_SIZE: u32 = 64 * 1024;
+
+/// One end of a Windows named pipe like `\\.\pipe\mermaid`, what VS Code uses for `--pipe` on Windows.
+pub struct NamedPipe {
+    file: File,
+
+    /// Set on the end created by the server, only that end can disconnect the other one.
+    server: bool,
+}
+
+impl NamedPipe {
+    /// Connects to a pipe the client is waiting on.
+    pub fn connect(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(NamedPipe {
+            file,
+            server: false,
+        })
+    }
+
+    pub fn try_clone(&self) -> std::io::Result<Self> {
+        Ok(NamedPipe {
+            file: self.file.try_clone()?,
+            server: self.server,
+        })
+    }
+
+    /// Closes the connection once the client read everything, even while other handles are open.
+    ///
+    /// The end that connected can't close the pipe for both, it only closes with its last handle.
+    pub fn disconnect(&self) -> std::io::Result<()> {
+        if !self.server {
+            return Ok(());
+        }
+
+        // Disconnecting throws away what the client didn't read yet.
+        self.file.sync_all()?;
+        // SAFETY: the handle belongs to `file`, which is still open.
+        match unsafe { DisconnectNamedPipe(self.file.as_raw_handle()) } {
+            0 => Err(std::io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Read for NamedPipe {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Write for NamedPipe {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Waits for the clients of a named pipe, each one gets its own instance of the pipe.
+pub struct NamedPipeListener {
+    /// The name of the pipe as a nul terminated wide string.
+    name: Vec<u16>,
+
+    /// The instance the next client connects to, created before accepting it so clients
+    /// don't find the pipe missing while the server handles the previous one.
+    next: Cell<Option<File>>,
+}
+
+impl NamedPipeListener {
+    /// Creates the pipe, it fails if another process already did.
+    pub fn bind(path: &Path) -> std::io::Result<Self> {
+        let name: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let first = create_instance(&name, true)?;
+        Ok(NamedPipeListener {
+            name,
+            next: Cell::new(Some(first)),
+        })
+    }
+
+    /// Waits for the next client.
+    pub fn accept(&self) -> std::io::Result<NamedPipe> {
+        let file = match self.next.take() {
+            Some(file) => file,
+            None => create_instance(&self.name, false)?,
+        };
+
+        // SAFETY: the handle belongs to `file`, which is still open, and it isn't overlapped.
+        let connected = unsafe { ConnectNamedPipe(file.as_raw_handle(), ptr::null_mut()) } != 0;
+        if !connected {
+            // The client may connect between creating the instance and waiting for it.
+            let error = std::io::Error::last_os_error();
+            if error.raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32) {
+                return Err(error);
+            }
+        }
+
+        // A failure is retried by the next call.
+        self.next.set(create_instance(&self.name, false).ok());
+        Ok(NamedPipe { file, server: true })
+    }
+}
+
+/// Creates an instance of the pipe, the first one fails if the pipe already exists.
+fn create_instance(name: &[u16], first: bool) -> std::io::Result<File> {
+    let open_mode = match first {
+        true => PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE,
+        false => PIPE_ACCESS_DUPLEX,
+    };
+
+    // SAFETY: `name` ends with a nul, and the default security attributes are used.
+    let handle = unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            open_mode,
+            PIPE_TYPE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            BUFFER_SIZE,
+            BUFFER_SIZE,
+            0,
+            ptr::null(),
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    // SAFETY: the handle was just created and nothing else owns it.
+    Ok(unsafe { File::from_raw_handle(handle) })
+}
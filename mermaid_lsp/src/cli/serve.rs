@@ -0,0 +1,455 @@
+#[cfg(unix)]
+use std::{
+    io::ErrorKind,
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+};
+use std::{
+    io::{Read, Write},
+    net::{Ipv4Addr, Shutdown, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "websocket")]
+use crate::jsonrpc::spawn_websocket;
+use crate::jsonrpc::{spawn_stream, Channels};
+
+#[cfg(windows)]
+use super::named_pipe::{NamedPipe, NamedPipeListener};
+use super::CliErrors;
+
+/// How the server talks to its client, from the command line.
+///
+/// The flags are the ones editors like VS Code pass to the servers they start.
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct TransportOptions {
+    /// Talk to the client over STDIN and STDOUT, the default
+    #[arg(long, global = true, conflicts_with_all = ["socket", "pipe", "listen"])]
+    pub stdio: bool,
+
+    /// Connect to the client on this TCP port of localhost
+    #[arg(long, value_name = "PORT", global = true, conflicts_with = "pipe")]
+    pub socket: Option<u16>,
+
+    /// Connect to the client on this Unix domain socket, or Windows named pipe like \\.\pipe\mermaid
+    #[arg(long, value_name = "PATH", global = true)]
+    pub pipe: Option<PathBuf>,
+
+    /// Wait for clients on `--socket` or `--pipe` instead, each one gets its own server
+    #[arg(long, global = true)]
+    pub listen: bool,
+
+    /// Wait for WebSocket clients on this TCP port of localhost, each text frame is a message
+    #[cfg(feature = "websocket")]
+    #[arg(
+        long,
+        value_name = "PORT",
+        global = true,
+        conflicts_with_all = ["stdio", "socket", "pipe"]
+    )]
+    pub websocket: Option<u16>,
+}
+
+/// The stream a client and the server talk through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transport {
+    Stdio,
+    /// A TCP port of localhost.
+    Socket(u16),
+    /// A Unix domain socket, or a named pipe on Windows.
+    Pipe(PathBuf),
+    /// A TCP port of localhost, where clients talk with WebSocket frames.
+    #[cfg(feature = "websocket")]
+    WebSocket(u16),
+}
+
+impl TransportOptions {
+    /// The transport chosen by the flags, STDIO unless there's an address.
+    pub fn transport(&self) -> Transport {
+        #[cfg(feature = "websocket")]
+        if let Some(port) = self.websocket {
+            return Transport::WebSocket(port);
+        }
+
+        match (self.socket, &self.pipe) {
+            (Some(port), _) => Transport::Socket(port),
+            (None, Some(path)) => Transport::Pipe(path.clone()),
+            (None, None) => Transport::Stdio,
+        }
+    }
+
+    /// Whether the server waits for its clients, instead of connecting to one.
+    pub fn listens(&self) -> bool {
+        #[cfg(feature = "websocket")]
+        if self.websocket.is_some() {
+            return true;
+        }
+
+        self.listen
+    }
+}
+
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transport::Stdio => f.write_str("STDIO"),
+            Transport::Socket(port) => write!(f, "{}:{}", Ipv4Addr::LOCALHOST, port),
+            Transport::Pipe(path) => write!(f, "{}", path.display()),
+            #[cfg(feature = "websocket")]
+            Transport::WebSocket(port) => write!(f, "ws://{}:{}", Ipv4Addr::LOCALHOST, port),
+        }
+    }
+}
+
+/// A client that connected to the server.
+pub enum Connection {
+    /// A byte stream, like STDIO or a socket.
+    Stream {
+        reader: Box<dyn Read + Send>,
+        writer: Box<dyn Write + Send>,
+    },
+    /// A socket that still has to complete the WebSocket handshake.
+    #[cfg(feature = "websocket")]
+    WebSocket(TcpStream),
+}
+
+impl Connection {
+    /// Starts the threads that read and write the messages of the client.
+    pub fn spawn(self) -> Channels {
+        match self {
+            Connection::Stream { reader, writer } => spawn_stream(reader, writer),
+            #[cfg(feature = "websocket")]
+            Connection::WebSocket(stream) => spawn_websocket(stream),
+        }
+    }
+
+    fn socket<T: Socket + Read + Write + Send + 'static>(stream: T) -> std::io::Result<Self> {
+        Ok(Connection::Stream {
+            reader: Box::new(stream.try_clone()?),
+            writer: Box::new(SocketWriter(stream)),
+        })
+    }
+}
+
+/// The sockets a client can connect with.
+trait Socket: Sized {
+    fn try_clone(&self) -> std::io::Result<Self>;
+    fn shutdown(&self) -> std::io::Result<()>;
+}
+
+impl Socket for TcpStream {
+    fn try_clone(&self) -> std::io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+
+    fn shutdown(&self) -> std::io::Result<()> {
+        TcpStream::shutdown(self, Shutdown::Both)
+    }
+}
+
+#[cfg(unix)]
+impl Socket for UnixStream {
+    fn try_clone(&self) -> std::io::Result<Self> {
+        UnixStream::try_clone(self)
+    }
+
+    fn shutdown(&self) -> std::io::Result<()> {
+        UnixStream::shutdown(self, Shutdown::Both)
+    }
+}
+
+#[cfg(windows)]
+impl Socket for NamedPipe {
+    fn try_clone(&self) -> std::io::Result<Self> {
+        NamedPipe::try_clone(self)
+    }
+
+    fn shutdown(&self) -> std::io::Result<()> {
+        self.disconnect()
+    }
+}
+
+/// The writing half of a socket, it closes the whole socket once dropped.
+///
+/// A socket stays open while any of its halves is alive, so the client wouldn't see the server
+/// leave while the reader is still waiting for it.
+struct SocketWriter<T: Socket + Write>(T);
+
+impl<T: Socket + Write> Write for SocketWriter<T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<T: Socket + Write> Drop for SocketWriter<T> {
+    fn drop(&mut self) {
+        let _ = self.0.shutdown();
+    }
+}
+
+/// A Unix domain socket of a `--listen` server, its file is removed when the server stops listening.
+#[cfg(unix)]
+pub struct UnixSocketListener {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl UnixSocketListener {
+    /// Creates the socket, replacing the file left behind by a server that was killed.
+    ///
+    /// The file is only replaced when it's a socket nobody listens on, a running server keeps its socket.
+    fn bind(path: &Path) -> std::io::Result<Self> {
+        let stale = std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket())
+            && UnixStream::connect(path).is_err_and(|e| e.kind() == ErrorKind::ConnectionRefused);
+        if stale {
+            std::fs::remove_file(path)?;
+        }
+
+        Ok(UnixSocketListener {
+            listener: UnixListener::bind(path)?,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// The file of the socket.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(unix)]
+impl Drop for UnixSocketListener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Waits for the clients of a `--listen` server.
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixSocketListener),
+    #[cfg(windows)]
+    Pipe(NamedPipeListener),
+    #[cfg(feature = "websocket")]
+    WebSocket(TcpListener),
+}
+
+impl Listener {
+    /// Waits for the next client.
+    pub fn accept(&self) -> Result<Connection, CliErrors> {
+        let connection = match self {
+            Listener::Tcp(listener) => listener.accept().and_then(|(s, _)| Connection::socket(s)),
+            #[cfg(unix)]
+            Listener::Unix(socket) => socket
+                .listener
+                .accept()
+                .and_then(|(s, _)| Connection::socket(s)),
+            #[cfg(windows)]
+            Listener::Pipe(listener) => listener.accept().and_then(Connection::socket),
+            #[cfg(feature = "websocket")]
+            Listener::WebSocket(listener) => {
+                listener.accept().map(|(s, _)| Connection::WebSocket(s))
+            }
+        };
+        connection.map_err(CliErrors::AcceptError)
+    }
+
+    /// The file that has to be removed once the server stops listening, if the listener has one.
+    pub fn socket_file(&self) -> Option<&Path> {
+        match self {
+            #[cfg(unix)]
+            Listener::Unix(socket) => Some(socket.path()),
+            _ => None,
+        }
+    }
+}
+
+impl Transport {
+    /// Connects to a client that is waiting for the server, like VS Code does with its servers.
+    pub fn connect(&self) -> Result<Connection, CliErrors> {
+        let error = |e| CliErrors::ConnectError(self.to_string(), e);
+        match self {
+            Transport::Stdio => Ok(Connection::Stream {
+                reader: Box::new(std::io::stdin()),
+                writer: Box::new(std::io::stdout()),
+            }),
+            Transport::Socket(port) => TcpStream::connect((Ipv4Addr::LOCALHOST, *port))
+                .and_then(Connection::socket)
+                .map_err(error),
+            #[cfg(unix)]
+            Transport::Pipe(path) => UnixStream::connect(path)
+                .and_then(Connection::socket)
+                .map_err(error),
+            #[cfg(windows)]
+            Transport::Pipe(path) => NamedPipe::connect(path)
+                .and_then(Connection::socket)
+                .map_err(error),
+            #[cfg(not(any(unix, windows)))]
+            Transport::Pipe(_) => Err(CliErrors::UnsupportedTransport(self.to_string())),
+            // Browsers can only connect to the server.
+            #[cfg(feature = "websocket")]
+            Transport::WebSocket(_) => Err(CliErrors::UnsupportedTransport(self.to_string())),
+        }
+    }
+
+    /// Starts waiting for clients on the address of the transport.
+    pub fn listen(&self) -> Result<Listener, CliErrors> {
+        let error = |e| CliErrors::ListenError(self.to_string(), e);
+        match self {
+            Transport::Stdio => Err(CliErrors::UnsupportedTransport(self.to_string())),
+            Transport::Socket(port) => TcpListener::bind((Ipv4Addr::LOCALHOST, *port))
+                .map(Listener::Tcp)
+                .map_err(error),
+            #[cfg(unix)]
+            Transport::Pipe(path) => UnixSocketListener::bind(path)
+                .map(Listener::Unix)
+                .map_err(error),
+            #[cfg(windows)]
+            Transport::Pipe(path) => NamedPipeListener::bind(path)
+                .map(Listener::Pipe)
+                .map_err(error),
+            #[cfg(not(any(unix, windows)))]
+            Transport::Pipe(_) => Err(CliErrors::UnsupportedTransport(self.to_string())),
+            #[cfg(feature = "websocket")]
+            Transport::WebSocket(port) => TcpListener::bind((Ipv4Addr::LOCALHOST, *port))
+                .map(Listener::WebSocket)
+                .map_err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use crate::jsonrpc::{ClientMessage, ClientPayload};
+
+    use super::*;
+
+    #[derive(Debug, Parser)]
+    struct Cli {
+        #[command(flatten)]
+        transport: TransportOptions,
+    }
+
+    fn transport(args: &[&str]) -> Result<Transport, clap::Error> {
+        let args = std::iter::once("mermaid_lsp").chain(args.iter().copied());
+        Cli::try_parse_from(args).map(|cli| cli.transport.transport())
+    }
+
+    #[test]
+    fn transport_options() {
+        assert_eq!(transport(&[]).unwrap(), Transport::Stdio);
+        assert_eq!(transport(&["--stdio"]).unwrap(), Transport::Stdio);
+        assert_eq!(
+            transport(&["--socket=5007"]).unwrap(),
+            Transport::Socket(5007)
+        );
+        assert_eq!(
+            transport(&["--pipe", "/tmp/lsp.sock", "--listen"]).unwrap(),
+            Transport::Pipe(PathBuf::from("/tmp/lsp.sock"))
+        );
+        assert!(transport(&["--stdio", "--socket=5007"]).is_err());
+        assert!(transport(&["--socket=5007", "--pipe=/tmp/lsp.sock"]).is_err());
+        assert!(Transport::Stdio.listen().is_err());
+    }
+
+    /// Sends a request from the client and a response from the server.
+    fn exchange(listener: Listener, client: Transport) {
+        let client = std::thread::spawn(move || {
+            let channels = client.connect().unwrap().spawn();
+            channels
+                .writer
+                .send(r#"{"jsonrpc":"2.0","id":1,"method":"shutdown"}"#.into())
+                .unwrap();
+            let response = channels.messages.recv().unwrap();
+            drop(channels.writer);
+            channels.thread.join().unwrap();
+            response
+        });
+
+        let server = listener.accept().unwrap().spawn();
+        assert!(matches!(
+            server.messages.recv().unwrap(),
+            Ok(ClientPayload::Single(ClientMessage::Request { .. }))
+        ));
+        server
+            .writer
+            .send(r#"{"jsonrpc":"2.0","id":1,"result":null}"#.into())
+            .unwrap();
+        assert!(matches!(
+            client.join().unwrap(),
+            Ok(ClientPayload::Single(ClientMessage::Response { .. }))
+        ));
+
+        // The client closed the socket once its writer was done.
+        assert!(server.messages.recv().is_err());
+    }
+
+    #[test]
+    fn tcp_transport() {
+        let listener = Transport::Socket(0).listen().unwrap();
+        let Listener::Tcp(tcp) = &listener else {
+            panic!("A socket should listen on TCP!");
+        };
+        let port = tcp.local_addr().unwrap().port();
+
+        exchange(listener, Transport::Socket(port));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pipe_transport() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("lsp.sock");
+        let listener = Transport::Pipe(path.clone()).listen().unwrap();
+
+        exchange(listener, Transport::Pipe(path));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pipe_socket_files() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("lsp.sock");
+        let transport = Transport::Pipe(path.clone());
+
+        // A killed server leaves its socket behind.
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+        let listener = transport.listen().unwrap();
+        assert_eq!(listener.socket_file(), Some(path.as_path()));
+
+        // A running server keeps its socket.
+        assert!(matches!(
+            transport.listen(),
+            Err(CliErrors::ListenError(..))
+        ));
+        assert!(path.exists());
+
+        drop(listener);
+        assert!(!path.exists());
+
+        // Other files are never replaced.
+        std::fs::write(&path, "Not a socket").unwrap();
+        assert!(transport.listen().is_err());
+        assert!(path.exists());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn named_pipe_transport() {
+        let path = PathBuf::from(format!(r"\\.\pipe\mermaid-lsp-{}", std::process::id()));
+        let listener = Transport::Pipe(path.clone()).listen().unwrap();
+
+        exchange(listener, Transport::Pipe(path));
+    }
+}
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    jsonrpc::{ResponseError, ServerNotification},
+    requests::WorkspaceEdit,
+    ServerState,
+};
+
+/// Params sent with the `workspace/applyEdit` request.
+#[derive(Debug, Serialize)]
+pub struct ApplyWorkspaceEditParams {
+    /// An optional label of the workspace edit. This label is
+    /// presented in the user interface for example on an undo
+    /// stack to undo the workspace edit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    /// The edits to apply.
+    pub edit: WorkspaceEdit,
+}
+
+/// The result of the `workspace/applyEdit` request.
+#[derive(Debug, Deserialize)]
+pub struct ApplyWorkspaceEditResult {
+    /// Indicates whether the edit was applied or not.
+    pub applied: bool,
+
+    /// An optional textual description for why the edit was not applied.
+    #[serde(rename = "failureReason")]
+    pub failure_reason: Option<String>,
+}
+
+/// The workspace/applyEdit request is sent from the server to the client to modify resource on the client side.
+pub fn apply_edit_request<F>(state: &mut ServerState, params: ApplyWorkspaceEditParams, handler: F)
+where
+    F: FnOnce(
+            &mut ServerState,
+            Result<ApplyWorkspaceEditResult, ResponseError>,
+        ) -> Vec<ServerNotification>
+        + Send
+        + 'static,
+{
+    state.send_request("workspace/applyEdit", params, handler)
+}
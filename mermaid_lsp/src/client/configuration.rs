@@ -0,0 +1,51 @@
+use serde::Serialize;
+
+use crate::{
+    jsonrpc::{ResponseError, ServerNotification},
+    ServerState,
+};
+
+/// Params sent with the `workspace/configuration` request.
+#[derive(Debug, Serialize)]
+pub struct ConfigurationParams {
+    pub items: Vec<ConfigurationItem>,
+}
+
+impl ConfigurationParams {
+    /// Asks for a single section of the settings, for every scope.
+    pub fn section(section: &str) -> Self {
+        ConfigurationParams {
+            items: vec![ConfigurationItem {
+                scope_uri: None,
+                section: Some(section.to_string()),
+            }],
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigurationItem {
+    /// The scope to get the configuration section for.
+    #[serde(rename = "scopeUri", skip_serializing_if = "Option::is_none")]
+    pub scope_uri: Option<String>,
+
+    /// The configuration section asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+}
+
+/// The workspace/configuration request is sent from the server to the client to fetch configuration settings from the client.
+/// The request can fetch several configuration settings in one roundtrip.
+/// The order of the returned configuration settings correspond to the order of the passed ConfigurationItems
+/// (e.g. the first item in the response is the result for the first configuration item in the params).
+pub fn configuration_request<F>(state: &mut ServerState, params: ConfigurationParams, handler: F)
+where
+    F: FnOnce(
+            &mut ServerState,
+            Result<Vec<serde_json::Value>, ResponseError>,
+        ) -> Vec<ServerNotification>
+        + Send
+        + 'static,
+{
+    state.send_request("workspace/configuration", params, handler)
+}
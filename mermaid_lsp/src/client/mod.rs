@@ -0,0 +1,138 @@
+mod apply_edit;
+mod configuration;
+mod register_capability;
+mod show_message_request;
+
+pub use apply_edit::*;
+pub use configuration::*;
+pub use register_capability::*;
+pub use show_message_request::*;
+
+use log::{debug, error};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    jsonrpc::{ErrorCodes, LspId, ResponseError, ServerNotification},
+    ServerState,
+};
+
+/// Handles the response to a request sent to the client, it returns the notifications to send back.
+pub type ResponseHandler = Box<
+    dyn FnOnce(
+            &mut ServerState,
+            Result<serde_json::Value, ResponseError>,
+        ) -> Vec<ServerNotification>
+        + Send,
+>;
+
+impl ServerState {
+    /// Queues a request for the client, `handler` gets its result once the response arrives.
+    ///
+    /// The request is sent after the current message is handled. A result that doesn't match
+    /// the type `handler` expects is turned into an `InternalError`.
+    pub fn send_request<P, R, F>(&mut self, method: &str, params: P, handler: F)
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+        F: FnOnce(&mut ServerState, Result<R, ResponseError>) -> Vec<ServerNotification>
+            + Send
+            + 'static,
+    {
+        let params = serde_json::to_value(params)
+            .expect("Request params couldn't be serialized into a value!");
+        let name = method.to_string();
+        let handler: ResponseHandler = Box::new(move |state, result| {
+            let result = result.and_then(|value| {
+                serde_json::from_value(value).map_err(|e| {
+                    error!("Invalid response to {} received! {:?}", name, e);
+                    ResponseError::new(
+                        ErrorCodes::InternalError,
+                        format!("Invalid response to {}: {}", name, e),
+                    )
+                })
+            });
+            handler(state, result)
+        });
+
+        let request = self.pending.request(method, params, handler);
+        debug!("Queueing request {:?}", request);
+        self.outgoing.push(request);
+    }
+
+    /// Gives the response of the client to the handler of its request.
+    pub fn handle_response(
+        &mut self,
+        id: &LspId,
+        result: Result<serde_json::Value, ResponseError>,
+    ) -> Vec<ServerNotification> {
+        match self.pending.complete(id) {
+            Some(handler) => handler(self, result),
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn client_requests_get_their_responses() {
+        let mut state = ServerState::default();
+        let (sections, received) = crossbeam_channel::unbounded();
+
+        configuration_request(
+            &mut state,
+            ConfigurationParams::section("mermaid"),
+            move |_, result| {
+                sections.send(result).unwrap();
+                vec![]
+            },
+        );
+        show_message_request(
+            &mut state,
+            ShowMessageRequestParams::new(
+                crate::notifications::window::MessageType::Info,
+                "Pick one".into(),
+                &["A", "B"],
+            ),
+            |_, result| {
+                let notification = match result {
+                    Err(e) => ServerNotification::new("test/failed", json!(e.code())),
+                    Ok(_) => ServerNotification::new("test/picked", json!(null)),
+                };
+                vec![notification]
+            },
+        );
+
+        let requests: Vec<_> = std::mem::take(&mut state.outgoing)
+            .into_iter()
+            .map(|r| serde_json::to_value(r).unwrap())
+            .collect();
+        assert_eq!(requests[0]["method"], "workspace/configuration");
+        assert_eq!(
+            requests[0]["params"],
+            json!({ "items": [{ "section": "mermaid" }] })
+        );
+        assert_eq!(requests[1]["method"], "window/showMessageRequest");
+        assert_eq!(requests[1]["params"]["actions"][1]["title"], "B");
+
+        state.handle_response(&LspId::from(1), Ok(json!([{ "lint": {} }])));
+        assert_eq!(
+            received.try_recv().unwrap().unwrap(),
+            vec![json!({ "lint": {} })]
+        );
+
+        let sent = state.handle_response(&LspId::from(2), Ok(json!({ "title": 1 })));
+        assert_eq!(
+            serde_json::to_value(&sent[0]).unwrap()["params"],
+            ErrorCodes::InternalError as i32
+        );
+        assert!(state
+            .handle_response(&LspId::from(2), Ok(json!(null)))
+            .is_empty());
+        assert!(state.pending.is_empty());
+    }
+}
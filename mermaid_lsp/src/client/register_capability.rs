@@ -0,0 +1,42 @@
+use serde::Serialize;
+
+use crate::{
+    jsonrpc::{ResponseError, ServerNotification},
+    ServerState,
+};
+
+/// Params sent with the `client/registerCapability` request.
+#[derive(Debug, Serialize)]
+pub struct RegistrationParams {
+    pub registrations: Vec<Registration>,
+}
+
+/// General parameters to register for a capability.
+#[derive(Debug, Serialize)]
+pub struct Registration {
+    /// The id used to register the request. The id can be used to deregister
+    /// the request again.
+    pub id: String,
+
+    /// The method / capability to register for.
+    pub method: String,
+
+    /// Options necessary for the registration.
+    #[serde(rename = "registerOptions", skip_serializing_if = "Option::is_none")]
+    pub register_options: Option<serde_json::Value>,
+}
+
+/// The client/registerCapability request is sent from the server to the client to register for a new capability on the client side.
+/// Not all clients need to support dynamic capability registration.
+/// A client opts in via the dynamicRegistration property on the specific client capabilities.
+pub fn register_capability_request<F>(
+    state: &mut ServerState,
+    params: RegistrationParams,
+    handler: F,
+) where
+    F: FnOnce(&mut ServerState, Result<(), ResponseError>) -> Vec<ServerNotification>
+        + Send
+        + 'static,
+{
+    state.send_request("client/registerCapability", params, handler)
+}
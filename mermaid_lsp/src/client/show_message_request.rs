@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    jsonrpc::{ResponseError, ServerNotification},
+    notifications::window::MessageType,
+    ServerState,
+};
+
+/// Params sent with the `window/showMessageRequest` request.
+#[derive(Debug, Serialize)]
+pub struct ShowMessageRequestParams {
+    /// The message type.
+    #[serde(rename = "type")]
+    pub message_type: u8,
+
+    /// The actual message.
+    pub message: String,
+
+    /// The message action items to present.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actions: Option<Vec<MessageActionItem>>,
+}
+
+impl ShowMessageRequestParams {
+    /// A message with a button for each action.
+    pub fn new(message_type: MessageType, message: String, actions: &[&str]) -> Self {
+        ShowMessageRequestParams {
+            message_type: message_type as u8,
+            message,
+            actions: Some(
+                actions
+                    .iter()
+                    .map(|title| MessageActionItem {
+                        title: title.to_string(),
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MessageActionItem {
+    /// A short title like 'Retry', 'Open Log' etc.
+    pub title: String,
+}
+
+/// The show message request is sent from a server to a client to ask the client to display a particular message in the user interface.
+/// In addition to the show message notification the request allows to pass actions and to wait for an answer from the client.
+///
+/// The result is the selected action, or `None` if the message was dismissed.
+pub fn show_message_request<F>(
+    state: &mut ServerState,
+    params: ShowMessageRequestParams,
+    handler: F,
+) where
+    F: FnOnce(
+            &mut ServerState,
+            Result<Option<MessageActionItem>, ResponseError>,
+        ) -> Vec<ServerNotification>
+        + Send
+        + 'static,
+{
+    state.send_request("window/showMessageRequest", params, handler)
+}
@@ -0,0 +1,130 @@
+use super::{DiagramBuilder, DiagramSource, EmbeddedDiagram};
+
+/// The block style of the blocks that contain diagrams.
+const MERMAID_STYLE: &str = "mermaid";
+
+/// Checks if a line, without its line break, delimits a block like `----` or `....`.
+fn is_delimiter(line: &str) -> bool {
+    if line == "--" {
+        return true;
+    }
+
+    let mut chars = line.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    "-./=+*_".contains(first) && line.len() >= 4 && chars.all(|c| c == first)
+}
+
+/// Checks if a line is an attribute list whose style is `mermaid`, like `[mermaid, format=svg]`.
+fn is_mermaid_style(line: &str) -> bool {
+    line.strip_prefix('[')
+        .and_then(|l| l.strip_suffix(']'))
+        .and_then(|attributes| attributes.split(',').next())
+        .is_some_and(|style| style.trim() == MERMAID_STYLE)
+}
+
+/// Finds every diagram of an AsciiDoc document.
+///
+/// Diagrams live inside blocks with the `[mermaid]` style used by Asciidoctor Diagram. The block
+/// is either delimited, like a `....` literal block, or a paragraph that ends at the next blank line.
+/// Other delimited blocks are skipped, so a listing that shows `[mermaid]` isn't a diagram.
+pub fn asciidoc_diagrams(text: &str) -> Vec<EmbeddedDiagram> {
+    asciidoc_sources(text)
+        .into_iter()
+        .map(DiagramSource::parse)
+        .collect()
+}
+
+/// The diagrams of [`asciidoc_diagrams`], before parsing them.
+pub(crate) fn asciidoc_sources(text: &str) -> Vec<DiagramSource> {
+    let lines: Vec<_> = text.split_inclusive('\n').collect();
+    let mut diagrams = vec![];
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i].trim_end();
+        i += 1;
+
+        if is_delimiter(line) {
+            let close = lines[i..].iter().position(|l| l.trim_end() == line);
+            i = close.map_or(lines.len(), |close| i + close + 1);
+            continue;
+        }
+        if !is_mermaid_style(line) {
+            continue;
+        }
+
+        let Some(next) = lines.get(i).map(|l| l.trim_end()) else {
+            break;
+        };
+        let (start, end) = match is_delimiter(next) {
+            true => {
+                let start = i + 1;
+                let close = lines[start..].iter().position(|l| l.trim_end() == next);
+                let end = close.map_or(lines.len(), |close| start + close);
+                i = (end + 1).min(lines.len());
+                (start, end)
+            }
+            false => {
+                let blank = lines[i..].iter().position(|l| l.trim().is_empty());
+                let end = blank.map_or(lines.len(), |blank| i + blank);
+                let start = i;
+                i = end;
+                (start, end)
+            }
+        };
+
+        let mut builder = DiagramBuilder::new(start as u32, "");
+        for line in &lines[start..end] {
+            builder.push("", line);
+        }
+        diagrams.push(builder.finish(""));
+    }
+
+    diagrams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn asciidoc_blocks() {
+        let text = r#"= Diagrams
+
+[mermaid, format=svg]
+....
+flowchart TD
+    A --> B
+....
+
+----
+[mermaid]
+not a diagram
+----
+
+[mermaid]
+sequenceDiagram
+    Alice->>Bob: Hi
+
+[source,mermaid]
+----
+pie
+----
+"#;
+
+        let diagrams = asciidoc_diagrams(text);
+        let texts: Vec<_> = diagrams.iter().map(|d| d.ast.cst.text()).collect();
+
+        assert_eq!(
+            texts,
+            vec![
+                "flowchart TD\n    A --> B\n",
+                "sequenceDiagram\n    Alice->>Bob: Hi\n"
+            ]
+        );
+        assert_eq!(diagrams[0].map.host_line(1), 5);
+        assert_eq!(diagrams[1].map.host_line(0), 14);
+    }
+}
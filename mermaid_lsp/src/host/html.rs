@@ -0,0 +1,171 @@
+use super::{DiagramBuilder, DiagramSource, EmbeddedDiagram};
+
+/// The class of the elements that contain diagrams.
+const MERMAID_CLASS: &str = "mermaid";
+
+/// The elements that can contain diagrams.
+const DIAGRAM_TAGS: [&str; 2] = ["pre", "div"];
+
+/// Checks if the attributes of a tag include `class` in their `class` attribute.
+fn has_class(attributes: &str, class: &str) -> bool {
+    let mut rest = attributes;
+    while let Some(i) = rest.find("class") {
+        let is_attribute = rest[..i]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+        rest = &rest[i + "class".len()..];
+        let Some(value) = rest.trim_start().strip_prefix('=').map(str::trim_start) else {
+            continue;
+        };
+        if !is_attribute {
+            continue;
+        }
+
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default(),
+            _ => value.split_whitespace().next().unwrap_or_default(),
+        };
+        return value.split_whitespace().any(|c| c == class);
+    }
+    false
+}
+
+/// Finds the next element with a diagram after `from`.
+///
+/// Returns the offset where its content starts and its tag. `lowercase` is the text with ASCII
+/// letters in lowercase, tags and attributes are case insensitive.
+fn next_diagram_element(lowercase: &str, mut from: usize) -> Option<(usize, &str)> {
+    while let Some(i) = lowercase[from..].find('<') {
+        let start = from + i + 1;
+        let rest = &lowercase[start..];
+        if rest.starts_with("!--") {
+            from = rest
+                .find("-->")
+                .map_or(lowercase.len(), |end| start + end + 3);
+            continue;
+        }
+
+        let end = rest.find('>')?;
+        let tag = &rest[..end];
+        let name_length = tag
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(tag.len());
+        let name = &tag[..name_length];
+        if DIAGRAM_TAGS.contains(&name) && has_class(&tag[name_length..], MERMAID_CLASS) {
+            return Some((start + end + 1, name));
+        }
+        from = start;
+    }
+    None
+}
+
+/// Builds the diagram inside `text[start..end]`.
+///
+/// The part of the first line before `start` belongs to the host, and so does the indentation
+/// of the closing tag when it's alone on its line.
+fn element_diagram(text: &str, start: usize, end: usize) -> DiagramSource {
+    let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    let first_line = text[..start].matches('\n').count() as u32;
+    let mut builder = DiagramBuilder::new(first_line, "");
+
+    let mut prefix = &text[line_start..start];
+    let mut end_prefix = "";
+    for line in text[start..end].split_inclusive('\n') {
+        if !line.ends_with('\n') && line.trim().is_empty() {
+            end_prefix = line;
+            break;
+        }
+        builder.push(prefix, line);
+        prefix = "";
+    }
+
+    builder.finish(end_prefix)
+}
+
+/// Finds every diagram of an HTML document.
+///
+/// Diagrams live inside `<pre class="mermaid">` and `<div class="mermaid">` elements, the way
+/// the Mermaid library finds them in a page. Character references like `&gt;` are not decoded,
+/// the content of the element is used as it's written.
+pub fn html_diagrams(text: &str) -> Vec<EmbeddedDiagram> {
+    html_sources(text)
+        .into_iter()
+        .map(DiagramSource::parse)
+        .collect()
+}
+
+/// The diagrams of [`html_diagrams`], before parsing them.
+pub(crate) fn html_sources(text: &str) -> Vec<DiagramSource> {
+    let lowercase = text.to_ascii_lowercase();
+    let mut diagrams = vec![];
+    let mut from = 0;
+
+    while let Some((start, tag)) = next_diagram_element(&lowercase, from) {
+        let closing = format!("</{}>", tag);
+        let end = lowercase[start..]
+            .find(&closing)
+            .map_or(text.len(), |i| start + i);
+
+        diagrams.push(element_diagram(text, start, end));
+        from = end;
+    }
+
+    diagrams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{jsonrpc::Position, mermaid::TextSpan};
+
+    #[test]
+    fn html_elements() {
+        let text = r#"<html>
+<body>
+  <!-- <pre class="mermaid">ignored</pre> -->
+  <pre class="code">flowchart TD</pre>
+  <PRE id="flow" class='diagram mermaid'>flowchart LR
+    A --> B
+  </PRE>
+  <div class=mermaid>
+    pie
+  </div>
+</body>
+</html>
+"#;
+
+        let diagrams = html_diagrams(text);
+        let texts: Vec<_> = diagrams.iter().map(|d| d.ast.cst.text()).collect();
+
+        assert_eq!(texts, vec!["flowchart LR\n    A --> B\n", "\n    pie\n"]);
+        assert_eq!(
+            diagrams[0].range(TextSpan::new(0, 9)).start,
+            Position {
+                line: 4,
+                character: 41
+            }
+        );
+        assert_eq!(
+            diagrams[0].range(TextSpan::new(25, 25)).start,
+            Position {
+                line: 6,
+                character: 2
+            }
+        );
+    }
+
+    #[test]
+    fn html_single_line_element() {
+        let diagrams = html_diagrams("<p>Flow</p><div class=\"mermaid\">graph TD; A-->B</div>");
+
+        assert_eq!(diagrams[0].ast.cst.text(), "graph TD; A-->B");
+        assert_eq!(
+            diagrams[0].range(TextSpan::new(0, 5)).start,
+            Position {
+                line: 0,
+                character: 32
+            }
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use super::{DiagramBuilder, DiagramSource, EmbeddedDiagram};
+
+/// The info string of the fences that contain diagrams.
+const MERMAID_INFO: &str = "mermaid";
+
+/// A block of a Markdown document that can contain a diagram.
+enum MarkdownBlock {
+    /// A code fence like ```` ```mermaid ```` or `~~~`.
+    Fence { fence: char, length: usize },
+    /// An Azure DevOps wiki block, `:::mermaid` closed by `:::`.
+    Colon,
+}
+
+impl MarkdownBlock {
+    /// Parses the line that opens a block, without its indentation.
+    ///
+    /// Returns the block and whether it contains a diagram.
+    fn open(line: &str) -> Option<(Self, bool)> {
+        let line = line.trim_end();
+        if let Some(info) = line.strip_prefix(":::") {
+            return (info.trim() == MERMAID_INFO).then_some((MarkdownBlock::Colon, true));
+        }
+
+        let fence = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+        let length = line.len() - line.trim_start_matches(fence).len();
+        if length < 3 {
+            return None;
+        }
+        let info = &line[length..];
+        // Backtick fences can't have backticks in their info string.
+        if fence == '`' && info.contains('`') {
+            return None;
+        }
+
+        let is_mermaid = info.split_whitespace().next() == Some(MERMAID_INFO);
+        Some((MarkdownBlock::Fence { fence, length }, is_mermaid))
+    }
+
+    /// Checks if a line, without its indentation, closes the block.
+    fn closes(&self, line: &str) -> bool {
+        let line = line.trim_end();
+        match self {
+            MarkdownBlock::Fence { fence, length } => {
+                line.len() >= *length && line.chars().all(|c| c == *fence)
+            }
+            MarkdownBlock::Colon => line == ":::",
+        }
+    }
+}
+
+/// The amount of spaces at the start of a line, up to `max`.
+fn indentation(line: &str, max: usize) -> usize {
+    line.bytes().take(max).take_while(|b| *b == b' ').count()
+}
+
+/// Finds every diagram of a Markdown document.
+///
+/// Diagrams live inside ```` ```mermaid ```` and `~~~mermaid` fences, or inside
+/// `:::mermaid` blocks of Azure DevOps wikis. The indentation of the fence is removed
+/// from the lines of its diagram, and a fence that is never closed runs until the end of the document.
+pub fn markdown_diagrams(text: &str) -> Vec<EmbeddedDiagram> {
+    markdown_sources(text)
+        .into_iter()
+        .map(DiagramSource::parse)
+        .collect()
+}
+
+/// The diagrams of [`markdown_diagrams`], before parsing them.
+pub(crate) fn markdown_sources(text: &str) -> Vec<DiagramSource> {
+    let mut diagrams = vec![];
+    // The open block, its indentation and its diagram.
+    let mut open: Option<(MarkdownBlock, usize, Option<DiagramBuilder>)> = None;
+
+    for (number, line) in text.split_inclusive('\n').enumerate() {
+        let number = number as u32;
+        match open.take() {
+            None => {
+                let indent = indentation(line, 4);
+                if indent > 3 {
+                    continue;
+                }
+                if let Some((block, is_mermaid)) = MarkdownBlock::open(&line[indent..]) {
+                    let builder =
+                        is_mermaid.then(|| DiagramBuilder::new(number + 1, &" ".repeat(indent)));
+                    open = Some((block, indent, builder));
+                }
+            }
+            Some((block, indent, builder)) => {
+                let line_indent = indentation(line, 4);
+                if line_indent <= 3 && block.closes(&line[line_indent..]) {
+                    if let Some(builder) = builder {
+                        let prefix = &line[..indentation(line, indent)];
+                        diagrams.push(builder.finish(prefix));
+                    }
+                    continue;
+                }
+
+                let builder = builder.map(|mut builder| {
+                    let prefix = indentation(line, indent);
+                    builder.push(&line[..prefix], &line[prefix..]);
+                    builder
+                });
+                open = Some((block, indent, builder));
+            }
+        }
+    }
+
+    if let Some((_, _, Some(builder))) = open {
+        diagrams.push(builder.finish(""));
+    }
+    diagrams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        host::{HostDocument, HostLanguage},
+        jsonrpc::Position,
+        mermaid::TextSpan,
+    };
+
+    fn texts(text: &str) -> Vec<String> {
+        markdown_diagrams(text)
+            .iter()
+            .map(|d| d.ast.cst.text().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn markdown_fences() {
+        let text = r#"# Diagrams
+
+```mermaid
+flowchart TD
+    A --> B
+```
+
+```rust
+// ```mermaid is not a fence here
+:::mermaid
+```
+
+~~~~ mermaid title
+sequenceDiagram
+~~~
+    Alice->>Bob: Hi
+~~~~
+
+  ```mermaid
+  pie
+    "A" : 1
+  ```
+"#;
+
+        assert_eq!(
+            texts(text),
+            vec![
+                "flowchart TD\n    A --> B\n",
+                "sequenceDiagram\n~~~\n    Alice->>Bob: Hi\n",
+                "pie\n  \"A\" : 1\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_azure_blocks() {
+        let text = "Intro\n::: mermaid\ngraph LR\n    A --> B\n:::\n\n```mermaid\nflowchart TD";
+
+        assert_eq!(texts(text), vec!["graph LR\n    A --> B\n", "flowchart TD"]);
+    }
+
+    #[test]
+    fn markdown_positions() {
+        let document = HostDocument::new(
+            HostLanguage::Markdown,
+            "# Title\n\n  ```mermaid\n  flowchart TD\n    A --> B\n  ```\n".to_string(),
+        );
+        let diagram = &document.diagrams[0];
+        let b = diagram.ast.cst.text().find('B').unwrap();
+
+        assert_eq!(
+            diagram.range(TextSpan::new(b, b + 1)).start,
+            Position {
+                line: 4,
+                character: 10
+            }
+        );
+
+        let (found, position) = document
+            .diagram_at(Position {
+                line: 4,
+                character: 10,
+            })
+            .unwrap();
+        assert_eq!(found.ast.cst.offset(position), b);
+        assert!(document
+            .diagram_at(Position {
+                line: 1,
+                character: 0
+            })
+            .is_none());
+    }
+}
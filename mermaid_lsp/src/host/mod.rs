@@ -0,0 +1,627 @@
+mod asciidoc;
+mod html;
+mod markdown;
+mod rst;
+mod rust;
+
+use std::path::Path;
+
+pub use asciidoc::*;
+pub use html::*;
+pub use markdown::*;
+pub use rst::*;
+pub use rust::*;
+
+use crate::{
+    jsonrpc::{Position, Range, TextEdit},
+    mermaid::{LineIndex, MermaidAST, TextSpan},
+};
+
+/// The extensions of the files that are considered Mermaid diagrams.
+pub const MERMAID_EXTENSIONS: [&str; 2] = ["mmd", "mermaid"];
+
+/// The language of a document that contains diagrams.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HostLanguage {
+    /// The whole document is a single diagram.
+    #[default]
+    Mermaid,
+    /// Diagrams live inside ```` ```mermaid ```` fences and `:::mermaid` blocks.
+    Markdown,
+    /// Diagrams live inside `<pre class="mermaid">` and `<div class="mermaid">` elements.
+    Html,
+    /// Diagrams live inside blocks with the `[mermaid]` style.
+    AsciiDoc,
+    /// Diagrams live inside the `.. mermaid::` directive of Sphinx.
+    ReStructuredText,
+    /// Diagrams live inside ```` ```mermaid ```` fences of `///` and `//!` doc comments.
+    Rust,
+}
+
+impl HostLanguage {
+    /// Finds the diagrams of a document written in this language, without parsing them.
+    pub(crate) fn diagram_sources(self, text: &str) -> Vec<DiagramSource> {
+        match self {
+            HostLanguage::Mermaid => vec![DiagramSource {
+                text: text.to_string(),
+                map: SourceMap::identity(),
+            }],
+            HostLanguage::Markdown => markdown_sources(text),
+            HostLanguage::Html => html_sources(text),
+            HostLanguage::AsciiDoc => asciidoc_sources(text),
+            HostLanguage::ReStructuredText => rst_sources(text),
+            HostLanguage::Rust => rust_sources(text),
+        }
+    }
+
+    /// The host of a `languageId` sent by the client, unknown languages are treated as Mermaid.
+    pub fn from_language_id(language_id: &str) -> Self {
+        match language_id {
+            "markdown" => HostLanguage::Markdown,
+            "html" => HostLanguage::Html,
+            "asciidoc" => HostLanguage::AsciiDoc,
+            "restructuredtext" => HostLanguage::ReStructuredText,
+            "rust" => HostLanguage::Rust,
+            _ => HostLanguage::Mermaid,
+        }
+    }
+
+    /// The host of a file based on its extension, `None` if it can't contain diagrams.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        if MERMAID_EXTENSIONS.contains(&extension) {
+            return Some(HostLanguage::Mermaid);
+        }
+
+        match extension {
+            "md" | "markdown" => Some(HostLanguage::Markdown),
+            "html" | "htm" => Some(HostLanguage::Html),
+            "adoc" | "asciidoc" => Some(HostLanguage::AsciiDoc),
+            "rst" => Some(HostLanguage::ReStructuredText),
+            "rs" => Some(HostLanguage::Rust),
+            _ => None,
+        }
+    }
+}
+
+/// Maps the positions of a diagram to the positions of the document that contains it.
+///
+/// A diagram is made of consecutive lines of its host, each line may lose a prefix
+/// that belongs to the host like the indentation of a fence.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// The line of the host where the diagram starts.
+    first_line: u32,
+
+    /// The prefix removed from each line of the diagram, no prefix was removed if empty.
+    prefixes: Vec<String>,
+
+    /// The prefix of the lines inserted into the diagram.
+    continuation: String,
+}
+
+impl SourceMap {
+    /// The map of a diagram that is the whole document.
+    pub fn identity() -> Self {
+        SourceMap::default()
+    }
+
+    fn prefix(&self, line: u32) -> &str {
+        self.prefixes.get(line as usize).map_or("", String::as_str)
+    }
+
+    fn prefix_width(&self, line: u32) -> u32 {
+        self.prefix(line).encode_utf16().count() as u32
+    }
+
+    /// The line of the host of a line of the diagram.
+    pub fn host_line(&self, line: u32) -> u32 {
+        self.first_line + line
+    }
+
+    /// Converts a position of the diagram into a position of the host.
+    pub fn host_position(&self, position: Position) -> Position {
+        Position {
+            line: self.host_line(position.line),
+            character: position.character + self.prefix_width(position.line),
+        }
+    }
+
+    /// Converts a range of the diagram into a range of the host.
+    pub fn host_range(&self, range: Range) -> Range {
+        Range {
+            start: self.host_position(range.start),
+            end: self.host_position(range.end),
+        }
+    }
+
+    /// Converts an edit of the diagram into an edit of the host, new lines get the prefix of the host.
+    pub fn host_edit(&self, edit: TextEdit) -> TextEdit {
+        let new_text = match self.continuation.is_empty() {
+            true => edit.new_text,
+            false => edit
+                .new_text
+                .replace('\n', &format!("\n{}", self.continuation)),
+        };
+
+        TextEdit {
+            range: self.host_range(edit.range),
+            new_text,
+        }
+    }
+
+    /// Converts a position of the host into a position of the diagram, `None` if it's outside of the diagram.
+    pub fn diagram_position(&self, position: Position) -> Option<Position> {
+        let line = position.line.checked_sub(self.first_line)?;
+        if !self.prefixes.is_empty() && line as usize >= self.prefixes.len() {
+            return None;
+        }
+
+        Some(Position {
+            line,
+            character: position.character.saturating_sub(self.prefix_width(line)),
+        })
+    }
+
+    /// The lines of the diagram between two lines of the host, both inclusive.
+    ///
+    /// `None` if the lines don't overlap with the diagram.
+    pub fn diagram_lines(&self, first: u32, last: u32) -> Option<(u32, u32)> {
+        let end = match self.prefixes.len() {
+            0 => u32::MAX,
+            len => self.first_line + len as u32 - 1,
+        };
+        if last < self.first_line || first > end {
+            return None;
+        }
+
+        Some((
+            first.max(self.first_line) - self.first_line,
+            last.min(end) - self.first_line,
+        ))
+    }
+}
+
+/// A diagram found inside a document.
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddedDiagram {
+    pub ast: MermaidAST,
+    pub map: SourceMap,
+}
+
+impl EmbeddedDiagram {
+    /// Converts a span of the diagram into a range of the host.
+    pub fn range(&self, span: TextSpan) -> Range {
+        self.map.host_range(self.ast.cst.range(span))
+    }
+
+    /// Converts a range of the host into a span of the diagram, `None` if the range doesn't touch the diagram.
+    ///
+    /// The range is clamped to the lines of the diagram.
+    pub fn span(&self, range: Range) -> Option<TextSpan> {
+        let (first, last) = self.map.diagram_lines(range.start.line, range.end.line)?;
+        let start = match self.map.diagram_position(range.start) {
+            Some(start) => start,
+            None => Position {
+                line: first,
+                character: 0,
+            },
+        };
+        let end = match self.map.diagram_position(range.end) {
+            Some(end) => end,
+            None => Position {
+                line: last,
+                character: u32::MAX,
+            },
+        };
+
+        Some(self.ast.cst.span(Range { start, end }))
+    }
+}
+
+/// Builds a diagram from the lines of its host.
+pub(crate) struct DiagramBuilder {
+    first_line: u32,
+    text: String,
+    prefixes: Vec<String>,
+    continuation: String,
+}
+
+impl DiagramBuilder {
+    /// Starts a diagram at `first_line` of the host, the lines inserted into it get the `continuation` prefix.
+    pub(crate) fn new(first_line: u32, continuation: &str) -> Self {
+        DiagramBuilder {
+            first_line,
+            text: String::new(),
+            prefixes: vec![],
+            continuation: continuation.to_string(),
+        }
+    }
+
+    /// Adds the next line of the host, `prefix` is the part of the line that belongs to the host.
+    pub(crate) fn push(&mut self, prefix: &str, content: &str) {
+        self.prefixes.push(prefix.to_string());
+        self.text.push_str(content);
+    }
+
+    /// Ends the diagram, `end_prefix` is the prefix of the host line right after the diagram.
+    pub(crate) fn finish(mut self, end_prefix: &str) -> DiagramSource {
+        if self.text.is_empty() || self.text.ends_with('\n') {
+            self.prefixes.push(end_prefix.to_string());
+        }
+
+        DiagramSource {
+            text: self.text,
+            map: SourceMap {
+                first_line: self.first_line,
+                prefixes: self.prefixes,
+                continuation: self.continuation,
+            },
+        }
+    }
+}
+
+/// The text of a diagram found inside a document, before parsing it.
+#[derive(Debug, Clone)]
+pub(crate) struct DiagramSource {
+    pub(crate) text: String,
+    pub(crate) map: SourceMap,
+}
+
+impl DiagramSource {
+    /// Parses the diagram from scratch.
+    pub(crate) fn parse(self) -> EmbeddedDiagram {
+        EmbeddedDiagram {
+            ast: MermaidAST::from_content(self.text),
+            map: self.map,
+        }
+    }
+
+    /// Parses the diagram again from a previous version of it.
+    ///
+    /// Only the syntax tree is updated incrementally, the typed AST is built again from the whole tree.
+    fn reparse(self, mut diagram: EmbeddedDiagram) -> EmbeddedDiagram {
+        let old = diagram.ast.cst.text();
+        if old != self.text {
+            let (start, old_end, new_end) = changed_text(old, &self.text);
+            let mut cst = std::mem::take(&mut diagram.ast.cst);
+            cst.edit(TextSpan::new(start, old_end), &self.text[start..new_end]);
+            diagram.ast = MermaidAST::from_cst(cst);
+        }
+
+        diagram.map = self.map;
+        diagram
+    }
+}
+
+/// The part of `old` replaced to get `new`, as its start and its end in both texts.
+fn changed_text(old: &str, new: &str) -> (usize, usize, usize) {
+    let mut start = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(start) {
+        start -= 1;
+    }
+
+    let mut suffix = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take(old.len().min(new.len()) - start)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(old.len() - suffix) {
+        suffix -= 1;
+    }
+
+    (start, old.len() - suffix, new.len() - suffix)
+}
+
+/// A document opened by the client with every diagram inside it.
+#[derive(Debug, Clone, Default)]
+pub struct HostDocument {
+    pub language: HostLanguage,
+
+    /// The whole text of the document.
+    pub text: String,
+
+    /// The diagrams of the document, in order.
+    pub diagrams: Vec<EmbeddedDiagram>,
+
+    /// The lines of `text`, Mermaid documents use the index of their syntax tree instead.
+    line_index: LineIndex,
+}
+
+impl HostDocument {
+    /// Finds and parses every diagram of a document.
+    pub fn new(language: HostLanguage, text: String) -> Self {
+        let diagrams = language
+            .diagram_sources(&text)
+            .into_iter()
+            .map(DiagramSource::parse)
+            .collect();
+
+        let line_index = match language {
+            HostLanguage::Mermaid => LineIndex::default(),
+            _ => LineIndex::new(&text),
+        };
+
+        HostDocument {
+            language,
+            text,
+            diagrams,
+            line_index,
+        }
+    }
+
+    /// A Mermaid document, the whole text is a single diagram.
+    pub fn mermaid(text: String) -> Self {
+        HostDocument::new(HostLanguage::Mermaid, text)
+    }
+
+    /// Replaces the text inside `range`, or the whole text if there's no range.
+    ///
+    /// The syntax tree of a Mermaid document is edited incrementally and its typed AST is built again
+    /// from it. Other hosts look for their diagrams again, the diagrams whose text didn't change keep
+    /// their AST and the one that was edited goes through the same steps. Diagrams are only parsed from
+    /// scratch when the edit adds or removes some.
+    pub fn edit(&mut self, range: Option<Range>, new_text: &str) {
+        let language = self.language;
+        let Some(range) = range else {
+            *self = HostDocument::new(language, new_text.to_string());
+            return;
+        };
+
+        let line_index = self.line_index();
+        let start = line_index.offset(&self.text, range.start);
+        let span = TextSpan::new(start, line_index.offset(&self.text, range.end).max(start));
+        self.text.replace_range(span.start..span.end, new_text);
+
+        match (language, self.diagrams.first_mut()) {
+            (HostLanguage::Mermaid, Some(diagram)) => {
+                let mut cst = std::mem::take(&mut diagram.ast.cst);
+                cst.edit(span, new_text);
+                diagram.ast = MermaidAST::from_cst(cst);
+            }
+            _ => {
+                self.line_index.edit(span, new_text);
+                let sources = language.diagram_sources(&self.text);
+                self.diagrams = update_diagrams(std::mem::take(&mut self.diagrams), sources);
+            }
+        }
+    }
+
+    /// The index of the lines of the whole document.
+    pub fn line_index(&self) -> &LineIndex {
+        match (self.language, self.diagrams.first()) {
+            (HostLanguage::Mermaid, Some(diagram)) => diagram.ast.cst.line_index(),
+            _ => &self.line_index,
+        }
+    }
+
+    /// The diagram that contains a position of the host, and the position inside the diagram.
+    pub fn diagram_at(&self, position: Position) -> Option<(&EmbeddedDiagram, Position)> {
+        self.diagrams
+            .iter()
+            .find_map(|d| Some((d, d.map.diagram_position(position)?)))
+    }
+}
+
+/// Pairs the diagrams found after an edit with the ones found before it.
+///
+/// The diagrams before and after the edited ones keep their AST, only their map changes.
+/// The edited ones are parsed again from their previous version, unless the edit changed
+/// how many there are, like when the delimiter of a fence is removed.
+fn update_diagrams(
+    mut old: Vec<EmbeddedDiagram>,
+    mut sources: Vec<DiagramSource>,
+) -> Vec<EmbeddedDiagram> {
+    let prefix = old
+        .iter()
+        .zip(sources.iter())
+        .take_while(|(d, s)| d.ast.cst.text() == s.text && d.map == s.map)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(sources[prefix..].iter().rev())
+        .take_while(|(d, s)| d.ast.cst.text() == s.text)
+        .count();
+
+    let after: Vec<_> = old.drain(old.len() - suffix..).collect();
+    let edited: Vec<_> = old.drain(prefix..).collect();
+    let after_sources = sources.split_off(sources.len() - suffix);
+    let edited_sources = sources.split_off(prefix);
+
+    match edited.len() == edited_sources.len() {
+        true => old.extend(
+            edited_sources
+                .into_iter()
+                .zip(edited)
+                .map(|(source, diagram)| source.reparse(diagram)),
+        ),
+        false => old.extend(edited_sources.into_iter().map(DiagramSource::parse)),
+    }
+    old.extend(
+        after
+            .into_iter()
+            .zip(after_sources)
+            .map(|(diagram, source)| source.reparse(diagram)),
+    );
+    old
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_map_positions() {
+        let mut builder = DiagramBuilder::new(3, "  ");
+        builder.push("  ", "flowchart TD\n");
+        builder.push("  ", "    A --> B\n");
+        let diagram = builder.finish(" ");
+
+        assert_eq!(
+            diagram.map.host_position(Position {
+                line: 1,
+                character: 4
+            }),
+            Position {
+                line: 4,
+                character: 6
+            }
+        );
+        assert_eq!(
+            diagram.map.diagram_position(Position {
+                line: 5,
+                character: 1
+            }),
+            Some(Position {
+                line: 2,
+                character: 0
+            })
+        );
+        assert_eq!(
+            diagram.map.diagram_position(Position {
+                line: 6,
+                character: 0
+            }),
+            None
+        );
+        assert_eq!(diagram.map.diagram_lines(0, 3), Some((0, 0)));
+        assert_eq!(diagram.map.diagram_lines(6, 9), None);
+    }
+
+    #[test]
+    fn source_map_edits() {
+        let mut builder = DiagramBuilder::new(1, "  ");
+        builder.push("  ", "flowchart TD\n");
+        let diagram = builder.finish("  ");
+
+        let edit = diagram.map.host_edit(TextEdit {
+            range: Range {
+                start: Position {
+                    line: 1,
+                    character: 0,
+                },
+                end: Position {
+                    line: 1,
+                    character: 0,
+                },
+            },
+            new_text: "    A\n".to_string(),
+        });
+
+        assert_eq!(
+            edit.range.start,
+            Position {
+                line: 2,
+                character: 2
+            }
+        );
+        assert_eq!(edit.new_text, "    A\n  ");
+    }
+
+    #[test]
+    fn host_document_edits() {
+        let mut document = HostDocument::new(
+            HostLanguage::Markdown,
+            "# Title\n\n```mermaid\nflowchart TD\n```\n".to_string(),
+        );
+        let position = Position {
+            line: 3,
+            character: 12,
+        };
+
+        document.edit(
+            Some(Range {
+                start: position,
+                end: position,
+            }),
+            "\n    A --> B",
+        );
+
+        assert_eq!(
+            document.text,
+            "# Title\n\n```mermaid\nflowchart TD\n    A --> B\n```\n"
+        );
+        assert_eq!(
+            document.diagrams[0].ast.cst.text(),
+            "flowchart TD\n    A --> B\n"
+        );
+    }
+
+    /// Applies an edit to a host and checks it matches finding and parsing its diagrams from scratch.
+    fn assert_host_edit(language: HostLanguage, text: &str, range: Range, new_text: &str) {
+        let mut document = HostDocument::new(language, text.to_string());
+        document.edit(Some(range), new_text);
+        let expected = HostDocument::new(language, document.text.clone());
+
+        assert_eq!(document.line_index, expected.line_index);
+        assert_eq!(document.diagrams.len(), expected.diagrams.len());
+        for (diagram, expected) in document.diagrams.iter().zip(expected.diagrams.iter()) {
+            assert_eq!(
+                diagram.ast.cst, expected.ast.cst,
+                "Edit {:?} of {:?}",
+                range, text
+            );
+            assert_eq!(diagram.map, expected.map, "Edit {:?} of {:?}", range, text);
+        }
+    }
+
+    fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+        Range {
+            start: Position {
+                line: start.0,
+                character: start.1,
+            },
+            end: Position {
+                line: end.0,
+                character: end.1,
+            },
+        }
+    }
+
+    #[test]
+    fn host_document_edits_match_new_document() {
+        let markdown = "# Title\n\n```mermaid\nflowchart TD\n    A --> B\n```\n\nText\n\n```mermaid\npie\n    \"A\" : 1\n```\n";
+        let edits = [
+            // Inside the first diagram, the second one moves down.
+            (range((4, 11), (4, 11)), "\n    B --> C"),
+            // Inside the second diagram.
+            (range((11, 7), (11, 8)), "Bé"),
+            // Outside of the diagrams.
+            (range((7, 0), (7, 4)), "More\ntext"),
+            // Removing the fence that closes the first diagram.
+            (range((5, 0), (6, 0)), ""),
+            // Opening a new fence before the first diagram.
+            (range((1, 0), (1, 0)), "```mermaid\nsequenceDiagram\n```\n"),
+            // Replacing everything between both diagrams.
+            (range((4, 4), (11, 4)), "X"),
+        ];
+        for (range, new_text) in edits {
+            assert_host_edit(HostLanguage::Markdown, markdown, range, new_text);
+        }
+
+        let rst = "Title\n=====\n\n.. mermaid::\n\n   flowchart TD\n      A --> B\n\nText\n";
+        assert_host_edit(
+            HostLanguage::ReStructuredText,
+            rst,
+            range((6, 13), (6, 13)),
+            "\n      B --> C",
+        );
+        assert_host_edit(
+            HostLanguage::ReStructuredText,
+            rst,
+            range((3, 0), (3, 2)),
+            "",
+        );
+
+        let html = "<pre class=\"mermaid\">flowchart LR\n  A --> B</pre>\n<div class=\"mermaid\">pie</div>\n";
+        assert_host_edit(HostLanguage::Html, html, range((1, 2), (1, 3)), "Start");
+        assert_host_edit(HostLanguage::Html, html, range((1, 9), (1, 15)), "");
+    }
+}
@@ -0,0 +1,123 @@
+use super::{DiagramBuilder, DiagramSource, EmbeddedDiagram};
+
+/// The directive that contains diagrams.
+const MERMAID_DIRECTIVE: &str = ".. mermaid::";
+
+/// The amount of spaces at the start of a line.
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Finds every diagram of a reStructuredText document.
+///
+/// Diagrams live inside the `.. mermaid::` directive of `sphinxcontrib-mermaid`. The content of
+/// the directive is every line indented deeper than it, after its options. A directive with an
+/// argument reads its diagram from another file, so it doesn't have a diagram of its own.
+pub fn rst_diagrams(text: &str) -> Vec<EmbeddedDiagram> {
+    rst_sources(text)
+        .into_iter()
+        .map(DiagramSource::parse)
+        .collect()
+}
+
+/// The diagrams of [`rst_diagrams`], before parsing them.
+pub(crate) fn rst_sources(text: &str) -> Vec<DiagramSource> {
+    let lines: Vec<_> = text.split_inclusive('\n').collect();
+    let mut diagrams = vec![];
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let indent = indentation(line);
+        i += 1;
+        let Some(argument) = line[indent..].trim_end().strip_prefix(MERMAID_DIRECTIVE) else {
+            continue;
+        };
+
+        let block = lines[i..]
+            .iter()
+            .position(|l| !l.trim().is_empty() && indentation(l) <= indent);
+        let block_end = block.map_or(lines.len(), |block| i + block);
+        let options = lines[i..block_end]
+            .iter()
+            .take_while(|l| l.trim_start().starts_with(':'))
+            .count();
+        let start = i + options;
+        i = block_end;
+        if !argument.trim().is_empty() {
+            continue;
+        }
+
+        let Some(first) = lines[start..block_end]
+            .iter()
+            .position(|l| !l.trim().is_empty())
+        else {
+            continue;
+        };
+        let start = start + first;
+        let end = lines[..block_end]
+            .iter()
+            .rposition(|l| !l.trim().is_empty())
+            .map_or(block_end, |last| last + 1);
+
+        let content_indent = indentation(lines[start]);
+        let strip = |line: &str| indentation(line).min(content_indent);
+        let mut builder = DiagramBuilder::new(start as u32, &" ".repeat(content_indent));
+        for line in &lines[start..end] {
+            let prefix = strip(line);
+            builder.push(&line[..prefix], &line[prefix..]);
+        }
+        let end_prefix = lines.get(end).map_or("", |line| &line[..strip(line)]);
+        diagrams.push(builder.finish(end_prefix));
+    }
+
+    diagrams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{jsonrpc::Position, mermaid::TextSpan};
+
+    #[test]
+    fn rst_directives() {
+        let text = r#"Diagrams
+========
+
+.. mermaid::
+   :caption: A flow
+
+   flowchart TD
+       A --> B
+
+   %% end of flow
+
+.. mermaid:: other.mmd
+
+Some text
+
+  .. mermaid::
+
+      sequenceDiagram
+"#;
+
+        let diagrams = rst_diagrams(text);
+        let texts: Vec<_> = diagrams.iter().map(|d| d.ast.cst.text()).collect();
+
+        assert_eq!(
+            texts,
+            vec![
+                "flowchart TD\n    A --> B\n\n%% end of flow\n",
+                "sequenceDiagram\n"
+            ]
+        );
+        assert_eq!(
+            diagrams[0].range(TextSpan::new(17, 18)).start,
+            Position {
+                line: 7,
+                character: 7
+            }
+        );
+        assert_eq!(diagrams[1].map.host_line(0), 17);
+    }
+}
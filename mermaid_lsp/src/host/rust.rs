@@ -0,0 +1,168 @@
+use super::{markdown_sources, DiagramSource, EmbeddedDiagram, SourceMap};
+
+/// The markers of the doc comments, outer and inner.
+const DOC_COMMENT_MARKERS: [&str; 2] = ["///", "//!"];
+
+/// Splits a line into its doc comment prefix and its Markdown.
+///
+/// The prefix is the indentation, the marker and the space after it. Returns the marker too,
+/// `None` if the line isn't a doc comment.
+fn doc_comment(line: &str) -> Option<(&'static str, usize)> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let marker = DOC_COMMENT_MARKERS
+        .into_iter()
+        .find(|m| rest.starts_with(m))?;
+    // `////` is a regular comment.
+    if rest[marker.len()..].starts_with('/') {
+        return None;
+    }
+
+    let space = rest[marker.len()..].starts_with(' ') as usize;
+    Some((marker, indent + marker.len() + space))
+}
+
+/// Moves a diagram found inside the Markdown of a doc comment into the Rust file.
+///
+/// `first_line` is the line of the file where the doc comment starts, and `prefixes` are the
+/// prefixes of its lines.
+fn into_file(mut diagram: DiagramSource, first_line: u32, prefixes: &[&str]) -> DiagramSource {
+    let map = &diagram.map;
+    let start = map.first_line as usize;
+    let prefix = |line: usize| prefixes.get(line).copied().unwrap_or_default();
+
+    diagram.map = SourceMap {
+        first_line: first_line + map.first_line,
+        prefixes: map
+            .prefixes
+            .iter()
+            .enumerate()
+            .map(|(i, p)| format!("{}{}", prefix(start + i), p))
+            .collect(),
+        continuation: format!("{}{}", prefix(start), map.continuation),
+    };
+    diagram
+}
+
+/// Finds every diagram of a Rust file.
+///
+/// Diagrams live inside ```` ```mermaid ```` fences of `///` and `//!` doc comments, as used by
+/// `aquamarine`. Consecutive doc comments of the same kind are read as a single Markdown document.
+pub fn rust_diagrams(text: &str) -> Vec<EmbeddedDiagram> {
+    rust_sources(text)
+        .into_iter()
+        .map(DiagramSource::parse)
+        .collect()
+}
+
+/// The diagrams of [`rust_diagrams`], before parsing them.
+pub(crate) fn rust_sources(text: &str) -> Vec<DiagramSource> {
+    let lines: Vec<_> = text.split_inclusive('\n').collect();
+    let mut diagrams = vec![];
+    let mut i = 0;
+
+    while i < lines.len() {
+        let Some((marker, _)) = doc_comment(lines[i]) else {
+            i += 1;
+            continue;
+        };
+
+        let first_line = i;
+        let mut prefixes = vec![];
+        let mut markdown = String::new();
+        while let Some((_, prefix)) = lines
+            .get(i)
+            .and_then(|l| doc_comment(l))
+            .filter(|(m, _)| *m == marker)
+        {
+            prefixes.push(&lines[i][..prefix]);
+            markdown.push_str(&lines[i][prefix..]);
+            i += 1;
+        }
+
+        diagrams.extend(
+            markdown_sources(&markdown)
+                .into_iter()
+                .map(|d| into_file(d, first_line as u32, &prefixes)),
+        );
+    }
+
+    diagrams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        jsonrpc::{Position, Range, TextEdit},
+        mermaid::TextSpan,
+    };
+
+    const TEXT: &str = r#"//! Crate docs.
+//!
+//! ```mermaid
+//! flowchart TD
+//!     A --> B
+//! ```
+/// Not the same block.
+/// ```mermaid
+/// pie
+////  "Ignored" : 1
+
+    /// Login flow
+    ///
+    /// ```mermaid
+    /// sequenceDiagram
+    ///     Alice->>Bob: Hi
+    /// ```
+    fn login() {}
+"#;
+
+    #[test]
+    fn rust_doc_comments() {
+        let diagrams = rust_diagrams(TEXT);
+        let texts: Vec<_> = diagrams.iter().map(|d| d.ast.cst.text()).collect();
+
+        assert_eq!(
+            texts,
+            vec![
+                "flowchart TD\n    A --> B\n",
+                "pie\n",
+                "sequenceDiagram\n    Alice->>Bob: Hi\n"
+            ]
+        );
+        assert_eq!(
+            diagrams[2].range(TextSpan::new(20, 25)).start,
+            Position {
+                line: 15,
+                character: 12
+            }
+        );
+    }
+
+    #[test]
+    fn rust_doc_comment_edits() {
+        let diagrams = rust_diagrams(TEXT);
+        let position = Position {
+            line: 1,
+            character: 0,
+        };
+
+        let edit = diagrams[2].map.host_edit(TextEdit {
+            range: Range {
+                start: position,
+                end: position,
+            },
+            new_text: "    Bob->>Alice: Hi\n".to_string(),
+        });
+
+        assert_eq!(
+            edit.range.start,
+            Position {
+                line: 15,
+                character: 8
+            }
+        );
+        assert_eq!(edit.new_text, "    Bob->>Alice: Hi\n    /// ");
+    }
+}
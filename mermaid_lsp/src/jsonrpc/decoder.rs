@@ -4,7 +4,18 @@ use std::io::BufReader;
 use log::debug;
 use log::error;
 
-use super::ClientMessage;
+use serde::Deserialize;
+
+use super::{ClientMessage, ClientPayload, ErrorCodes, LspId, ResponseError, ServerResponse};
+
+/// The header with the length of the body in bytes, it's required.
+const CONTENT_LENGTH: &str = "content-length";
+
+/// The header with the mime type and charset of the body, it's optional.
+const CONTENT_TYPE: &str = "content-type";
+
+/// The charsets a body can be encoded with, `utf8` is still sent by some clients.
+const SUPPORTED_CHARSETS: [&str; 2] = ["utf-8", "utf8"];
 
 pub struct LSPMessages<T: std::io::Read> {
     reader: BufReader<T>,
@@ -20,84 +31,230 @@ impl<T: std::io::Read> LSPMessages<T> {
 pub enum ParseJsonRPCMessageErrors {
     FailedToReadHeader(std::io::Error),
     IncorrectHeaderFormat,
+    MissingContentLength,
+    UnsupportedCharset(String),
     FailedToReadBody(std::io::Error),
+    /// The stream ended before the whole body was read, the client is gone.
+    TruncatedBody {
+        expected: usize,
+        read: usize,
+    },
     ContentLengthNotANumber(std::num::ParseIntError),
+    /// The body isn't valid JSON.
     FailedToParseBody(serde_json::Error),
+    /// The body is valid JSON but not a request or a notification, `id` is set if it had one.
+    InvalidMessage {
+        id: Option<LspId>,
+        error: serde_json::Error,
+    },
 }
 
-impl<T: std::io::Read> Iterator for LSPMessages<T> {
-    type Item = Result<ClientMessage, ParseJsonRPCMessageErrors>;
+impl ParseJsonRPCMessageErrors {
+    /// The id of the request that couldn't be read, if there was one.
+    ///
+    /// Errors without an id belong to messages that couldn't be read at all, they are answered with a `null` id.
+    pub fn id(&self) -> Option<&LspId> {
+        match self {
+            ParseJsonRPCMessageErrors::InvalidMessage { id, .. } => id.as_ref(),
+            _ => None,
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        debug!("[LSPMessages iterator] Next function called!");
-        let mut header_bytes = vec![];
+    /// The error response for the message, `None` if it can't be answered.
+    ///
+    /// Bodies that aren't JSON are answered with a `null` id, invalid messages with their own id or a `null`
+    /// one, like each element of the batch `[1, 2, 3]`. Broken frames can't be answered.
+    /// Notifications are never invalid, any object with a string `method` and no `id` is one.
+    pub fn response(&self) -> Option<ServerResponse> {
+        let error = match self {
+            ParseJsonRPCMessageErrors::FailedToParseBody(error) => ResponseError::new(
+                ErrorCodes::ParseError,
+                format!("The message isn't valid JSON: {}", error),
+            ),
+            ParseJsonRPCMessageErrors::InvalidMessage { error, .. } => ResponseError::new(
+                ErrorCodes::InvalidRequest,
+                format!("The message isn't a valid request: {}", error),
+            ),
+            _ => return None,
+        };
 
-        if let Err(e) = self
-            .reader
-            .read_until(b'\n', &mut header_bytes)
-            .map_err(ParseJsonRPCMessageErrors::FailedToReadHeader)
-        {
-            error!(
-                "[LSPMessages iterator] Failed to read LSP message header! {:?}",
-                e
-            );
-            return Some(Err(e));
+        Some(ServerResponse::new_error(self.id().cloned(), error))
+    }
+}
+
+/// Reads a message from a JSON value, keeping its id if it isn't a valid message.
+fn parse_message(value: serde_json::Value) -> Result<ClientMessage, ParseJsonRPCMessageErrors> {
+    let id = value.get("id").and_then(|id| LspId::deserialize(id).ok());
+
+    serde_json::from_value(value)
+        .map_err(|error| ParseJsonRPCMessageErrors::InvalidMessage { id, error })
+}
+
+/// Reads a message or a batch from its body, telling apart bodies that aren't JSON from JSON that isn't a message.
+///
+/// Each message of a batch is read on its own, so a single invalid message doesn't fail the rest.
+pub fn parse_body(body: &[u8]) -> Result<ClientPayload, ParseJsonRPCMessageErrors> {
+    let value: serde_json::Value =
+        serde_json::from_slice(body).map_err(ParseJsonRPCMessageErrors::FailedToParseBody)?;
+
+    match value {
+        serde_json::Value::Array(values) => Ok(ClientPayload::Batch(
+            values.into_iter().map(parse_message).collect(),
+        )),
+        value => parse_message(value).map(ClientPayload::Single),
+    }
+}
+
+/// Splits a header line like `Content-Length: 52` into its lowercase name and its value.
+fn split_header(line: &str) -> Option<(String, &str)> {
+    let (name, value) = line.split_once(':')?;
+    let is_token = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+
+    is_token.then(|| (name.to_ascii_lowercase(), value.trim()))
+}
+
+/// Validates the `charset` parameter of a `Content-Type` like `application/vscode-jsonrpc; charset=utf-8`.
+fn check_charset(content_type: &str) -> Result<(), ParseJsonRPCMessageErrors> {
+    let charset = content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|parameter| parameter.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, value)| value.trim().trim_matches('"').to_ascii_lowercase());
+
+    match charset {
+        Some(charset) if !SUPPORTED_CHARSETS.contains(&charset.as_str()) => {
+            Err(ParseJsonRPCMessageErrors::UnsupportedCharset(charset))
         }
-        self.reader.consume(2); // The remaining \r\n that separate the header and the body
-        debug!(
-            "[LSPMessages iterator] Header bytes received! {:?}",
-            String::from_utf8_lossy(&header_bytes)
-        );
+        _ => Ok(()),
+    }
+}
 
-        let last_header_part = match header_bytes
-            .split(|b| *b == b':')
-            .last()
-            .ok_or(ParseJsonRPCMessageErrors::IncorrectHeaderFormat)
-        {
-            Ok(v) => v,
-            Err(e) => {
-                error!("[LSPMessages iterator] Invalid header format!");
-                return Some(Err(e));
+/// Gets the length of the body from the headers of a message, in any order and case.
+///
+/// Unknown headers are ignored as the spec allows new ones to be added.
+pub fn parse_headers(headers: &[String]) -> Result<usize, ParseJsonRPCMessageErrors> {
+    let mut content_length = None;
+    for header in headers {
+        let (name, value) =
+            split_header(header).ok_or(ParseJsonRPCMessageErrors::IncorrectHeaderFormat)?;
+        match name.as_str() {
+            CONTENT_LENGTH => {
+                let length = value
+                    .parse()
+                    .map_err(ParseJsonRPCMessageErrors::ContentLengthNotANumber)?;
+                content_length = Some(length);
+            }
+            CONTENT_TYPE => check_charset(value)?,
+            _ => debug!("[LSPMessages iterator] Ignoring header {:?}", header),
+        }
+    }
+
+    content_length.ok_or(ParseJsonRPCMessageErrors::MissingContentLength)
+}
+
+impl<T: std::io::Read> LSPMessages<T> {
+    /// Reads the header lines of the next message, up to the empty line that ends them.
+    ///
+    /// Empty lines before the headers are skipped. A line that isn't a header but contains a
+    /// `Content-Length` is the leftover of a broken message glued to the next one, so the headers
+    /// start again from there. Returns `None` if the stream ends before the empty line, as a
+    /// message without a body can't be answered anyway.
+    fn read_headers(&mut self) -> Result<Option<Vec<String>>, ParseJsonRPCMessageErrors> {
+        let mut headers: Vec<String> = vec![];
+        loop {
+            let mut line = vec![];
+            let read = self
+                .reader
+                .read_until(b'\n', &mut line)
+                .map_err(ParseJsonRPCMessageErrors::FailedToReadHeader)?;
+            if read == 0 {
+                if !headers.is_empty() {
+                    debug!(
+                        "[LSPMessages iterator] The stream ended inside the headers {:?}",
+                        headers
+                    );
+                }
+                return Ok(None);
             }
-        };
 
-        // Ignoring [ ]<NUMBER>[\r\n]
-        let body_length_bytes = &last_header_part[1..(last_header_part.len() - 2)];
-        debug!(
-            "[LSPMessages iterator] Body length as a string! - Original: {:?} - Cut: {:?}",
-            String::from_utf8_lossy(last_header_part),
-            String::from_utf8_lossy(body_length_bytes)
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                match headers.is_empty() {
+                    true => continue,
+                    false => return Ok(Some(headers)),
+                }
+            }
+
+            let resync = line
+                .to_ascii_lowercase()
+                .find(CONTENT_LENGTH)
+                .filter(|start| *start > 0 && split_header(line).is_none());
+            match resync {
+                Some(start) => {
+                    debug!(
+                        "[LSPMessages iterator] Skipping {:?} before the next message",
+                        &line[..start]
+                    );
+                    headers = vec![line[start..].to_string()];
+                }
+                None => headers.push(line.to_string()),
+            }
+        }
+    }
+
+    /// Skips the body of a message that can't be read, so the next one starts at its headers.
+    fn skip_body(&mut self, length: usize) {
+        let skipped = std::io::copy(
+            &mut (&mut self.reader).take(length as u64),
+            &mut std::io::sink(),
         );
-        let body_length_string = match String::from_utf8(body_length_bytes.to_vec())
-            .map_err(|_| ParseJsonRPCMessageErrors::IncorrectHeaderFormat)
-        {
-            Ok(v) => v,
+        debug!("[LSPMessages iterator] Skipped body {:?}", skipped);
+    }
+}
+
+impl<T: std::io::Read> Iterator for LSPMessages<T> {
+    type Item = Result<ClientPayload, ParseJsonRPCMessageErrors>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        debug!("[LSPMessages iterator] Next function called!");
+
+        let headers = match self.read_headers() {
+            Ok(Some(headers)) => headers,
+            Ok(None) => return None,
             Err(e) => {
-                error!("[LSPMessages iterator] Invalid UTF8-bytes were supplied to header!");
+                error!(
+                    "[LSPMessages iterator] Failed to read LSP message header! {:?}",
+                    e
+                );
                 return Some(Err(e));
             }
         };
+        debug!("[LSPMessages iterator] Headers received! {:?}", headers);
 
-        let body_length: usize = match body_length_string
-            .parse()
-            .map_err(ParseJsonRPCMessageErrors::ContentLengthNotANumber)
-        {
+        let body_length = match parse_headers(&headers) {
             Ok(v) => v,
-            Err(err) => {
-                error!(
-                    "[LSPMessages iterator] The content length is not a valid number! {:?}",
-                    err
-                );
-
-                return Some(Err(err));
+            Err(e) => {
+                error!("[LSPMessages iterator] Invalid headers! {:?}", e);
+                // The body can still be skipped if its length is known.
+                if let Some(length) = headers
+                    .iter()
+                    .filter_map(|h| split_header(h))
+                    .find(|(name, _)| name == CONTENT_LENGTH)
+                    .and_then(|(_, value)| value.parse().ok())
+                {
+                    self.skip_body(length);
+                }
+                return Some(Err(e));
             }
         };
         debug!("[LSPMessages iterator] Body length: {}", body_length);
 
-        let mut body_bytes = vec![0u8; body_length];
-        if let Err(e) = self
-            .reader
-            .read_exact(&mut body_bytes)
+        let mut body_bytes = vec![];
+        if let Err(e) = (&mut self.reader)
+            .take(body_length as u64)
+            .read_to_end(&mut body_bytes)
             .map_err(ParseJsonRPCMessageErrors::FailedToReadBody)
         {
             error!(
@@ -106,10 +263,19 @@ impl<T: std::io::Read> Iterator for LSPMessages<T> {
             );
             return Some(Err(e));
         }
+        if body_bytes.len() < body_length {
+            let e = ParseJsonRPCMessageErrors::TruncatedBody {
+                expected: body_length,
+                read: body_bytes.len(),
+            };
+            error!(
+                "[LSPMessages iterator] The message body was cut short! {:?}",
+                e
+            );
+            return Some(Err(e));
+        }
 
-        let body = match serde_json::from_slice(&body_bytes)
-            .map_err(ParseJsonRPCMessageErrors::FailedToParseBody)
-        {
+        let body = match parse_body(&body_bytes) {
             Ok(v) => v,
             Err(e) => {
                 error!(
@@ -125,3 +291,178 @@ impl<T: std::io::Read> Iterator for LSPMessages<T> {
         Some(Ok(body))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = r#"{"jsonrpc":"2.0","method":"exit"}"#;
+
+    fn messages(input: &str) -> Vec<Result<ClientPayload, ParseJsonRPCMessageErrors>> {
+        LSPMessages::new(BufReader::new(input.as_bytes())).collect()
+    }
+
+    #[test]
+    fn decode_headers_in_any_order() {
+        let input = format!(
+            "content-type: application/vscode-jsonrpc; charset=utf-8\r\nCONTENT-LENGTH: {}\r\n\r\n{}\
+             Content-Length:{}\nX-Custom: 1\n\n{}",
+            BODY.len(),
+            BODY,
+            BODY.len(),
+            BODY
+        );
+
+        let messages = messages(&input);
+
+        assert_eq!(messages.len(), 2);
+        assert!(messages.iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn decode_rejects_invalid_headers() {
+        let headers =
+            |h: &[&str]| parse_headers(&h.iter().map(|h| h.to_string()).collect::<Vec<_>>());
+
+        assert!(matches!(
+            headers(&["Content-Type: application/vscode-jsonrpc; charset=latin1", "Content-Length: 2"]),
+            Err(ParseJsonRPCMessageErrors::UnsupportedCharset(c)) if c == "latin1"
+        ));
+        assert!(matches!(
+            headers(&["Content-Type: application/vscode-jsonrpc"]),
+            Err(ParseJsonRPCMessageErrors::MissingContentLength)
+        ));
+        assert!(matches!(
+            headers(&["Content-Length: two"]),
+            Err(ParseJsonRPCMessageErrors::ContentLengthNotANumber(_))
+        ));
+        assert!(matches!(
+            headers(&["Content Length 2"]),
+            Err(ParseJsonRPCMessageErrors::IncorrectHeaderFormat)
+        ));
+        assert_eq!(
+            headers(&["Content-Length: 2", "content-type: x; charset=\"UTF-8\""]).unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn decode_recovers_after_malformed_messages() {
+        let input = format!(
+            "Content-Length: 2\r\nContent-Type: text/plain; charset=latin1\r\n\r\n{{}}\
+             Content-Length: nope\r\n\r\n{}\
+             Content-Length: {}\r\n\r\n{}",
+            BODY,
+            BODY.len(),
+            BODY
+        );
+
+        let messages = messages(&input);
+
+        assert!(matches!(
+            messages[0],
+            Err(ParseJsonRPCMessageErrors::UnsupportedCharset(_))
+        ));
+        assert!(matches!(
+            messages[1],
+            Err(ParseJsonRPCMessageErrors::ContentLengthNotANumber(_))
+        ));
+        assert!(messages[2].is_ok());
+        assert_eq!(messages.len(), 3);
+    }
+
+    #[test]
+    fn decode_end_of_stream() {
+        assert!(messages("").is_empty());
+        assert!(messages("\r\n").is_empty());
+        assert!(messages("Content-Length: 2\r\n").is_empty());
+
+        let input = format!("Content-Length: {}\r\n\r\n{}", BODY.len() + 10, BODY);
+        let messages = messages(&input);
+
+        assert_eq!(messages.len(), 1);
+        assert!(matches!(
+            messages[0],
+            Err(ParseJsonRPCMessageErrors::TruncatedBody { expected, read }) if expected == BODY.len() + 10 && read == BODY.len()
+        ));
+    }
+
+    #[test]
+    fn decode_invalid_bodies() {
+        assert!(matches!(
+            parse_body(b"{\"jsonrpc\": \"2.0\", \"id\": 1,"),
+            Err(ParseJsonRPCMessageErrors::FailedToParseBody(_))
+        ));
+
+        let invalid = parse_body(br#"{"jsonrpc": "2.0", "id": "a", "method": 1}"#).unwrap_err();
+        assert_eq!(invalid.id(), Some(&LspId::String("a".into())));
+
+        let invalid = parse_body(br#"{"jsonrpc": "2.0", "method": 1}"#).unwrap_err();
+        assert!(matches!(
+            invalid,
+            ParseJsonRPCMessageErrors::InvalidMessage { id: None, .. }
+        ));
+        assert!(invalid.response().is_some());
+
+        let invalid =
+            parse_body(br#"{"jsonrpc": "2.0", "id": true, "method": "shutdown"}"#).unwrap_err();
+        assert_eq!(invalid.id(), None);
+        assert!(matches!(
+            invalid.response(),
+            Some(ServerResponse::Error { error, .. }) if error.code() == ErrorCodes::InvalidRequest as i32
+        ));
+    }
+
+    #[test]
+    fn decode_batches() {
+        let batch = parse_body(
+            br#"[
+                {"jsonrpc": "2.0", "id": 1, "method": "shutdown"},
+                {"jsonrpc": "2.0", "method": "exit"},
+                {"jsonrpc": "2.0", "id": 2, "method": 2},
+                1
+            ]"#,
+        );
+
+        let Ok(ClientPayload::Batch(messages)) = batch else {
+            panic!("{:?}", batch);
+        };
+        assert_eq!(messages.len(), 4);
+        assert!(matches!(messages[0], Ok(ClientMessage::Request { .. })));
+        assert!(matches!(
+            messages[1],
+            Ok(ClientMessage::Notification { .. })
+        ));
+        assert_eq!(
+            messages[2].as_ref().unwrap_err().id(),
+            Some(&LspId::from(2))
+        );
+        assert!(messages[3].is_err());
+        assert!(matches!(
+            parse_body(b"[]"),
+            Ok(ClientPayload::Batch(messages)) if messages.is_empty()
+        ));
+    }
+
+    #[test]
+    fn decode_batch_of_invalid_messages() {
+        let Ok(ClientPayload::Batch(messages)) = parse_body(b"[1, 2, 3]") else {
+            panic!("The batch should be read!");
+        };
+
+        let responses: Vec<_> = messages
+            .iter()
+            .map(|m| serde_json::to_value(m.as_ref().unwrap_err().response()).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 3);
+        for response in responses {
+            assert_eq!(response["id"], serde_json::Value::Null);
+            assert_eq!(response["error"]["code"], ErrorCodes::InvalidRequest as i32);
+        }
+
+        let Ok(ClientPayload::Batch(messages)) = parse_body(br#"[{"foo": 1}]"#) else {
+            panic!("The batch should be read!");
+        };
+        assert!(messages[0].as_ref().unwrap_err().response().is_some());
+    }
+}
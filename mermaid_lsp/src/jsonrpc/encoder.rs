@@ -1,17 +1,78 @@
-use crate::jsonrpc::ServerResponse;
+use std::io::Write;
+
+use serde::Serialize;
 
 #[derive(Debug)]
 pub enum EncodeErrors {
     MessageSerializationError(serde_json::Error),
+    FailedToWrite(std::io::Error),
 }
 
-/// Encodes a `ServerResponse` according to the LSP spec.
-pub fn encode_message(msg: ServerResponse) -> Result<String, EncodeErrors> {
+/// Encodes a message sent by the server, like a `ServerResponse`, according to the LSP spec.
+pub fn encode_message<T: Serialize>(msg: T) -> Result<String, EncodeErrors> {
     let body = serde_json::to_string(&msg).map_err(EncodeErrors::MessageSerializationError)?;
-    let content_length = body.len();
+    Ok(frame(&body))
+}
+
+/// Adds the headers of the LSP spec to the JSON body of a message.
+fn frame(body: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+/// Writes the messages of the server to any output, like STDOUT or a socket.
+///
+/// The output is flushed after each message, so the client gets it right away.
+pub struct LSPWriter<T: Write> {
+    writer: T,
+}
+
+impl<T: Write> LSPWriter<T> {
+    pub fn new(writer: T) -> Self {
+        LSPWriter { writer }
+    }
+
+    /// Encodes a message and writes it.
+    pub fn send<M: Serialize>(&mut self, msg: M) -> Result<(), EncodeErrors> {
+        let message = encode_message(msg)?;
+        self.write_framed(&message)
+    }
+
+    /// Writes a message that was already serialized into JSON, with its headers.
+    pub fn write_body(&mut self, body: &str) -> Result<(), EncodeErrors> {
+        self.write_framed(&frame(body))
+    }
+
+    fn write_framed(&mut self, message: &str) -> Result<(), EncodeErrors> {
+        self.writer
+            .write_all(message.as_bytes())
+            .and_then(|_| self.writer.flush())
+            .map_err(EncodeErrors::FailedToWrite)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn lsp_writer() {
+        let mut writer = LSPWriter::new(vec![]);
+        writer.send(json!({ "id": 1 })).unwrap();
+        writer.write_body("{}").unwrap();
+
+        assert_eq!(
+            String::from_utf8(writer.writer).unwrap(),
+            "Content-Length: 8\r\n\r\n{\"id\":1}Content-Length: 2\r\n\r\n{}"
+        );
 
-    Ok(format!(
-        "Content-Length: {}\r\n\r\n{}",
-        content_length, body
-    ))
+        // A slice can't grow, like a stream the client closed.
+        let mut full = [0u8; 4];
+        let mut closed = LSPWriter::new(&mut full[..]);
+        assert!(matches!(
+            closed.send(json!({ "id": 1 })),
+            Err(EncodeErrors::FailedToWrite(_))
+        ));
+    }
 }
@@ -1,23 +1,44 @@
 mod decoder;
 mod encoder;
 mod error_codes;
+mod pending;
+#[cfg(feature = "server")]
+mod transport;
+#[cfg(feature = "websocket")]
+mod websocket;
 
 use std::fmt::Display;
 
 pub use decoder::*;
 pub use encoder::*;
 pub use error_codes::*;
-use serde::{Deserialize, Serialize};
+pub use pending::*;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+#[cfg(feature = "server")]
+pub use transport::*;
+#[cfg(feature = "websocket")]
+pub use websocket::*;
 
 /// The JSON RPC version currently used
 pub const JSON_RPC_VERSION: &str = "2.0";
 
-/// A request/response id, it can be either a string or an integer
-#[derive(Debug, Serialize, Deserialize)]
+/// A request/response id, it can be a string, a number or `null`.
+///
+/// The LSP only sends strings and integers, but JSON-RPC also allows `null`, which is
+/// what the server answers with when it couldn't read the id of a request. Numbers are
+/// kept as they were sent, so responses echo ids outside of `i64` or with fractions too.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum LspId {
     String(String),
-    Integer(i32),
+    Number(serde_json::Number),
+    Null,
+}
+
+impl From<i64> for LspId {
+    fn from(id: i64) -> Self {
+        LspId::Number(id.into())
+    }
 }
 
 // Display implementation for LspId, mainly for logs.
@@ -25,26 +46,85 @@ impl Display for LspId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             LspId::String(s) => f.write_str(s),
-            LspId::Integer(i) => f.write_fmt(format_args!("{}", i)),
+            LspId::Number(n) => f.write_fmt(format_args!("{}", n)),
+            LspId::Null => f.write_str("null"),
         }
     }
 }
 
 /// Represents the message an LSP client sends to this server
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
+///
+/// The kind of message depends on the fields it has: an `id` and a `method` make a request,
+/// only a `method` makes a notification, and an `id` with a `result` or an `error` makes a response.
+#[derive(Debug)]
 pub enum ClientMessage {
     Request {
         id: LspId,
         method: String,
         params: Option<serde_json::Value>,
     },
+    /// The successful answer to a `ServerRequest`.
+    Response {
+        id: LspId,
+        result: serde_json::Value,
+    },
+    /// The answer to a `ServerRequest` that failed.
+    ErrorResponse { id: LspId, error: ResponseError },
     Notification {
         method: String,
         params: Option<serde_json::Value>,
     },
 }
 
+impl<'de> Deserialize<'de> for ClientMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut message = serde_json::Map::deserialize(deserializer)?;
+        let id = message
+            .remove("id")
+            .map(LspId::deserialize)
+            .transpose()
+            .map_err(D::Error::custom)?;
+        let method = message
+            .remove("method")
+            .map(String::deserialize)
+            .transpose()
+            .map_err(D::Error::custom)?;
+        let params = message.remove("params").filter(|p| !p.is_null());
+
+        match (id, method) {
+            (Some(id), Some(method)) => Ok(ClientMessage::Request { id, method, params }),
+            (None, Some(method)) => Ok(ClientMessage::Notification { method, params }),
+            (Some(id), None) => match (message.remove("result"), message.remove("error")) {
+                (Some(result), None) => Ok(ClientMessage::Response { id, result }),
+                (None, Some(error)) => Ok(ClientMessage::ErrorResponse {
+                    id,
+                    error: ResponseError::deserialize(error).map_err(D::Error::custom)?,
+                }),
+                _ => Err(D::Error::custom(
+                    "a response needs either a `result` or an `error`",
+                )),
+            },
+            (None, None) => Err(D::Error::custom("a message needs an `id` or a `method`")),
+        }
+    }
+}
+
+/// The params of a `$/cancelRequest` notification.
+#[derive(Debug, Deserialize)]
+pub struct CancelParams {
+    /// The id of the request to cancel.
+    pub id: LspId,
+}
+
+/// Represents the body of a frame sent by the client, a single message or a JSON-RPC batch of them.
+#[derive(Debug)]
+pub enum ClientPayload {
+    Single(ClientMessage),
+    /// The messages of a batch, each one read on its own. The responses to its requests are sent back
+    /// together inside an array.
+    Batch(Vec<Result<ClientMessage, ParseJsonRPCMessageErrors>>),
+}
+
 /// Represents the response the server sends to a `ClientMessage`
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
@@ -81,8 +161,53 @@ impl ServerResponse {
     }
 }
 
-/// Represents a response that signals an error
+/// Represents a request the server sends to the client, its response comes back as a `ClientMessage`.
 #[derive(Debug, Serialize)]
+pub struct ServerRequest {
+    jsonrpc: String,
+    id: LspId,
+    method: String,
+    params: serde_json::Value,
+}
+
+impl ServerRequest {
+    /// Creates a new `ServerRequest` for the given method.
+    pub fn new(id: LspId, method: &str, params: serde_json::Value) -> Self {
+        ServerRequest {
+            jsonrpc: JSON_RPC_VERSION.into(),
+            id,
+            method: method.into(),
+            params,
+        }
+    }
+
+    /// The id the response to this request will have.
+    pub fn id(&self) -> &LspId {
+        &self.id
+    }
+}
+
+/// Represents a notification the server sends to the client, it doesn't expect a response.
+#[derive(Debug, Serialize)]
+pub struct ServerNotification {
+    jsonrpc: String,
+    method: String,
+    params: serde_json::Value,
+}
+
+impl ServerNotification {
+    /// Creates a new `ServerNotification` for the given method.
+    pub fn new(method: &str, params: serde_json::Value) -> Self {
+        ServerNotification {
+            jsonrpc: JSON_RPC_VERSION.into(),
+            method: method.into(),
+            params,
+        }
+    }
+}
+
+/// Represents a response that signals an error
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ResponseError {
     /// The code of the errror according to `ErrorCodes`
     code: i32,
@@ -91,6 +216,7 @@ pub struct ResponseError {
     message: String,
 
     /// Optional data to supply context for the given error
+    #[serde(default)]
     data: Option<serde_json::Value>,
 }
 
@@ -109,6 +235,16 @@ impl ResponseError {
         self.data = data;
         self
     }
+
+    /// The code of the error, one of `ErrorCodes` unless it comes from the client.
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+
+    /// The message of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
 }
 
 /// An item to transfer a text document from the client to the server.
@@ -128,3 +264,135 @@ pub struct TextDocumentItem {
     /// The content of the opened text document.
     pub text: String,
 }
+
+/// Text documents are identified using a URI.
+#[derive(Debug, Deserialize)]
+pub struct TextDocumentIdentifier {
+    /// The text document's URI.
+    pub uri: String,
+}
+
+/// An identifier to denote a specific version of a text document.
+#[derive(Debug, Deserialize)]
+pub struct VersionedTextDocumentIdentifier {
+    /// The text document's URI.
+    pub uri: String,
+
+    /// The version number of this document.
+    ///
+    /// The version number of a document will increase after each change,
+    /// including undo/redo. The number doesn't need to be consecutive.
+    pub version: i32,
+}
+
+/// Position in a text document expressed as zero-based line and zero-based character offset.
+/// The character offset is measured in UTF-16 code units, the default encoding of the LSP.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Position {
+    /// Line position in a document (zero-based).
+    pub line: u32,
+
+    /// Character offset on a line in a document (zero-based).
+    pub character: u32,
+}
+
+/// A range in a text document expressed as (zero-based) start and end positions.
+/// The end position is exclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Range {
+    /// The range's start position.
+    pub start: Position,
+
+    /// The range's end position.
+    pub end: Position,
+}
+
+/// Represents a location inside a resource, such as a line inside a text file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    pub uri: String,
+    pub range: Range,
+}
+
+/// A textual edit applicable to a text document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextEdit {
+    /// The range of the text document to be manipulated. To insert
+    /// text into a document create a range where start === end.
+    pub range: Range,
+
+    /// The string to be inserted. For delete operations use an
+    /// empty string.
+    #[serde(rename = "newText")]
+    pub new_text: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn lsp_ids() {
+        let id = |value| serde_json::from_value::<LspId>(value).unwrap();
+
+        assert_eq!(id(json!("a")), LspId::String("a".into()));
+        assert_eq!(id(json!(i64::MAX)), LspId::from(i64::MAX));
+        assert_eq!(id(json!(-1)), LspId::from(-1));
+        assert_eq!(id(json!(null)), LspId::Null);
+        assert_eq!(serde_json::to_value(LspId::Null).unwrap(), json!(null));
+        for number in [json!(u64::MAX), json!(2.5)] {
+            assert_eq!(serde_json::to_value(id(number.clone())).unwrap(), number);
+        }
+        assert!(serde_json::from_value::<LspId>(json!(true)).is_err());
+    }
+
+    #[test]
+    fn client_messages() {
+        let message = |value| serde_json::from_value::<ClientMessage>(value).unwrap();
+
+        assert!(matches!(
+            message(json!({ "jsonrpc": "2.0", "id": null, "method": "shutdown" })),
+            ClientMessage::Request {
+                id: LspId::Null,
+                ..
+            }
+        ));
+        assert!(matches!(
+            message(json!({ "jsonrpc": "2.0", "id": 4294967296_i64, "method": "shutdown" })),
+            ClientMessage::Request { id, .. } if id == LspId::from(4294967296)
+        ));
+        for id in [json!(18446744073709551615_u64), json!(2.5)] {
+            assert!(matches!(
+                message(json!({ "jsonrpc": "2.0", "id": id, "method": "shutdown" })),
+                ClientMessage::Request {
+                    id: LspId::Number(_),
+                    ..
+                }
+            ));
+        }
+        assert!(serde_json::from_value::<ClientMessage>(
+            json!({ "jsonrpc": "2.0", "id": true, "method": "shutdown" })
+        )
+        .is_err());
+        assert!(
+            serde_json::from_value::<ClientMessage>(json!({ "jsonrpc": "2.0", "id": 1 })).is_err()
+        );
+        assert!(matches!(
+            message(json!({ "jsonrpc": "2.0", "method": "exit" })),
+            ClientMessage::Notification { .. }
+        ));
+        assert!(matches!(
+            message(json!({ "jsonrpc": "2.0", "id": 1, "result": null })),
+            ClientMessage::Response {
+                id,
+                result: serde_json::Value::Null
+            } if id == LspId::from(1)
+        ));
+        assert!(matches!(
+            message(json!({ "jsonrpc": "2.0", "id": 2, "error": { "code": -32601, "message": "No" } })),
+            ClientMessage::ErrorResponse { error, .. } if error.code() == ErrorCodes::MethodNotFound as i32
+        ));
+    }
+}
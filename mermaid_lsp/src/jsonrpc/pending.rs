@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use log::warn;
+
+use super::{LspId, ServerRequest};
+
+/// The requests sent to the client that are waiting for their response.
+///
+/// Every request gets a new integer id, and keeps a `handler` that is given back along with
+/// its response so the caller can continue where it left off.
+pub struct PendingRequests<T> {
+    next_id: i64,
+    pending: HashMap<LspId, (String, T)>,
+}
+
+impl<T> Default for PendingRequests<T> {
+    fn default() -> Self {
+        PendingRequests {
+            next_id: 1,
+            pending: HashMap::new(),
+        }
+    }
+}
+
+// Debug implementation for PendingRequests, handlers are usually closures so only the methods are shown.
+impl<T> std::fmt::Debug for PendingRequests<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.pending.iter().map(|(id, (method, _))| (id, method)))
+            .finish()
+    }
+}
+
+impl<T> PendingRequests<T> {
+    /// Creates a request with a new id, its `handler` waits until the response arrives.
+    pub fn request(
+        &mut self,
+        method: &str,
+        params: serde_json::Value,
+        handler: T,
+    ) -> ServerRequest {
+        let id = LspId::from(self.next_id);
+        self.next_id += 1;
+        self.pending
+            .insert(id.clone(), (method.to_string(), handler));
+
+        ServerRequest::new(id, method, params)
+    }
+
+    /// Takes the handler of the request answered by a response, `None` if no request has that id.
+    pub fn complete(&mut self, id: &LspId) -> Option<T> {
+        match self.pending.remove(id) {
+            Some((_, handler)) => Some(handler),
+            None => {
+                warn!("Response to an unknown request {} received!", id);
+                None
+            }
+        }
+    }
+
+    /// The amount of requests waiting for their response.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn pending_requests() {
+        let mut pending = PendingRequests::default();
+
+        let first = pending.request("workspace/configuration", json!({ "items": [] }), "first");
+        let second = pending.request("workspace/applyEdit", json!(null), "second");
+
+        assert_eq!(first.id(), &LspId::from(1));
+        assert_eq!(second.id(), &LspId::from(2));
+        assert_eq!(
+            serde_json::to_value(&first).unwrap(),
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "workspace/configuration", "params": { "items": [] } })
+        );
+        assert_eq!(pending.len(), 2);
+
+        assert_eq!(pending.complete(&LspId::from(2)), Some("second"));
+        assert_eq!(pending.complete(&LspId::from(2)), None);
+        assert_eq!(pending.complete(&LspId::String("1".into())), None);
+        assert_eq!(pending.complete(&LspId::from(1)), Some("first"));
+        assert!(pending.is_empty());
+    }
+}
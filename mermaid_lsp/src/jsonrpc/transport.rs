@@ -0,0 +1,114 @@
+use std::{
+    io::{BufReader, Read, Write},
+    thread::JoinHandle,
+};
+
+use crossbeam_channel::{Receiver, Sender};
+use log::{error, info};
+
+use super::{ClientPayload, LSPMessages, LSPWriter, ParseJsonRPCMessageErrors};
+
+/// The threads that read and write the messages of a client.
+pub struct Channels {
+    /// The messages of the client, it disconnects when the client is gone.
+    pub messages: Receiver<Result<ClientPayload, ParseJsonRPCMessageErrors>>,
+    /// The JSON bodies of the messages for the client, the writer adds the framing of its transport.
+    pub writer: Sender<String>,
+    /// The thread that writes, it's done once `writer` is dropped and every message is written.
+    pub thread: JoinHandle<()>,
+}
+
+/// Starts the threads of a byte stream, where each message has `Content-Length` headers.
+pub fn spawn_stream<R, W>(reader: R, writer: W) -> Channels
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    let messages = spawn_reader(BufReader::new(reader));
+    let (writer, thread) = spawn_writer(LSPWriter::new(writer));
+    Channels {
+        messages,
+        writer,
+        thread,
+    }
+}
+
+/// Reads the messages of the client on their own thread, so the server can wait for them and for
+/// its workers at the same time.
+///
+/// The channel disconnects when the client closes the stream.
+pub fn spawn_reader<T: Read + Send + 'static>(
+    reader: BufReader<T>,
+) -> Receiver<Result<ClientPayload, ParseJsonRPCMessageErrors>> {
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    std::thread::spawn(move || {
+        for payload in LSPMessages::new(reader) {
+            if sender.send(payload).is_err() {
+                break;
+            }
+        }
+        info!("The reader thread is done!");
+    });
+    receiver
+}
+
+/// Writes the messages of the server on their own thread, the only one that owns the output.
+///
+/// The thread ends once every sender is dropped and the messages sent before are written.
+pub fn spawn_writer<T: Write + Send + 'static>(
+    mut writer: LSPWriter<T>,
+) -> (Sender<String>, JoinHandle<()>) {
+    let (sender, receiver) = crossbeam_channel::unbounded::<String>();
+    let thread = std::thread::spawn(move || {
+        for body in receiver {
+            info!("Sending message: {:?}", body);
+            if let Err(e) = writer.write_body(&body) {
+                error!("An error occurred while writing a message {:?}", e);
+            }
+        }
+        info!("The writer thread is done!");
+    });
+    (sender, thread)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// An output that can be read after the writer thread takes it.
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn transport_threads() {
+        let input = "Content-Length: 2\r\n\r\n{}Content-Length: 40\r\n\r\n{\"jsonrpc\":\"2.0\",\"method\":\"initialized\"}";
+        let messages = spawn_reader(BufReader::new(input.as_bytes()));
+        let payloads: Vec<_> = messages.iter().collect();
+        assert_eq!(payloads.len(), 2);
+        assert!(payloads[0].is_err());
+        assert!(matches!(payloads[1], Ok(ClientPayload::Single(_))));
+
+        let output = SharedOutput::default();
+        let (sender, thread) = spawn_writer(LSPWriter::new(output.clone()));
+        sender.send("first".into()).unwrap();
+        sender.send("second".into()).unwrap();
+        drop(sender);
+        thread.join().unwrap();
+        assert_eq!(
+            &*output.0.lock().unwrap(),
+            b"Content-Length: 5\r\n\r\nfirstContent-Length: 6\r\n\r\nsecond"
+        );
+    }
+}
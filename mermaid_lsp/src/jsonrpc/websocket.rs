@@ -0,0 +1,224 @@
+use std::{
+    io::{Read, Write},
+    net::{Shutdown, TcpStream},
+};
+
+use crossbeam_channel::{Receiver, Sender};
+use log::{error, info, warn};
+use tungstenite::{protocol::Role, Error, Message, WebSocket};
+
+use super::{parse_body, Channels, ClientPayload, ParseJsonRPCMessageErrors};
+
+/// The reading half of a WebSocket.
+///
+/// The frames the reader would answer by itself, like pongs, are written by the writing half instead,
+/// so frames of both halves never interleave on the socket.
+struct ReadHalf(TcpStream);
+
+impl Read for ReadHalf {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for ReadHalf {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Starts the threads of a WebSocket client, the handshake is done inside the writing one.
+///
+/// Each text frame is one message or one batch, without `Content-Length` headers. A WebSocket
+/// can't be shared between threads like a stream, so the reading thread has its own on a clone of
+/// the socket and hands the pings and closes of the client to the writing one.
+pub fn spawn_websocket(stream: TcpStream) -> Channels {
+    let (sender, messages) = crossbeam_channel::unbounded();
+    let (writer, outgoing) = crossbeam_channel::unbounded::<String>();
+    let thread = std::thread::spawn(move || {
+        let mut socket = match tungstenite::accept(stream) {
+            Ok(socket) => socket,
+            Err(e) => {
+                error!("The WebSocket handshake failed! {}", e);
+                return;
+            }
+        };
+        let reader = match socket.get_ref().try_clone() {
+            Ok(stream) => WebSocket::from_raw_socket(ReadHalf(stream), Role::Server, None),
+            Err(e) => {
+                error!("The WebSocket can't be read from another thread! {}", e);
+                return;
+            }
+        };
+        let (replies, control) = crossbeam_channel::unbounded();
+        std::thread::spawn(move || read_websocket(reader, sender, replies));
+
+        write_websocket(&mut socket, outgoing, control);
+        let _ = socket.get_ref().shutdown(Shutdown::Both);
+        info!("The WebSocket thread is done!");
+    });
+
+    Channels {
+        messages,
+        writer,
+        thread,
+    }
+}
+
+/// Reads the frames of the client until it closes the connection.
+///
+/// Pings and closes are handed to the writing thread as the frames that answer them.
+fn read_websocket(
+    mut reader: WebSocket<ReadHalf>,
+    sender: Sender<Result<ClientPayload, ParseJsonRPCMessageErrors>>,
+    replies: Sender<Message>,
+) {
+    loop {
+        match reader.read() {
+            Ok(Message::Text(text)) => {
+                let _ = sender.send(parse_body(text.as_bytes()));
+            }
+            Ok(Message::Binary(_)) => {
+                warn!("Ignoring a binary WebSocket frame, messages are sent as text!")
+            }
+            Ok(Message::Ping(payload)) => {
+                let _ = replies.send(Message::Pong(payload));
+            }
+            Ok(close @ Message::Close(_)) => {
+                let _ = replies.send(close);
+            }
+            Ok(_) => {}
+            Err(Error::ConnectionClosed | Error::AlreadyClosed) => {
+                info!("The WebSocket client closed the connection!");
+                return;
+            }
+            Err(e) => {
+                error!("An error occurred while reading a WebSocket frame {:?}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Writes the messages of the server and the replies of the reading thread, until either is done.
+fn write_websocket(
+    socket: &mut WebSocket<TcpStream>,
+    outgoing: Receiver<String>,
+    control: Receiver<Message>,
+) {
+    loop {
+        crossbeam_channel::select! {
+            recv(outgoing) -> body => match body {
+                Ok(body) => {
+                    info!("Sending message: {:?}", body);
+                    if let Err(e) = socket.send(Message::text(body)) {
+                        error!("An error occurred while writing a message {:?}", e);
+                    }
+                }
+                // The server is done, every message it sent was written.
+                Err(_) => {
+                    let _ = socket.close(None).and_then(|_| socket.flush());
+                    return;
+                }
+            },
+            recv(control) -> reply => match reply {
+                Ok(Message::Close(_)) => {
+                    let _ = socket.close(None).and_then(|_| socket.flush());
+                    return;
+                }
+                Ok(reply) => {
+                    if let Err(e) = socket.send(reply) {
+                        error!("An error occurred while answering a WebSocket frame {:?}", e);
+                    }
+                }
+                // The client is gone.
+                Err(_) => return,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, TcpListener};
+
+    use crate::jsonrpc::{ClientMessage, ClientPayload};
+
+    use super::*;
+
+    #[test]
+    fn websocket_transport() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let client = std::thread::spawn(move || {
+            let stream = TcpStream::connect(address).unwrap();
+            let (mut socket, _) =
+                tungstenite::client(format!("ws://{}/", address), stream).unwrap();
+            socket
+                .send(Message::text(
+                    r#"{"jsonrpc":"2.0","id":1,"method":"shutdown"}"#,
+                ))
+                .unwrap();
+            (socket.read().unwrap(), socket.read().unwrap())
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        let channels = spawn_websocket(stream);
+        assert!(matches!(
+            channels.messages.recv().unwrap(),
+            Ok(ClientPayload::Single(ClientMessage::Request { .. }))
+        ));
+
+        channels
+            .writer
+            .send(r#"{"jsonrpc":"2.0","id":1,"result":null}"#.into())
+            .unwrap();
+        drop(channels.writer);
+        channels.thread.join().unwrap();
+
+        let (response, close) = client.join().unwrap();
+        assert_eq!(
+            response,
+            Message::text(r#"{"jsonrpc":"2.0","id":1,"result":null}"#)
+        );
+        assert!(close.is_close());
+    }
+
+    #[test]
+    fn websocket_client_closes() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let client = std::thread::spawn(move || {
+            let stream = TcpStream::connect(address).unwrap();
+            let (mut socket, _) =
+                tungstenite::client(format!("ws://{}/", address), stream).unwrap();
+            socket.send(Message::Ping("hi".into())).unwrap();
+            let pong = socket.read().unwrap();
+            socket.close(None).unwrap();
+            let closed = loop {
+                match socket.read() {
+                    Ok(_) => continue,
+                    Err(e) => break e,
+                }
+            };
+            (pong, closed)
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        let channels = spawn_websocket(stream);
+
+        // The server still has its writer, the client leaving is enough to stop both threads.
+        assert!(channels.messages.recv().is_err());
+        channels.thread.join().unwrap();
+
+        let (pong, closed) = client.join().unwrap();
+        assert_eq!(pong, Message::Pong("hi".into()));
+        assert!(matches!(closed, Error::ConnectionClosed));
+    }
+}
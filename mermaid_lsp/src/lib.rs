@@ -1,20 +1,97 @@
-use std::collections::HashMap;
+#[cfg(feature = "server")]
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
-use mermaid::MermaidAST;
+#[cfg(feature = "server")]
+use client::ResponseHandler;
+#[cfg(feature = "server")]
+use host::HostDocument;
+#[cfg(feature = "server")]
+use jsonrpc::{PendingRequests, ServerRequest};
+#[cfg(feature = "server")]
+use mermaid::lint::LintSettings;
+#[cfg(feature = "server")]
+use requests::ClientCapabilities;
+#[cfg(feature = "server")]
+use workspace::{Indexer, WorkspaceIndex};
 
+#[cfg(feature = "server")]
+pub mod cli;
+#[cfg(feature = "server")]
+pub mod client;
+pub mod host;
 pub mod jsonrpc;
+#[cfg(feature = "server")]
+pub mod logging;
 pub mod mermaid;
+#[cfg(feature = "server")]
 pub mod notifications;
+#[cfg(feature = "server")]
 pub mod requests;
+#[cfg(feature = "server")]
+pub mod router;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "server")]
+pub mod worker;
+#[cfg(feature = "server")]
+pub mod workspace;
 
 /// Represents the whole state of the server
+#[cfg(feature = "server")]
 #[derive(Debug, Default)]
 pub struct ServerState {
     /// All documents that have been opened and the LSP recognizes.
     /// Consists of a key that is the URI of the file and a value
-    /// that is the AST from the parsed file.
-    pub documents: HashMap<String, MermaidAST>,
+    /// with every diagram parsed from the file.
+    pub documents: HashMap<String, Arc<HostDocument>>,
 
     /// Flag that indicates whether or not the server has been initialized.
     pub initialized: bool,
+
+    /// Flag that indicates whether or not the client has asked the server to shut down.
+    /// The server exits with an error if the client leaves without doing it first.
+    pub shutdown: bool,
+
+    /// The symbols of every Mermaid file inside the workspace folders.
+    /// It's shared with the thread that indexes the workspace in the background.
+    pub workspace: Arc<Mutex<WorkspaceIndex>>,
+
+    /// The thread that updates the workspace index, snapshots don't have one.
+    pub indexer: Indexer,
+
+    /// The severity of each lint rule, from the project config file and the client settings.
+    pub lint: LintSettings,
+
+    /// What the client supports, sent with the `initialize` request.
+    pub capabilities: ClientCapabilities,
+
+    /// The requests sent to the client that are waiting for their response.
+    pub pending: PendingRequests<ResponseHandler>,
+
+    /// The requests that will be sent to the client once the current message is handled.
+    pub outgoing: Vec<ServerRequest>,
+}
+
+#[cfg(feature = "server")]
+impl ServerState {
+    /// A copy of the state for the requests that only read it, so they can run on another thread.
+    ///
+    /// Documents are shared with the original until they change. The requests sent to the
+    /// client stay with the original.
+    pub fn snapshot(&self) -> ServerState {
+        ServerState {
+            documents: self.documents.clone(),
+            initialized: self.initialized,
+            shutdown: self.shutdown,
+            workspace: self.workspace.clone(),
+            indexer: Indexer::default(),
+            lint: self.lint.clone(),
+            capabilities: self.capabilities.clone(),
+            pending: PendingRequests::default(),
+            outgoing: vec![],
+        }
+    }
 }
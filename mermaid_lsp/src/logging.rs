@@ -0,0 +1,297 @@
+use std::{
+    cell::RefCell,
+    fs::{File, OpenOptions},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crossbeam_channel::Sender;
+use log::{warn, LevelFilter, Log, Metadata, Record};
+use simplelog::{CombinedLogger, Config, SharedLogger, WriteLogger};
+
+use crate::{
+    jsonrpc::ServerNotification,
+    notifications::window::{log_message_notification, MessageType},
+};
+
+/// The name of the log file inside the state directory.
+const LOG_FILE_NAME: &str = "mermaid_lsp.log";
+
+/// The records that are forwarded to the client, the rest only go to the log file.
+const CLIENT_LEVEL: LevelFilter = LevelFilter::Warn;
+
+/// The warning about a log file that couldn't be opened, logged before any client was there to see it.
+static LOG_FILE_WARNING: OnceLock<String> = OnceLock::new();
+
+thread_local! {
+    /// Where the `window/logMessage` notifications of this thread go, the client it works for.
+    ///
+    /// A server with `--listen` has a client per connection, so each one only gets its own warnings.
+    static CLIENT_MESSAGES: RefCell<Option<Sender<ServerNotification>>> = const { RefCell::new(None) };
+}
+
+/// Where and what the server logs, from the command line or the environment.
+#[derive(Debug, Clone, clap::Args)]
+pub struct LogOptions {
+    /// The file where logs are written [default for serve: $XDG_STATE_HOME/mermaid_lsp/mermaid_lsp.log]
+    #[arg(long, env = "MERMAID_LSP_LOG_FILE", global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// The most detailed level that is logged: off, error, warn, info, debug or trace
+    #[arg(
+        long,
+        env = "MERMAID_LSP_LOG_LEVEL",
+        default_value = "info",
+        global = true
+    )]
+    pub log_level: LevelFilter,
+
+    /// Write logs to STDERR too
+    #[arg(
+        long,
+        env = "MERMAID_LSP_LOG_STDERR",
+        value_parser = clap::builder::BoolishValueParser::new(),
+        global = true
+    )]
+    pub log_stderr: bool,
+}
+
+/// The default log file, inside the state directory of the user like `~/.local/state`.
+pub fn default_log_file() -> PathBuf {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("mermaid_lsp")
+        .join(LOG_FILE_NAME)
+}
+
+/// Opens the log file to append to it, creating it and the directories that contain it.
+///
+/// Servers of different editors share the file, so the logs of the previous ones are kept.
+pub fn open_log_file(path: &Path) -> std::io::Result<File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Sets up the loggers described by `options`.
+///
+/// A log file that can't be opened is not fatal, the server logs to STDERR instead and
+/// warns every client about it once `connect_client` forwards their messages. Warnings and errors are forwarded to the client when `to_client` is set,
+/// the commands that don't talk to a client leave it unset and only log to a file given explicitly.
+pub fn init(options: &LogOptions, to_client: bool) {
+    let level = options.log_level;
+    let path = match (&options.log_file, to_client) {
+        (Some(path), _) => Some(path.clone()),
+        (None, true) => Some(default_log_file()),
+        (None, false) => None,
+    };
+    let mut loggers: Vec<Box<dyn SharedLogger>> = vec![];
+    if to_client {
+        loggers.push(Box::new(ClientLogger {
+            level: level.min(CLIENT_LEVEL),
+        }));
+    }
+
+    let file_error = match path.as_deref().map(open_log_file) {
+        Some(Ok(file)) => {
+            loggers.push(WriteLogger::new(level, Config::default(), file));
+            None
+        }
+        Some(Err(e)) => Some(e),
+        None => None,
+    };
+    if options.log_stderr || file_error.is_some() {
+        loggers.push(WriteLogger::new(
+            level,
+            Config::default(),
+            std::io::stderr(),
+        ));
+    }
+
+    if CombinedLogger::init(loggers).is_err() {
+        return;
+    }
+    if let (Some(path), Some(e)) = (path, file_error) {
+        let warning = format!(
+            "The log file {} couldn't be opened, logging to STDERR instead! {}",
+            path.display(),
+            e
+        );
+        warn!("{}", warning);
+        let _ = LOG_FILE_WARNING.set(warning);
+    }
+}
+
+/// Forwards the warnings of this thread to a client that just connected, starting with the ones
+/// logged while setting up the loggers, before any client could receive them.
+pub fn connect_client(messages: Sender<ServerNotification>) {
+    if let Some(warning) = LOG_FILE_WARNING.get() {
+        let _ = messages.send(log_message_notification(
+            MessageType::Warning,
+            warning.clone(),
+        ));
+    }
+    forward_to_client(Some(messages));
+}
+
+/// Sends the warnings logged by this thread to a client as `window/logMessage` notifications,
+/// `None` stops sending them.
+pub fn forward_to_client(messages: Option<Sender<ServerNotification>>) {
+    CLIENT_MESSAGES.with(|client| *client.borrow_mut() = messages);
+}
+
+/// Where the warnings logged by this thread are sent, the threads it starts for its client use it too.
+pub fn client_messages() -> Option<Sender<ServerNotification>> {
+    CLIENT_MESSAGES.with(|client| client.borrow().clone())
+}
+
+/// Queues records as `window/logMessage` notifications for the client of the thread that logs them,
+/// the server sends them after each message.
+struct ClientLogger {
+    level: LevelFilter,
+}
+
+impl Log for ClientLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let notification =
+            log_message_notification(MessageType::from(record.level()), record.args().to_string());
+        CLIENT_MESSAGES.with(|client| {
+            if let Some(messages) = client.borrow().as_ref() {
+                let _ = messages.send(notification);
+            }
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+impl SharedLogger for ClientLogger {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        None
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Debug, Parser)]
+    struct Cli {
+        #[command(flatten)]
+        log: LogOptions,
+    }
+
+    #[test]
+    fn log_options_from_args() {
+        let cli = Cli::parse_from([
+            "mermaid_lsp",
+            "--log-file",
+            "/tmp/lsp.log",
+            "--log-level",
+            "debug",
+            "--log-stderr",
+        ]);
+
+        assert_eq!(cli.log.log_file, Some(PathBuf::from("/tmp/lsp.log")));
+        assert_eq!(cli.log.log_level, LevelFilter::Debug);
+        assert!(cli.log.log_stderr);
+    }
+
+    #[test]
+    fn open_log_file_creates_directories() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("nested").join(LOG_FILE_NAME);
+
+        assert!(open_log_file(&path).is_ok());
+        assert!(path.exists());
+
+        std::fs::write(&path, "First\n").unwrap();
+        writeln!(open_log_file(&path).unwrap(), "Second").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "First\nSecond\n");
+
+        let blocked = path.join(LOG_FILE_NAME);
+        assert!(open_log_file(&blocked).is_err());
+    }
+
+    #[test]
+    fn client_logger_queues_warnings() {
+        let logger = ClientLogger {
+            level: LevelFilter::Warn,
+        };
+        let warn = |logger: &ClientLogger, message| {
+            logger.log(
+                &Record::builder()
+                    .level(log::Level::Warn)
+                    .args(format_args!("{}", message))
+                    .build(),
+            )
+        };
+        let (sender, received) = crossbeam_channel::unbounded();
+        forward_to_client(Some(sender));
+
+        logger.log(
+            &Record::builder()
+                .level(log::Level::Info)
+                .args(format_args!("Ignored"))
+                .build(),
+        );
+        warn(&logger, "Careful");
+        std::thread::scope(|s| {
+            s.spawn(|| warn(&logger, "Another client"));
+        });
+
+        let messages: Vec<_> = received
+            .try_iter()
+            .map(|m| serde_json::to_value(m).unwrap())
+            .collect();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["method"], "window/logMessage");
+        assert_eq!(
+            messages[0]["params"],
+            serde_json::json!({ "type": 2, "message": "Careful" })
+        );
+    }
+
+    #[test]
+    fn connected_clients_get_the_log_file_warning() {
+        let _ = LOG_FILE_WARNING.set("The log file /nope couldn't be opened".into());
+        let (sender, received) = crossbeam_channel::unbounded();
+
+        std::thread::scope(|s| {
+            s.spawn(|| connect_client(sender));
+        });
+
+        let messages: Vec<_> = received
+            .try_iter()
+            .map(|m| serde_json::to_value(m).unwrap())
+            .collect();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["params"]["type"], 2);
+        assert_eq!(
+            messages[0]["params"]["message"],
+            *LOG_FILE_WARNING.get().unwrap()
+        );
+    }
+}
@@ -1,80 +1,627 @@
+use clap::Parser;
+use clap::Subcommand;
+use crossbeam_channel::Receiver;
+use crossbeam_channel::Sender;
+use log::debug;
 use log::error;
 use log::info;
 use log::warn;
-use mermaid_lsp::jsonrpc::encode_message;
+use mermaid_lsp::cli::ast_json;
+use mermaid_lsp::cli::format_document;
+use mermaid_lsp::cli::has_errors;
+use mermaid_lsp::cli::has_unreadable;
+use mermaid_lsp::cli::human_report;
+use mermaid_lsp::cli::json_report;
+use mermaid_lsp::cli::read_document;
+use mermaid_lsp::cli::sarif_report;
+use mermaid_lsp::cli::write_output;
+use mermaid_lsp::cli::CliErrors;
+use mermaid_lsp::cli::Connection;
+use mermaid_lsp::cli::FileDiagnostics;
+use mermaid_lsp::cli::OutputFormat;
+use mermaid_lsp::cli::TransportOptions;
+use mermaid_lsp::host::HostDocument;
+use mermaid_lsp::jsonrpc::CancelParams;
+use mermaid_lsp::jsonrpc::Channels;
 use mermaid_lsp::jsonrpc::ClientMessage;
+use mermaid_lsp::jsonrpc::ClientPayload;
+use mermaid_lsp::jsonrpc::EncodeErrors;
 use mermaid_lsp::jsonrpc::ErrorCodes;
-use mermaid_lsp::jsonrpc::LSPMessages;
+use mermaid_lsp::jsonrpc::LspId;
 use mermaid_lsp::jsonrpc::ParseJsonRPCMessageErrors;
 use mermaid_lsp::jsonrpc::ResponseError;
+use mermaid_lsp::jsonrpc::ServerNotification;
 use mermaid_lsp::jsonrpc::ServerResponse;
+use mermaid_lsp::logging;
+use mermaid_lsp::logging::LogOptions;
+use mermaid_lsp::mermaid::lint::LintConfig;
+use mermaid_lsp::mermaid::FormatOptions;
+use mermaid_lsp::notifications::initialized_notification;
+use mermaid_lsp::notifications::text_document::did_change_notification;
 use mermaid_lsp::notifications::text_document::did_open_notification;
+use mermaid_lsp::notifications::text_document::publish_all_diagnostics;
+use mermaid_lsp::notifications::text_document::publish_diagnostics_notification;
+use mermaid_lsp::notifications::workspace::did_change_configuration_notification;
+use mermaid_lsp::notifications::workspace::did_change_watched_files_notification;
+use mermaid_lsp::requests::code_action_request;
+use mermaid_lsp::requests::document_symbol_request;
+use mermaid_lsp::requests::folding_range_request;
+use mermaid_lsp::requests::formatting_request;
 use mermaid_lsp::requests::initialize_request;
+use mermaid_lsp::requests::on_type_formatting_request;
+use mermaid_lsp::requests::range_formatting_request;
 use mermaid_lsp::requests::shutdown_request;
+use mermaid_lsp::requests::workspace_symbol_request;
+use mermaid_lsp::router::Dispatch;
+use mermaid_lsp::router::RequestJob;
+use mermaid_lsp::router::Router;
+use mermaid_lsp::worker::WorkerPool;
 use mermaid_lsp::ServerState;
-use simplelog::*;
-use std::fs::File;
-use std::io;
-use std::io::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::ops::ControlFlow;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
-fn main() {
-    WriteLogger::init(
-        LevelFilter::Debug,
-        Config::default(),
-        File::create("/home/elrohirgt/Documents/Development/MermaidLSP/mermaid_lsp.log").unwrap(),
-    )
-    .unwrap();
+/// A language server for Mermaid diagrams, and the tools to lint and format them without an editor.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    log: LogOptions,
+
+    #[command(flatten)]
+    transport: TransportOptions,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Runs the language server over STDIN and STDOUT or the chosen transport, the default
+    Serve,
+
+    /// Prints the diagnostics of files, fails if some diagnostic is an error
+    Check {
+        /// The format of the report
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+
+    /// Formats the diagrams of files in place
+    Fmt {
+        /// Only lists the files that aren't formatted, fails if there's any
+        #[arg(long)]
+        check: bool,
+
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+
+    /// Prints the AST of a file as JSON
+    Ast { file: PathBuf },
+}
+
+/// The exit code of a command that couldn't read or write its files.
+const IO_FAILURE: u8 = 2;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Command::Serve);
+    logging::init(&cli.log, matches!(command, Command::Serve));
 
     info!("Logging setup correctly!");
 
-    let stdin = std::io::stdin();
-    let reader = std::io::BufReader::new(stdin);
-    let mut messages = LSPMessages::new(reader);
+    let result = match command {
+        Command::Serve => start(&cli.transport),
+        Command::Check { format, files } => check(format, &files),
+        Command::Fmt { check, files } => fmt(check, &files),
+        Command::Ast { file } => ast(&file),
+    };
 
-    messages.try_fold(
-        ServerState::default(),
-        |state, message| match handle_message(state, message) {
-            ServerAction::Ignore(new_state) => ControlFlow::Continue(new_state),
-            ServerAction::Exit => ControlFlow::Break(()),
-            ServerAction::Respond(new_state, response) => {
-                let response = match encode_message(response) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        error!("The response couldn't be serialized into a string! {:?}", e);
-                        return ControlFlow::Break(());
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            error!("{}", e);
+            eprintln!("{}", e);
+            ExitCode::from(IO_FAILURE)
+        }
+    }
+}
+
+/// Checks the files with the lint config of the current directory.
+///
+/// An invalid config fails the command, checking with the default rules instead would hide it.
+/// Files that can't be read are reported along with the rest.
+fn check(format: OutputFormat, files: &[PathBuf]) -> Result<ExitCode, CliErrors> {
+    let config = LintConfig::from_folder(&std::env::current_dir().unwrap_or_default())
+        .map_err(CliErrors::InvalidLintConfig)?;
+
+    let checked: Vec<_> = files
+        .iter()
+        .map(|path| match read_document(path) {
+            Ok(document) => FileDiagnostics::new(path.clone(), &document, &config),
+            Err(e) => FileDiagnostics::unreadable(path.clone(), e.to_string()),
+        })
+        .collect();
+
+    let report = match format {
+        OutputFormat::Human => human_report(&checked),
+        OutputFormat::Json => format!("{:#}\n", json_report(&checked)),
+        OutputFormat::Sarif => format!("{:#}\n", sarif_report(&checked)),
+    };
+    write_output(&mut std::io::stdout().lock(), &report)?;
+
+    match (has_unreadable(&checked), has_errors(&checked)) {
+        (true, _) => Ok(ExitCode::from(IO_FAILURE)),
+        (false, true) => Ok(ExitCode::FAILURE),
+        (false, false) => Ok(ExitCode::SUCCESS),
+    }
+}
+
+/// Formats the files in place, or lists the ones that would change with `--check`.
+fn fmt(check: bool, files: &[PathBuf]) -> Result<ExitCode, CliErrors> {
+    let mut unformatted = false;
+    for path in files {
+        let document = read_document(path)?;
+        let formatted = format_document(&document, &FormatOptions::default());
+        if formatted == document.text {
+            continue;
+        }
+
+        unformatted = true;
+        match check {
+            true => write_output(
+                &mut std::io::stdout().lock(),
+                &format!("{}\n", path.display()),
+            )?,
+            false => std::fs::write(path, formatted)
+                .map_err(|e| CliErrors::WriteError(path.clone(), e))?,
+        }
+    }
+
+    match check && unformatted {
+        true => Ok(ExitCode::FAILURE),
+        false => Ok(ExitCode::SUCCESS),
+    }
+}
+
+/// Prints the AST of a file.
+fn ast(file: &Path) -> Result<ExitCode, CliErrors> {
+    let document = read_document(file)?;
+    write_output(
+        &mut std::io::stdout().lock(),
+        &format!("{}\n", ast_json(&document)?),
+    )?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Serves the client of the chosen transport, or every client that connects with `--listen`.
+///
+/// Each client gets its own server with its own state. Listening only stops when the process is
+/// interrupted or terminated, which removes the file of a Unix domain socket on the way out.
+fn start(options: &TransportOptions) -> Result<ExitCode, CliErrors> {
+    let transport = options.transport();
+    if !options.listens() {
+        info!("Serving a client over {}", transport);
+        return Ok(serve(transport.connect()?));
+    }
+
+    let listener = transport.listen()?;
+    if let Some(path) = listener.socket_file().map(Path::to_path_buf) {
+        let removed = ctrlc::set_handler(move || {
+            let _ = std::fs::remove_file(&path);
+            std::process::exit(0);
+        });
+        if let Err(e) = removed {
+            warn!(
+                "The socket file won't be removed when the server stops! {}",
+                e
+            );
+        }
+    }
+    info!("Listening for clients on {}", transport);
+    loop {
+        match listener.accept() {
+            Ok(connection) => {
+                std::thread::spawn(move || {
+                    info!("A client connected!");
+                    serve(connection)
+                });
+            }
+            Err(e) => error!("{}", e),
+        }
+    }
+}
+
+/// The most threads that answer requests, whatever the number of cores.
+const MAX_WORKERS: usize = 4;
+
+/// A request that a worker is answering.
+struct InFlight {
+    /// The document the request is about, and its version when the request was received.
+    document: Option<(String, Option<Arc<HostDocument>>)>,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Answers the messages of a client until it asks the server to exit.
+///
+/// Messages are read and written by their own threads. Requests that only read the state
+/// are answered by workers from a snapshot, while the rest of the messages keep coming.
+fn serve(connection: Connection) -> ExitCode {
+    let Channels {
+        messages,
+        writer,
+        thread: writer_thread,
+    } = connection.spawn();
+    let (log_sender, log_messages) = crossbeam_channel::unbounded();
+    logging::connect_client(log_sender);
+    let (finished_sender, finished) = crossbeam_channel::unbounded();
+    let workers = WorkerPool::new(
+        std::thread::available_parallelism().map_or(1, |n| n.get().min(MAX_WORKERS)),
+        finished_sender,
+    );
+    let router = router();
+    let mut in_flight: HashMap<LspId, InFlight> = HashMap::new();
+
+    let flow = serve_loop(&messages, &finished, |state, event| {
+        let flow = match event {
+            Event::Payload(payload) => {
+                if let Ok(payload) = &payload {
+                    for id in cancelled_requests(payload) {
+                        let Some(request) = in_flight.remove(&id) else {
+                            debug!("Request {} isn't running, ignoring its cancellation", id);
+                            continue;
+                        };
+                        request.cancelled.store(true, Ordering::Release);
+                        let response = ServerResponse::new_error(
+                            Some(id),
+                            ResponseError::new(
+                                ErrorCodes::RequestCancelled,
+                                "The request was cancelled!".into(),
+                            ),
+                        );
+                        if send_message(&writer, response).is_err() {
+                            return ControlFlow::Break(ExitCode::FAILURE);
+                        }
                     }
-                };
+                }
 
-                let mut stdout = io::stdout();
+                match handle_payload(&router, state, payload) {
+                    ServerAction::Ignore(new_state) => ControlFlow::Continue(new_state),
+                    ServerAction::Exit(code) => ControlFlow::Break(code),
+                    ServerAction::Respond(new_state, response) => {
+                        match send_message(&writer, response) {
+                            Ok(_) => ControlFlow::Continue(new_state),
+                            Err(_) => ControlFlow::Break(ExitCode::FAILURE),
+                        }
+                    }
+                    ServerAction::Spawn(new_state, job) => {
+                        let document = job.uri.clone().map(|uri| (uri, job.document()));
+                        let id = job.id.clone();
+                        let cancelled = workers.spawn(job);
+                        in_flight.insert(
+                            id,
+                            InFlight {
+                                document,
+                                cancelled,
+                            },
+                        );
+                        ControlFlow::Continue(new_state)
+                    }
+                    ServerAction::Notify(new_state, notifications) => {
+                        match notifications
+                            .into_iter()
+                            .try_for_each(|n| send_message(&writer, n))
+                        {
+                            Ok(_) => ControlFlow::Continue(new_state),
+                            Err(_) => ControlFlow::Break(ExitCode::FAILURE),
+                        }
+                    }
+                    // A batch made only of notifications isn't answered at all.
+                    ServerAction::RespondBatch(new_state, responses, notifications) => {
+                        let sent = match responses.is_empty() {
+                            true => Ok(()),
+                            false => send_message(&writer, responses),
+                        };
+                        match sent.and_then(|_| {
+                            notifications
+                                .into_iter()
+                                .try_for_each(|n| send_message(&writer, n))
+                        }) {
+                            Ok(_) => ControlFlow::Continue(new_state),
+                            Err(_) => ControlFlow::Break(ExitCode::FAILURE),
+                        }
+                    }
+                }
+            }
+            // Cancelled requests were already answered.
+            Event::Finished(id, response) => {
+                let Some(request) = in_flight.remove(&id) else {
+                    debug!("Dropping the response to cancelled request {}", id);
+                    return ControlFlow::Continue(state);
+                };
 
-                info!("Sending response: {:?}", response);
-                if let Err(e) = stdout.write_all(response.as_bytes()) {
-                    error!("An error occurred while writing to STDOUT {:?}", e);
+                let modified = request.document.is_some_and(|(uri, document)| {
+                    match (document, state.documents.get(&uri)) {
+                        (Some(old), Some(current)) => !Arc::ptr_eq(&old, current),
+                        (old, current) => old.is_some() != current.is_some(),
+                    }
+                });
+                let response = match modified {
+                    false => response,
+                    true => {
+                        info!("The document of request {} changed while it ran", id);
+                        ServerResponse::new_error(
+                            Some(id),
+                            ResponseError::new(
+                                ErrorCodes::ContentModified,
+                                "The document changed while the request was running!".into(),
+                            ),
+                        )
+                    }
+                };
+                match send_message(&writer, response) {
+                    Ok(_) => ControlFlow::Continue(state),
+                    Err(_) => ControlFlow::Break(ExitCode::FAILURE),
                 }
-                if let Err(e) = stdout.flush() {
-                    error!("An error occurred while flushing STDOUT {:?}", e);
+            }
+        };
+
+        // The requests queued while handling the message are sent after its response.
+        let flow = match flow {
+            ControlFlow::Continue(mut new_state) => {
+                match std::mem::take(&mut new_state.outgoing)
+                    .into_iter()
+                    .try_for_each(|r| send_message(&writer, r))
+                {
+                    Ok(_) => ControlFlow::Continue(new_state),
+                    Err(_) => ControlFlow::Break(ExitCode::FAILURE),
                 }
+            }
+            flow => flow,
+        };
 
-                info!("Response sent!");
+        // Warnings logged while handling the message are shown by the client too.
+        match log_messages
+            .try_iter()
+            .try_for_each(|n| send_message(&writer, n))
+        {
+            Ok(_) => flow,
+            Err(_) => ControlFlow::Break(ExitCode::FAILURE),
+        }
+    });
 
-                ControlFlow::Continue(new_state)
+    // The messages sent so far are written before exiting.
+    drop(writer);
+    if writer_thread.join().is_err() {
+        error!("The writer thread panicked!");
+    }
+
+    match flow {
+        ControlFlow::Break(code) => {
+            info!("The server is exiting...");
+            code
+        }
+        // The spec asks to exit with an error when the client goes away without an `exit` notification.
+        ControlFlow::Continue(_) => {
+            warn!("The client disconnected without an exit notification!");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Something the server has to handle, a payload from the client or a request a worker finished.
+enum Event {
+    Payload(Result<ClientPayload, ParseJsonRPCMessageErrors>),
+    Finished(LspId, ServerResponse),
+}
+
+/// Folds the events of the server into its state, until `handle` breaks or the client disconnects.
+fn serve_loop(
+    messages: &Receiver<Result<ClientPayload, ParseJsonRPCMessageErrors>>,
+    finished: &Receiver<(LspId, ServerResponse)>,
+    mut handle: impl FnMut(ServerState, Event) -> ControlFlow<ExitCode, ServerState>,
+) -> ControlFlow<ExitCode, ServerState> {
+    let mut state = ServerState::default();
+    loop {
+        let event = crossbeam_channel::select! {
+            recv(messages) -> payload => match payload {
+                Ok(payload) => Event::Payload(payload),
+                Err(_) => return ControlFlow::Continue(state),
+            },
+            recv(finished) -> response => match response {
+                Ok((id, response)) => Event::Finished(id, response),
+                Err(_) => return ControlFlow::Continue(state),
+            },
+        };
+        state = match handle(state, event) {
+            ControlFlow::Continue(new_state) => new_state,
+            flow => return flow,
+        };
+    }
+}
+
+/// The ids of the requests a payload cancels with `$/cancelRequest` notifications.
+fn cancelled_requests(payload: &ClientPayload) -> Vec<LspId> {
+    let cancelled = |message: &ClientMessage| match message {
+        ClientMessage::Notification { method, params } if method == "$/cancelRequest" => {
+            serde_json::from_value::<CancelParams>(params.clone().unwrap_or_default())
+                .map(|params| params.id)
+                .map_err(|e| warn!("Invalid params supplied to $/cancelRequest! {}", e))
+                .ok()
+        }
+        _ => None,
+    };
+
+    match payload {
+        ClientPayload::Single(message) => cancelled(message).into_iter().collect(),
+        ClientPayload::Batch(messages) => messages.iter().flatten().filter_map(cancelled).collect(),
+    }
+}
+
+/// Registers the handlers of every method the server supports.
+///
+/// `exit` is handled by `handle_message`, since it stops the server.
+fn router() -> Router {
+    Router::default()
+        .request_mut("initialize", initialize_request)
+        .request_mut("shutdown", shutdown_request)
+        .request("textDocument/codeAction", code_action_request)
+        .request("textDocument/documentSymbol", document_symbol_request)
+        .request("textDocument/foldingRange", folding_range_request)
+        .request("textDocument/formatting", formatting_request)
+        .request("textDocument/rangeFormatting", range_formatting_request)
+        .request("textDocument/onTypeFormatting", on_type_formatting_request)
+        .request("workspace/symbol", workspace_symbol_request)
+        .notification(
+            "textDocument/didOpen",
+            |state, params| match did_open_notification(state, params) {
+                Ok(uri) => vec![publish_diagnostics_notification(state, uri)],
+                Err(e) => {
+                    error!("An error ocurred while opening file: {:?}", e);
+                    vec![]
+                }
+            },
+        )
+        .notification(
+            "textDocument/didChange",
+            |state, params| match did_change_notification(state, params) {
+                Ok(uri) => vec![publish_diagnostics_notification(state, uri)],
+                Err(e) => {
+                    error!("An error ocurred while changing file: {:?}", e);
+                    vec![]
+                }
+            },
+        )
+        .notification("initialized", |state, params| -> Vec<ServerNotification> {
+            initialized_notification(state, params);
+            vec![]
+        })
+        .notification("workspace/didChangeConfiguration", |state, params| {
+            did_change_configuration_notification(state, params);
+            publish_all_diagnostics(state)
+        })
+        .notification(
+            "workspace/didChangeWatchedFiles",
+            |state, params| -> Vec<ServerNotification> {
+                did_change_watched_files_notification(state, params);
+                vec![]
+            },
+        )
+}
+
+/// Serializes a message and hands it to the writer thread, which frames it for the transport.
+///
+/// Fails only if the message couldn't be serialized.
+fn send_message<T: Serialize>(writer: &Sender<String>, message: T) -> Result<(), EncodeErrors> {
+    let message =
+        match serde_json::to_string(&message).map_err(EncodeErrors::MessageSerializationError) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("The message couldn't be serialized into a string! {:?}", e);
+                return Err(e);
             }
-        },
-    );
+        };
 
-    info!("The server is exiting...");
+    if writer.send(message).is_err() {
+        error!("The writer thread is gone, the message was dropped!");
+    }
+    Ok(())
 }
 
 /// Enum that represents all actions the server can take when it recieves a `ClientMessage`
 enum ServerAction {
     Respond(ServerState, ServerResponse),
+    Notify(ServerState, Vec<ServerNotification>),
     Ignore(ServerState),
-    Exit,
+    /// A request that only reads the state, for a worker to answer.
+    Spawn(ServerState, RequestJob),
+    /// The responses to the requests of a batch, and the notifications its notifications sent back.
+    RespondBatch(ServerState, Vec<ServerResponse>, Vec<ServerNotification>),
+    Exit(ExitCode),
+}
+
+/// The exit code for an `exit` notification, an error unless it's preceded by a shutdown request.
+fn exit_code(state: &ServerState) -> ExitCode {
+    match state.shutdown {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
+
+/// Handles a possible incoming `ClientPayload`, a message or a batch of them.
+fn handle_payload(
+    router: &Router,
+    state: ServerState,
+    payload: Result<ClientPayload, ParseJsonRPCMessageErrors>,
+) -> ServerAction {
+    match payload {
+        Ok(ClientPayload::Single(message)) => handle_message(router, state, Ok(message)),
+        Ok(ClientPayload::Batch(messages)) => handle_batch(router, state, messages),
+        Err(e) => handle_message(router, state, Err(e)),
+    }
+}
+
+/// Handles every message of a batch in order, and collects their responses into one.
+///
+/// An empty batch is an invalid request. An `exit` notification stops the server right away,
+/// without answering the rest of the batch.
+fn handle_batch(
+    router: &Router,
+    mut state: ServerState,
+    messages: Vec<Result<ClientMessage, ParseJsonRPCMessageErrors>>,
+) -> ServerAction {
+    info!("Batch of {} messages received!", messages.len());
+    if messages.is_empty() {
+        let response = ServerResponse::new_error(
+            None,
+            ResponseError::new(ErrorCodes::InvalidRequest, "The batch is empty!".into()),
+        );
+        return ServerAction::Respond(state, response);
+    }
+
+    let mut responses = vec![];
+    let mut notifications = vec![];
+    for message in messages {
+        state = match handle_message(router, state, message) {
+            ServerAction::Respond(new_state, response) => {
+                responses.push(response);
+                new_state
+            }
+            ServerAction::Notify(new_state, sent) => {
+                notifications.extend(sent);
+                new_state
+            }
+            ServerAction::RespondBatch(new_state, batch_responses, sent) => {
+                responses.extend(batch_responses);
+                notifications.extend(sent);
+                new_state
+            }
+            // The requests of a batch are answered together, so there's no point in waiting for a worker.
+            ServerAction::Spawn(new_state, job) => {
+                responses.push(job.run());
+                new_state
+            }
+            ServerAction::Ignore(new_state) => new_state,
+            ServerAction::Exit(code) => return ServerAction::Exit(code),
+        };
+    }
+
+    ServerAction::RespondBatch(state, responses, notifications)
 }
 
 /// Handles a possible incoming `ClientMessage`.
 fn handle_message(
+    router: &Router,
     mut state: ServerState,
     message: Result<ClientMessage, ParseJsonRPCMessageErrors>,
 ) -> ServerAction {
@@ -86,7 +633,7 @@ fn handle_message(
                 (false, ClientMessage::Request { id, method, params })
                     if method == *"initialize" =>
                 {
-                    let response = initialize_request(id, params);
+                    let response = router.handle_request(&mut state, id, &method, params);
                     let initialized = matches!(response, ServerResponse::Result { .. });
                     ServerAction::Respond(
                         ServerState {
@@ -97,12 +644,38 @@ fn handle_message(
                     )
                 }
 
-                (false, _) => {
+                (_, ClientMessage::Notification { method, .. }) if method == *"exit" => {
+                    ServerAction::Exit(exit_code(&state))
+                }
+
+                (_, ClientMessage::Response { id, result }) => {
+                    match state.handle_response(&id, Ok(result)) {
+                        notifications if notifications.is_empty() => ServerAction::Ignore(state),
+                        notifications => ServerAction::Notify(state, notifications),
+                    }
+                }
+
+                (_, ClientMessage::ErrorResponse { id, error }) => {
+                    match state.handle_response(&id, Err(error)) {
+                        notifications if notifications.is_empty() => ServerAction::Ignore(state),
+                        notifications => ServerAction::Notify(state, notifications),
+                    }
+                }
+
+                (false, ClientMessage::Notification { method, .. }) => {
+                    warn!(
+                        "Dropping {} notification, the server isn't initialized!",
+                        method
+                    );
+                    ServerAction::Ignore(state)
+                }
+
+                (false, ClientMessage::Request { id, .. }) => {
                     warn!(
                         "Message recieved and valid but an initialize request has not been sent!"
                     );
                     let response = ServerResponse::new_error(
-                        None,
+                        Some(id),
                         ResponseError::new(
                             ErrorCodes::ServerNotInitialized,
                             "The server needs to be initialized first with a `initialize` request!"
@@ -113,10 +686,10 @@ fn handle_message(
                     ServerAction::Respond(state, response)
                 }
 
-                (true, ClientMessage::Request { method, .. }) if method == *"initialize" => {
+                (true, ClientMessage::Request { id, method, .. }) if method == *"initialize" => {
                     warn!("Initialize request received but server is already initialized!");
                     let response = ServerResponse::new_error(
-                        None,
+                        Some(id),
                         ResponseError::new(
                             ErrorCodes::InvalidRequest,
                             "The server is already initialized!".into(),
@@ -126,58 +699,40 @@ fn handle_message(
                     ServerAction::Respond(state, response)
                 }
 
+                (true, ClientMessage::Request { id, .. }) if state.shutdown => {
+                    warn!("Request received after a shutdown request!");
+                    let response = ServerResponse::new_error(
+                        Some(id),
+                        ResponseError::new(
+                            ErrorCodes::InvalidRequest,
+                            "The server is shutting down!".into(),
+                        ),
+                    );
+
+                    ServerAction::Respond(state, response)
+                }
+
                 (true, ClientMessage::Request { id, method, params }) => {
-                    // Handle requests other than initialize...
-                    match method.as_str() {
-                        "shutdown" => {
-                            info!("Shutting down the server with id: {}", id);
-                            ServerAction::Respond(state, shutdown_request(id))
-                        }
-                        _ => {
-                            warn!("Unimplemented request received!");
-                            let response = ServerResponse::new_error(
-                                None,
-                                ResponseError::new(
-                                    ErrorCodes::InvalidRequest,
-                                    "This method is not implemented yet!".into(),
-                                ),
-                            );
-
-                            ServerAction::Respond(state, response)
-                        }
+                    match router.dispatch(&mut state, id, &method, params) {
+                        Dispatch::Respond(response) => ServerAction::Respond(state, response),
+                        Dispatch::Spawn(job) => ServerAction::Spawn(state, job),
                     }
                 }
 
                 (true, ClientMessage::Notification { method, params }) => {
-                    // Handle notifications...
-                    match method.as_str() {
-                        "exit" => ServerAction::Exit,
-                        "textDocument/didOpen" => match did_open_notification(&mut state, params) {
-                            Ok(_) => ServerAction::Ignore(state),
-                            Err(e) => {
-                                error!("An error ocurred while opening file: {:?}", e);
-                                ServerAction::Ignore(state)
-                            }
-                        },
-                        _ => {
-                            warn!("Unimplemented notification received! Ignoring...");
-                            ServerAction::Ignore(state)
-                        }
+                    match router.handle_notification(&mut state, &method, params) {
+                        notifications if notifications.is_empty() => ServerAction::Ignore(state),
+                        notifications => ServerAction::Notify(state, notifications),
                     }
                 }
             }
         }
         Err(e) => {
             error!("An error ocurred while recieving message! {:?}", e);
-            let response = ServerResponse::new_error(
-                None,
-                ResponseError::new(
-                    ErrorCodes::InvalidRequest,
-                    "An error occurred while trying to recieve a message!".into(),
-                ),
-            );
-
-            ServerAction::Respond(state, response)
+            match e.response() {
+                Some(response) => ServerAction::Respond(state, response),
+                None => ServerAction::Ignore(state),
+            }
         }
     }
 }
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::{
+    cst::{tokens_span, SyntaxKind, SyntaxNode, SyntaxToken, SyntaxTree, TextSpan},
+    MermaidToken,
+};
+
+/// All the data relevant to a class diagram.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
+pub struct ClassDiagram {
+    /// Every class of the diagram, declared explicitly or through a relation.
+    pub classes: Vec<ClassDefinition>,
+
+    /// Every relation between two classes, in order.
+    pub relations: Vec<ClassRelation>,
+
+    /// Namespaces that group classes.
+    pub namespaces: Vec<ClassNamespace>,
+}
+
+/// A class of the diagram.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct ClassDefinition {
+    /// The name of the class, the first place where it appears.
+    pub name: MermaidToken,
+
+    /// Attributes and methods of the class, in order.
+    pub members: Vec<MermaidToken>,
+
+    /// The span of the declaration, for classes with a body it's the whole block.
+    pub span: TextSpan,
+}
+
+/// A relation between two classes, like `Animal <|-- Duck`.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct ClassRelation {
+    pub from: MermaidToken,
+    pub to: MermaidToken,
+
+    /// The relation operator, like `<|--` or `*--`.
+    pub relation: MermaidToken,
+
+    /// The text after the `:`.
+    pub label: Option<MermaidToken>,
+}
+
+/// A `namespace X { ... }` block.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct ClassNamespace {
+    pub name: MermaidToken,
+    pub span: TextSpan,
+}
+
+/// A class diagram being parsed, with the position of each class by name.
+#[derive(Default)]
+struct ClassDiagramBuilder {
+    diagram: ClassDiagram,
+    classes: HashMap<String, usize>,
+}
+
+impl ClassDiagramBuilder {
+    /// Finds a class by name, creating it if it doesn't exist yet.
+    fn class_mut(
+        &mut self,
+        tree: &SyntaxTree,
+        name: TextSpan,
+        span: TextSpan,
+    ) -> &mut ClassDefinition {
+        let classes = &mut self.diagram.classes;
+        let index = *self
+            .classes
+            .entry(tree.slice(name).to_string())
+            .or_insert_with(|| {
+                classes.push(ClassDefinition {
+                    name: MermaidToken::new(tree, name),
+                    members: vec![],
+                    span,
+                });
+                classes.len() - 1
+            });
+        &mut classes[index]
+    }
+}
+
+/// Parses all the data relevant to a class diagram.
+pub fn parse_class_diagram(tree: &SyntaxTree) -> ClassDiagram {
+    let mut builder = ClassDiagramBuilder::default();
+    parse_statements(tree, tree.root(), &mut builder);
+    builder.diagram
+}
+
+/// Walks the statements below `node` and stores the data they define.
+fn parse_statements(tree: &SyntaxTree, node: &SyntaxNode, diagram: &mut ClassDiagramBuilder) {
+    for child in node.child_nodes() {
+        match child.kind() {
+            SyntaxKind::Statement => parse_statement(tree, child, diagram),
+            SyntaxKind::Block => {
+                let Some(start) = child.block_start() else {
+                    continue;
+                };
+                let tokens: Vec<_> = start.significant_tokens().collect();
+                match tokens.as_slice() {
+                    [keyword, name, ..] if keyword.is_ident("namespace") => {
+                        diagram.diagram.namespaces.push(ClassNamespace {
+                            name: MermaidToken::new(tree, name.span()),
+                            span: child.span(),
+                        });
+                        parse_statements(tree, child, diagram);
+                    }
+                    [keyword, name, ..] if keyword.is_ident("class") => {
+                        let class = diagram.class_mut(tree, name.span(), child.span());
+                        class.span = child.span();
+                        class.members.extend(
+                            child
+                                .child_nodes()
+                                .filter(|n| n.kind() == SyntaxKind::Statement)
+                                .filter(|n| !n.text().trim_start().starts_with("<<"))
+                                .filter_map(SyntaxNode::content_span)
+                                .map(|s| MermaidToken::new(tree, s)),
+                        );
+                    }
+                    _ => parse_statements(tree, child, diagram),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parses a single statement of a class diagram.
+fn parse_statement(tree: &SyntaxTree, statement: &SyntaxNode, diagram: &mut ClassDiagramBuilder) {
+    let tokens: Vec<_> = statement.significant_tokens().collect();
+    let Some(span) = statement.content_span() else {
+        return;
+    };
+
+    match tokens.as_slice() {
+        [keyword, name, ..] if keyword.is_ident("class") => {
+            diagram.class_mut(tree, name.span(), span);
+        }
+        [first, ..]
+            if matches!(
+                first.text(),
+                "note"
+                    | "classDef"
+                    | "style"
+                    | "cssClass"
+                    | "click"
+                    | "callback"
+                    | "link"
+                    | "direction"
+            ) => {}
+        // Members declared like `Animal : +int age`
+        [name, colon, rest @ ..] if colon.is_punct(':') && name.kind() == SyntaxKind::Ident => {
+            let member = tokens_span(rest).map(|s| MermaidToken::new(tree, s));
+            diagram
+                .class_mut(tree, name.span(), span)
+                .members
+                .extend(member);
+        }
+        _ => {
+            if let Some(relation) = parse_relation(tree, &tokens) {
+                diagram.class_mut(tree, relation.from.span, span);
+                diagram.class_mut(tree, relation.to.span, span);
+                diagram.diagram.relations.push(relation);
+            }
+        }
+    }
+}
+
+/// Checks if a token can be part of a relation operator like `<|--`, `*--` or `--o`.
+fn is_relation_part(token: &SyntaxToken) -> bool {
+    token.kind() == SyntaxKind::Arrow
+        || ['|', '*', '(', ')'].iter().any(|c| token.is_punct(*c))
+        || token.is_ident("o")
+}
+
+/// The first and the last token of the relation operator of a statement, like `<|--` or `*--`.
+pub(crate) fn relation_operator(tokens: &[&SyntaxToken]) -> Option<(usize, usize)> {
+    let arrow = tokens.iter().position(|t| {
+        t.kind() == SyntaxKind::Arrow && (t.text().contains('-') || t.text().contains(".."))
+    })?;
+
+    let adjacent = |a: &SyntaxToken, b: &SyntaxToken| a.span().end == b.span().start;
+    let mut start = arrow;
+    while start > 0
+        && is_relation_part(tokens[start - 1])
+        && adjacent(tokens[start - 1], tokens[start])
+    {
+        start -= 1;
+    }
+    let mut end = arrow;
+    while end + 1 < tokens.len()
+        && is_relation_part(tokens[end + 1])
+        && adjacent(tokens[end], tokens[end + 1])
+    {
+        end += 1;
+    }
+
+    Some((start, end))
+}
+
+/// Parses a relation like `Animal "1" <|-- "many" Duck : label`.
+fn parse_relation(tree: &SyntaxTree, tokens: &[&SyntaxToken]) -> Option<ClassRelation> {
+    let (start, end) = relation_operator(tokens)?;
+    let from = tokens[..start]
+        .iter()
+        .rev()
+        .find(|t| t.kind() == SyntaxKind::Ident)?;
+    let colon = tokens.iter().position(|t| t.is_punct(':'));
+    let to = tokens[end + 1..colon.unwrap_or(tokens.len()).max(end + 1)]
+        .iter()
+        .find(|t| t.kind() == SyntaxKind::Ident)?;
+    let label = colon.and_then(|c| tokens_span(&tokens[c + 1..]));
+
+    Some(ClassRelation {
+        from: MermaidToken::new(tree, from.span()),
+        to: MermaidToken::new(tree, to.span()),
+        relation: MermaidToken::new(tree, tokens[start].span().cover(tokens[end].span())),
+        label: label.map(|l| MermaidToken::new(tree, l)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIAGRAM: &str = r#"classDiagram
+    Animal <|-- Duck
+    Animal "1" *-- "many" Leg : has
+    Animal : +int age
+    class Duck {
+        <<interface>>
+        +String beakColor
+        +swim()
+    }
+    namespace Shapes {
+        class Square~Shape~
+    }
+"#;
+
+    #[test]
+    fn parse_class_diagram_success() {
+        let tree = SyntaxTree::parse(DIAGRAM.to_string());
+
+        let diagram = parse_class_diagram(&tree);
+
+        let classes: Vec<_> = diagram
+            .classes
+            .iter()
+            .map(|c| {
+                (
+                    c.name.content.as_str(),
+                    c.members
+                        .iter()
+                        .map(|m| m.content.as_str())
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            classes,
+            vec![
+                ("Animal", vec!["+int age"]),
+                ("Duck", vec!["+String beakColor", "+swim()"]),
+                ("Leg", vec![]),
+                ("Square", vec![]),
+            ]
+        );
+
+        let relations: Vec<_> = diagram
+            .relations
+            .iter()
+            .map(|r| {
+                (
+                    r.from.content.as_str(),
+                    r.relation.content.as_str(),
+                    r.to.content.as_str(),
+                    r.label.as_ref().map(|l| l.content.as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            relations,
+            vec![
+                ("Animal", "<|--", "Duck", None),
+                ("Animal", "*--", "Leg", Some("has")),
+            ]
+        );
+        assert_eq!(diagram.namespaces[0].name.content, "Shapes");
+    }
+}
@@ -0,0 +1,222 @@
+use serde::Serialize;
+
+use super::{
+    document_symbols, DiagramSymbolKind, MermaidAST, MermaidDiagramTypes, DIAGRAM_KEYWORDS,
+    GANTT_KEYWORDS, REFERENCE_KEYWORDS, SEQUENCE_BLOCK_KEYWORDS,
+};
+
+/// Keywords of flowchart statements.
+const FLOWCHART_KEYWORDS: [&str; 8] = [
+    "subgraph",
+    "end",
+    "direction",
+    "classDef",
+    "class",
+    "style",
+    "linkStyle",
+    "click",
+];
+
+/// Keywords of class diagram statements.
+const CLASS_KEYWORDS: [&str; 6] = [
+    "class",
+    "namespace",
+    "direction",
+    "classDef",
+    "style",
+    "note",
+];
+
+/// Keywords of state diagram statements.
+const STATE_KEYWORDS: [&str; 5] = ["state", "direction", "classDef", "class", "note"];
+
+/// Keywords of sequence diagram statements, besides its blocks and references.
+const SEQUENCE_KEYWORDS: [&str; 8] = [
+    "participant",
+    "actor",
+    "autonumber",
+    "note",
+    "else",
+    "and",
+    "option",
+    "end",
+];
+
+/// What a completion inserts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CompletionKind {
+    /// The type of a diagram, like `flowchart`.
+    Diagram,
+    Keyword,
+    /// A name declared in the diagram, like a node or a participant.
+    Symbol,
+}
+
+/// A word that can be inserted at the cursor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+
+    /// Extra information of the word, like the label of a node.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl Completion {
+    fn keyword(kind: CompletionKind, label: &str) -> Self {
+        Completion {
+            label: label.to_string(),
+            kind,
+            detail: None,
+        }
+    }
+}
+
+/// The keywords of the statements of a diagram type.
+fn diagram_keywords(d_type: &MermaidDiagramTypes) -> Vec<&'static str> {
+    match d_type {
+        MermaidDiagramTypes::Flowchart => FLOWCHART_KEYWORDS.to_vec(),
+        MermaidDiagramTypes::Sequence => [
+            &SEQUENCE_KEYWORDS[..],
+            &SEQUENCE_BLOCK_KEYWORDS[..],
+            &REFERENCE_KEYWORDS[..],
+        ]
+        .concat(),
+        MermaidDiagramTypes::Class => CLASS_KEYWORDS.to_vec(),
+        MermaidDiagramTypes::State => STATE_KEYWORDS.to_vec(),
+        MermaidDiagramTypes::Gantt => [&GANTT_KEYWORDS[..], &["section"]].concat(),
+        _ => vec![],
+    }
+}
+
+/// The part of the word before `offset`, that completions have to start with.
+fn word_before(text: &str, offset: usize) -> &str {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    let before = &text[..offset];
+    let start = before
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+        .map_or(0, |i| {
+            i + before[i..].chars().next().map_or(1, char::len_utf8)
+        });
+    &before[start..]
+}
+
+/// The completions at a byte offset of a document.
+///
+/// Up to the line that declares the diagram, the completions are the types of diagrams. After it,
+/// they're the keywords of its type and the names declared in the diagram. Only the ones that start
+/// with the word before the offset are kept, and nothing is completed inside the frontmatter.
+pub fn completions(ast: &MermaidAST, offset: usize) -> Vec<Completion> {
+    let tree = &ast.cst;
+    let text = tree.text();
+    if tree
+        .frontmatter()
+        .is_some_and(|f| f.span().start <= offset && offset < f.span().end)
+    {
+        return vec![];
+    }
+
+    let prefix = word_before(text, offset);
+    let in_header = match tree.diagram_header() {
+        Some(header) => {
+            let start = header.span().start;
+            let line_end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+            offset <= line_end
+        }
+        None => true,
+    };
+
+    let completions: Vec<Completion> = match in_header {
+        true => DIAGRAM_KEYWORDS
+            .iter()
+            .map(|k| Completion::keyword(CompletionKind::Diagram, k))
+            .collect(),
+        false => {
+            let keywords = diagram_keywords(&ast.diagram.d_type)
+                .into_iter()
+                .map(|k| Completion::keyword(CompletionKind::Keyword, k));
+            let symbols = document_symbols(ast);
+            let symbols = symbols
+                .iter()
+                .flat_map(|s| s.flatten())
+                .filter(|s| {
+                    !matches!(
+                        s.kind,
+                        DiagramSymbolKind::Title | DiagramSymbolKind::Diagram
+                    )
+                })
+                // The word being typed is already parsed as a symbol.
+                .filter(|s| !s.selection_span.contains_offset(offset))
+                .map(|s| Completion {
+                    label: s.name.clone(),
+                    kind: CompletionKind::Symbol,
+                    detail: s.detail.clone(),
+                });
+            keywords.chain(symbols).collect()
+        }
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    completions
+        .into_iter()
+        .filter(|c| c.label.starts_with(prefix) && seen.insert(c.label.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(text: &str, offset: usize) -> Vec<String> {
+        let ast = MermaidAST::from_content(text.to_string());
+        completions(&ast, offset)
+            .into_iter()
+            .map(|c| c.label)
+            .collect()
+    }
+
+    #[test]
+    fn complete_diagram_types() {
+        assert_eq!(labels("", 0).len(), DIAGRAM_KEYWORDS.len());
+        assert_eq!(labels("flo", 3), vec!["flowchart"]);
+        assert_eq!(labels("state", 5), vec!["stateDiagram", "stateDiagram-v2"]);
+        assert!(labels("---\ntitle: Flow\n---\n", 6).is_empty());
+    }
+
+    #[test]
+    fn complete_diagram_body() {
+        let text = "flowchart TD\n    start[Start] --> stop\n    s";
+        let completions = completions(&MermaidAST::from_content(text.to_string()), text.len());
+
+        assert_eq!(
+            completions,
+            vec![
+                Completion::keyword(CompletionKind::Keyword, "subgraph"),
+                Completion::keyword(CompletionKind::Keyword, "style"),
+                Completion {
+                    label: "start".into(),
+                    kind: CompletionKind::Symbol,
+                    detail: Some("Start".into()),
+                },
+                Completion::keyword(CompletionKind::Symbol, "stop"),
+            ]
+        );
+
+        let text = "sequenceDiagram\n    participant Alice\n    l";
+        assert_eq!(labels(text, text.len()), vec!["loop", "links"]);
+    }
+
+    #[test]
+    fn word_before_offset() {
+        assert_eq!(word_before("A --> stateDi", 13), "stateDi");
+        assert_eq!(word_before("A --> ", 6), "");
+        assert_eq!(word_before("é_b", 4), "é_b");
+        assert_eq!(word_before("aé", 2), "a");
+    }
+}
@@ -0,0 +1,1002 @@
+use serde::Serialize;
+
+use crate::jsonrpc::{Position, Range};
+
+use super::{
+    diagram_body::parse_diagram_type, lexer::lex_line, line_index::LineIndex, MermaidDiagramTypes,
+};
+
+/// A range of bytes inside a document, `start` is inclusive and `end` is exclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct TextSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl TextSpan {
+    /// Creates a new span, `start` must be less or equal than `end`.
+    pub fn new(start: usize, end: usize) -> Self {
+        debug_assert!(start <= end, "Invalid span {}..{}", start, end);
+        TextSpan { start, end }
+    }
+
+    /// The length of the span in bytes.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Checks if the span doesn't contain any bytes.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Checks if `other` is completely inside this span.
+    pub fn contains_span(&self, other: TextSpan) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    /// Checks if the byte offset is inside this span, the end of the span is included.
+    pub fn contains_offset(&self, offset: usize) -> bool {
+        self.start <= offset && offset <= self.end
+    }
+
+    /// Checks if both spans share at least one byte or touch each other.
+    pub fn touches(&self, other: TextSpan) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    /// The smallest span that contains both spans.
+    pub fn cover(&self, other: TextSpan) -> Self {
+        TextSpan {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// Moves the span by the given amount of bytes.
+    pub fn shifted(&self, delta: isize) -> Self {
+        TextSpan {
+            start: self.start.saturating_add_signed(delta),
+            end: self.end.saturating_add_signed(delta),
+        }
+    }
+}
+
+/// The kind of an element from the concrete syntax tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    // Tokens
+    /// Spaces and tabs.
+    Whitespace,
+    /// A line terminator, either `\n` or `\r\n`.
+    Newline,
+    /// A `%%` comment until the end of the line.
+    Comment,
+    /// A `%%{ ... }%%` directive.
+    Directive,
+    /// The `---` line that opens or closes a frontmatter.
+    FrontmatterDelimiter,
+    /// The content of a line inside the frontmatter.
+    FrontmatterText,
+    /// A sequence of alphanumeric characters or `_`.
+    Ident,
+    /// A double quoted string.
+    String,
+    /// A sequence of arrow characters like `-->` or `==>`.
+    Arrow,
+    /// A single ASCII punctuation character.
+    Punct,
+    /// Any other character.
+    Text,
+
+    // Nodes
+    /// The root of the whole document.
+    Root,
+    /// The `---` delimited block at the start of the document.
+    Frontmatter,
+    /// The line that declares the type of the diagram, like `flowchart TD`.
+    DiagramHeader,
+    /// A line with a statement of the diagram.
+    Statement,
+    /// A group of statements, like `subgraph ... end` or `class A { ... }`.
+    Block,
+    /// The statement that opens a `Block`.
+    BlockStart,
+    /// The statement that closes a `Block`, like `end` or `}`.
+    BlockEnd,
+    /// A statement that doesn't belong where it appears, like an `end` without a block.
+    Error,
+}
+
+impl SyntaxKind {
+    /// Checks if this kind doesn't affect the meaning of a diagram.
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Whitespace
+                | SyntaxKind::Newline
+                | SyntaxKind::Comment
+                | SyntaxKind::Directive
+        )
+    }
+}
+
+/// A leaf of the concrete syntax tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    kind: SyntaxKind,
+    text: String,
+    span: TextSpan,
+}
+
+impl SyntaxToken {
+    pub fn new(kind: SyntaxKind, text: String, span: TextSpan) -> Self {
+        SyntaxToken { kind, text, span }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn span(&self) -> TextSpan {
+        self.span
+    }
+
+    /// Checks if this token is whitespace, a newline or a comment.
+    pub fn is_trivia(&self) -> bool {
+        self.kind.is_trivia()
+    }
+
+    /// Checks if this token is a punctuation character equal to `c`.
+    pub fn is_punct(&self, c: char) -> bool {
+        self.kind == SyntaxKind::Punct && self.text.starts_with(c)
+    }
+
+    /// Checks if this token is the identifier `ident`.
+    pub fn is_ident(&self, ident: &str) -> bool {
+        self.kind == SyntaxKind::Ident && self.text == ident
+    }
+}
+
+/// An inner node of the concrete syntax tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxNode {
+    kind: SyntaxKind,
+    span: TextSpan,
+    children: Vec<SyntaxElement>,
+}
+
+/// A child of a `SyntaxNode`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            SyntaxElement::Node(n) => n.kind,
+            SyntaxElement::Token(t) => t.kind,
+        }
+    }
+
+    pub fn span(&self) -> TextSpan {
+        match self {
+            SyntaxElement::Node(n) => n.span,
+            SyntaxElement::Token(t) => t.span,
+        }
+    }
+
+    pub fn as_node(&self) -> Option<&SyntaxNode> {
+        match self {
+            SyntaxElement::Node(n) => Some(n),
+            SyntaxElement::Token(_) => None,
+        }
+    }
+
+    pub fn as_token(&self) -> Option<&SyntaxToken> {
+        match self {
+            SyntaxElement::Node(_) => None,
+            SyntaxElement::Token(t) => Some(t),
+        }
+    }
+
+    /// Moves the element and everything inside it by the given amount of bytes.
+    pub(crate) fn shift(&mut self, delta: isize) {
+        match self {
+            SyntaxElement::Node(n) => {
+                n.span = n.span.shifted(delta);
+                n.children.iter_mut().for_each(|c| c.shift(delta));
+            }
+            SyntaxElement::Token(t) => t.span = t.span.shifted(delta),
+        }
+    }
+}
+
+impl SyntaxNode {
+    /// Creates a new node, its span goes from the start of its first child to the end of the last one.
+    ///
+    /// A node without children is placed at `offset`.
+    pub fn new(kind: SyntaxKind, children: Vec<SyntaxElement>, offset: usize) -> Self {
+        let span = match (children.first(), children.last()) {
+            (Some(first), Some(last)) => TextSpan::new(first.span().start, last.span().end),
+            _ => TextSpan::new(offset, offset),
+        };
+
+        SyntaxNode {
+            kind,
+            span,
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn span(&self) -> TextSpan {
+        self.span
+    }
+
+    pub fn children(&self) -> &[SyntaxElement] {
+        &self.children
+    }
+
+    /// Iterates over the direct children that are nodes.
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(SyntaxElement::as_node)
+    }
+
+    /// Iterates over the direct children that are tokens.
+    pub fn child_tokens(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.children.iter().filter_map(SyntaxElement::as_token)
+    }
+
+    /// Iterates over every node and token below this node in preorder.
+    pub fn descendants(&self) -> Descendants<'_> {
+        Descendants {
+            stack: self.children.iter().rev().collect(),
+        }
+    }
+
+    /// Iterates over every token below this node in order.
+    pub fn tokens(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.descendants().filter_map(SyntaxElement::as_token)
+    }
+
+    /// Iterates over every token below this node that isn't trivia.
+    pub fn significant_tokens(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.tokens().filter(|t| !t.is_trivia())
+    }
+
+    /// The span from the first to the last significant token of this node.
+    pub fn content_span(&self) -> Option<TextSpan> {
+        let mut tokens = self.significant_tokens();
+        let first = tokens.next()?;
+        let last = tokens.last().unwrap_or(first);
+        Some(TextSpan::new(first.span.start, last.span.end))
+    }
+
+    /// The exact source text covered by this node.
+    pub fn text(&self) -> String {
+        self.tokens().map(SyntaxToken::text).collect()
+    }
+
+    /// The opening statement of a `Block` node.
+    pub fn block_start(&self) -> Option<&SyntaxNode> {
+        self.child_nodes()
+            .find(|n| n.kind == SyntaxKind::BlockStart)
+    }
+
+    /// The closing statement of a `Block` node, `None` if the block was never closed.
+    pub fn block_end(&self) -> Option<&SyntaxNode> {
+        self.child_nodes().find(|n| n.kind == SyntaxKind::BlockEnd)
+    }
+
+    pub(crate) fn into_children(self) -> Vec<SyntaxElement> {
+        self.children
+    }
+
+    /// The leading whitespace of a line based node like a `Statement`.
+    pub fn indentation(&self) -> &str {
+        match self.children.first() {
+            Some(SyntaxElement::Token(t)) if t.kind == SyntaxKind::Whitespace => &t.text,
+            _ => "",
+        }
+    }
+}
+
+/// Preorder iterator over the descendants of a `SyntaxNode`.
+pub struct Descendants<'a> {
+    stack: Vec<&'a SyntaxElement>,
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = &'a SyntaxElement;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let element = self.stack.pop()?;
+        if let SyntaxElement::Node(n) = element {
+            self.stack.extend(n.children.iter().rev());
+        }
+        Some(element)
+    }
+}
+
+/// A lossless concrete syntax tree of a mermaid document.
+///
+/// Every byte of the document, including whitespace and comments, belongs to a
+/// token of the tree, so the original text can always be rebuilt from it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyntaxTree {
+    text: String,
+    root: SyntaxNode,
+    line_index: LineIndex,
+}
+
+impl Default for SyntaxNode {
+    fn default() -> Self {
+        SyntaxNode::new(SyntaxKind::Root, vec![], 0)
+    }
+}
+
+impl SyntaxTree {
+    /// Parses the whole content of a mermaid document.
+    pub fn parse(text: String) -> Self {
+        let root = parse_root(&text);
+        let line_index = LineIndex::new(&text);
+
+        SyntaxTree {
+            text,
+            root,
+            line_index,
+        }
+    }
+
+    /// Creates a tree from a text, the root node that was parsed from it and the index of its lines.
+    pub(crate) fn from_parts(text: String, root: SyntaxNode, line_index: LineIndex) -> Self {
+        SyntaxTree {
+            text,
+            root,
+            line_index,
+        }
+    }
+
+    pub(crate) fn into_parts(self) -> (String, SyntaxNode, LineIndex) {
+        (self.text, self.root, self.line_index)
+    }
+
+    pub fn root(&self) -> &SyntaxNode {
+        &self.root
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn line_index(&self) -> &LineIndex {
+        &self.line_index
+    }
+
+    /// The frontmatter node of the document, if any.
+    pub fn frontmatter(&self) -> Option<&SyntaxNode> {
+        self.root
+            .child_nodes()
+            .find(|n| n.kind == SyntaxKind::Frontmatter)
+    }
+
+    /// The line that declares the diagram type, if any.
+    pub fn diagram_header(&self) -> Option<&SyntaxNode> {
+        self.root
+            .child_nodes()
+            .find(|n| n.kind == SyntaxKind::DiagramHeader)
+    }
+
+    /// The type of the diagram declared in the document.
+    pub fn diagram_type(&self) -> MermaidDiagramTypes {
+        self.diagram_header()
+            .map(|h| parse_diagram_type(&self.text[h.span.start..h.span.end]))
+            .unwrap_or_default()
+    }
+
+    /// The source text inside a span.
+    pub fn slice(&self, span: TextSpan) -> &str {
+        &self.text[span.start..span.end]
+    }
+
+    /// Converts a byte offset into an LSP position.
+    pub fn position(&self, offset: usize) -> Position {
+        self.line_index.position(&self.text, offset)
+    }
+
+    /// Converts a span into an LSP range.
+    pub fn range(&self, span: TextSpan) -> Range {
+        Range {
+            start: self.position(span.start),
+            end: self.position(span.end),
+        }
+    }
+
+    /// Converts an LSP position into a byte offset.
+    pub fn offset(&self, position: Position) -> usize {
+        self.line_index.offset(&self.text, position)
+    }
+
+    /// Converts an LSP range into a span.
+    pub fn span(&self, range: Range) -> TextSpan {
+        let start = self.offset(range.start);
+        TextSpan::new(start, self.offset(range.end).max(start))
+    }
+}
+
+/// The span from the first to the last token, `None` if there are no tokens.
+pub(crate) fn tokens_span(tokens: &[&SyntaxToken]) -> Option<TextSpan> {
+    let first = tokens.first()?;
+    let last = tokens.last()?;
+    Some(first.span().cover(last.span()))
+}
+
+/// A line of the document already split into tokens.
+pub(crate) struct Line {
+    pub tokens: Vec<SyntaxToken>,
+    pub span: TextSpan,
+}
+
+impl Line {
+    /// Checks if the line only contains whitespace and comments.
+    fn is_trivia(&self) -> bool {
+        self.tokens.iter().all(SyntaxToken::is_trivia)
+    }
+
+    fn significant(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.tokens.iter().filter(|t| !t.is_trivia())
+    }
+
+    /// The width of the leading whitespace of the line.
+    fn indentation(&self) -> usize {
+        match self.tokens.first() {
+            Some(t) if t.kind == SyntaxKind::Whitespace => t.text.chars().count(),
+            _ => 0,
+        }
+    }
+
+    fn into_elements(self) -> impl Iterator<Item = SyntaxElement> {
+        self.tokens.into_iter().map(SyntaxElement::Token)
+    }
+}
+
+/// Splits a text into lines, `offset` is the byte offset where the text starts.
+pub(crate) fn split_lines(text: &str, offset: usize) -> impl Iterator<Item = (usize, &str)> {
+    text.split_inclusive('\n').scan(offset, |start, line| {
+        let line_start = *start;
+        *start += line.len();
+        Some((line_start, line))
+    })
+}
+
+/// Splits and tokenizes the lines of a text, `offset` is the byte offset where the text starts.
+pub(crate) fn lex_lines(text: &str, offset: usize) -> Vec<Line> {
+    split_lines(text, offset)
+        .map(|(start, line)| Line {
+            tokens: lex_line(line, start),
+            span: TextSpan::new(start, start + line.len()),
+        })
+        .collect()
+}
+
+/// Parses the whole document into a `Root` node.
+fn parse_root(text: &str) -> SyntaxNode {
+    let mut lines = split_lines(text, 0).peekable();
+    let mut children = vec![];
+
+    // Blank lines before the frontmatter
+    while let Some((start, line)) = lines.next_if(|(_, l)| l.trim().is_empty()) {
+        children.extend(lex_line(line, start).into_iter().map(SyntaxElement::Token));
+    }
+
+    let rest = match lines.peek() {
+        Some((start, _)) => *start,
+        None => text.len(),
+    };
+    let rest = match parse_frontmatter(&text[rest..], rest) {
+        Some((frontmatter, end)) => {
+            children.push(SyntaxElement::Node(frontmatter));
+            end
+        }
+        None => rest,
+    };
+
+    let mut lines = lex_lines(&text[rest..], rest).into_iter().peekable();
+    while let Some(line) = lines.next_if(Line::is_trivia) {
+        children.extend(line.into_elements());
+    }
+
+    let d_type = match lines.next() {
+        Some(line) => {
+            let header_text = &text[line.span.start..line.span.end];
+            let d_type = parse_diagram_type(header_text);
+            let offset = line.span.start;
+            children.push(SyntaxElement::Node(SyntaxNode::new(
+                SyntaxKind::DiagramHeader,
+                line.into_elements().collect(),
+                offset,
+            )));
+            d_type
+        }
+        None => MermaidDiagramTypes::Unknown,
+    };
+
+    children.extend(parse_body(&d_type, lines.collect()));
+    SyntaxNode::new(SyntaxKind::Root, children, 0)
+}
+
+/// Attempts to parse a `---` delimited frontmatter at the start of `text`.
+///
+/// Returns the node and the byte offset where the frontmatter ends. A
+/// frontmatter that is never closed is not considered a frontmatter.
+fn parse_frontmatter(text: &str, offset: usize) -> Option<(SyntaxNode, usize)> {
+    let mut lines = split_lines(text, offset);
+    let (start, first) = lines.next()?;
+    if first.trim_end() != "---" {
+        return None;
+    }
+
+    let mut children = delimiter_tokens(first, start);
+    for (start, line) in lines {
+        if line.trim_end() == "---" {
+            children.extend(delimiter_tokens(line, start));
+            let end = start + line.len();
+            return Some((
+                SyntaxNode::new(SyntaxKind::Frontmatter, children, offset),
+                end,
+            ));
+        }
+
+        let content = line.trim_end_matches(['\r', '\n']);
+        if !content.is_empty() {
+            children.push(SyntaxElement::Token(SyntaxToken::new(
+                SyntaxKind::FrontmatterText,
+                content.to_string(),
+                TextSpan::new(start, start + content.len()),
+            )));
+        }
+        children.extend(newline_token(line, start));
+    }
+
+    None
+}
+
+/// Tokens of a `---` line, including trailing whitespace and the line terminator.
+fn delimiter_tokens(line: &str, offset: usize) -> Vec<SyntaxElement> {
+    let mut tokens = vec![SyntaxElement::Token(SyntaxToken::new(
+        SyntaxKind::FrontmatterDelimiter,
+        "---".to_string(),
+        TextSpan::new(offset, offset + 3),
+    ))];
+
+    let rest = &line[3..];
+    let content = rest.trim_end_matches(['\r', '\n']);
+    if !content.is_empty() {
+        tokens.push(SyntaxElement::Token(SyntaxToken::new(
+            SyntaxKind::Whitespace,
+            content.to_string(),
+            TextSpan::new(offset + 3, offset + 3 + content.len()),
+        )));
+    }
+    tokens.extend(newline_token(line, offset));
+    tokens
+}
+
+/// The line terminator of a line as a token, if it has one.
+fn newline_token(line: &str, offset: usize) -> Option<SyntaxElement> {
+    let content_len = line.trim_end_matches(['\r', '\n']).len();
+    (content_len < line.len()).then(|| {
+        SyntaxElement::Token(SyntaxToken::new(
+            SyntaxKind::Newline,
+            line[content_len..].to_string(),
+            TextSpan::new(offset + content_len, offset + line.len()),
+        ))
+    })
+}
+
+/// How a block of statements is closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockCloser {
+    /// Closed by an `end` keyword, like flowchart subgraphs.
+    End,
+    /// Closed by a `}`, like class bodies.
+    Brace,
+    /// Closed by the next section or the end of the document, like gantt sections.
+    Section,
+    /// Closed by a line with less or equal indentation, like mindmap nodes.
+    Indentation(usize),
+}
+
+/// What a line of the diagram body means for the block structure.
+enum LineRole {
+    Statement,
+    Open(BlockCloser),
+    Close(BlockCloser),
+}
+
+/// A block that hasn't been closed yet.
+struct OpenBlock {
+    closer: BlockCloser,
+    start: Line,
+    children: Vec<SyntaxElement>,
+}
+
+impl OpenBlock {
+    /// Converts this block into the elements it represents.
+    ///
+    /// Blocks closed by indentation without any statements are plain statements.
+    fn finish(self, end: Option<Line>) -> Vec<SyntaxElement> {
+        let is_leaf = matches!(self.closer, BlockCloser::Indentation(_))
+            && self.children.iter().all(|c| c.kind().is_trivia());
+
+        if is_leaf {
+            let offset = self.start.span.start;
+            let statement = SyntaxNode::new(
+                SyntaxKind::Statement,
+                self.start.into_elements().collect(),
+                offset,
+            );
+            return std::iter::once(SyntaxElement::Node(statement))
+                .chain(self.children)
+                .collect();
+        }
+
+        let offset = self.start.span.start;
+        let mut children = vec![SyntaxElement::Node(SyntaxNode::new(
+            SyntaxKind::BlockStart,
+            self.start.into_elements().collect(),
+            offset,
+        ))];
+        children.extend(self.children);
+        if let Some(end) = end {
+            let offset = end.span.start;
+            children.push(SyntaxElement::Node(SyntaxNode::new(
+                SyntaxKind::BlockEnd,
+                end.into_elements().collect(),
+                offset,
+            )));
+        }
+
+        vec![SyntaxElement::Node(SyntaxNode::new(
+            SyntaxKind::Block,
+            children,
+            offset,
+        ))]
+    }
+}
+
+/// Keywords that open a block closed by `end` in a sequence diagram.
+pub(crate) const SEQUENCE_BLOCK_KEYWORDS: [&str; 9] = [
+    "loop", "alt", "opt", "par", "par_over", "critical", "break", "rect", "box",
+];
+
+/// Decides the role of a non trivia line inside the body of a diagram.
+fn line_role(d_type: &MermaidDiagramTypes, line: &Line) -> LineRole {
+    let mut significant = line.significant();
+    let first = significant.next();
+    let is_single = significant.next().is_none();
+    let last = line.significant().last();
+
+    let is_end = first.is_some_and(|t| t.is_ident("end"))
+        && line.significant().skip(1).all(|t| t.is_punct(';'));
+    let opens_brace = last.is_some_and(|t| t.is_punct('{'));
+    let closes_brace = is_single && first.is_some_and(|t| t.is_punct('}'));
+
+    match d_type {
+        MermaidDiagramTypes::Flowchart if first.is_some_and(|t| t.is_ident("subgraph")) => {
+            LineRole::Open(BlockCloser::End)
+        }
+        MermaidDiagramTypes::Flowchart | MermaidDiagramTypes::Sequence if is_end => {
+            LineRole::Close(BlockCloser::End)
+        }
+        MermaidDiagramTypes::Sequence
+            if first.is_some_and(|t| {
+                t.kind == SyntaxKind::Ident && SEQUENCE_BLOCK_KEYWORDS.contains(&t.text.as_str())
+            }) =>
+        {
+            LineRole::Open(BlockCloser::End)
+        }
+        MermaidDiagramTypes::Class
+        | MermaidDiagramTypes::State
+        | MermaidDiagramTypes::EntityRelationship
+        | MermaidDiagramTypes::Requirement
+            if opens_brace =>
+        {
+            LineRole::Open(BlockCloser::Brace)
+        }
+        MermaidDiagramTypes::Class
+        | MermaidDiagramTypes::State
+        | MermaidDiagramTypes::EntityRelationship
+        | MermaidDiagramTypes::Requirement
+            if closes_brace =>
+        {
+            LineRole::Close(BlockCloser::Brace)
+        }
+        MermaidDiagramTypes::Gantt
+        | MermaidDiagramTypes::UserJourney
+        | MermaidDiagramTypes::Timeline
+            if first.is_some_and(|t| t.is_ident("section")) =>
+        {
+            LineRole::Open(BlockCloser::Section)
+        }
+        MermaidDiagramTypes::Mindmap => {
+            LineRole::Open(BlockCloser::Indentation(line.indentation()))
+        }
+        _ => LineRole::Statement,
+    }
+}
+
+/// Parses the lines after the diagram header into statements and blocks.
+pub(crate) fn parse_body(d_type: &MermaidDiagramTypes, lines: Vec<Line>) -> Vec<SyntaxElement> {
+    let mut parser = BodyParser::new(d_type);
+    lines.into_iter().for_each(|line| parser.push_line(line));
+    parser.finish()
+}
+
+/// Parser that builds the statements and blocks of a diagram body line by line.
+pub(crate) struct BodyParser<'a> {
+    d_type: &'a MermaidDiagramTypes,
+    root: Vec<SyntaxElement>,
+    stack: Vec<OpenBlock>,
+}
+
+impl<'a> BodyParser<'a> {
+    pub fn new(d_type: &'a MermaidDiagramTypes) -> Self {
+        BodyParser {
+            d_type,
+            root: vec![],
+            stack: vec![],
+        }
+    }
+
+    /// Adds the elements to the innermost open block.
+    fn push(&mut self, elements: Vec<SyntaxElement>) {
+        match self.stack.last_mut() {
+            Some(block) => block.children.extend(elements),
+            None => self.root.extend(elements),
+        }
+    }
+
+    /// Closes the innermost open block.
+    fn close(&mut self, end: Option<Line>) {
+        if let Some(block) = self.stack.pop() {
+            let elements = block.finish(end);
+            self.push(elements);
+        }
+    }
+
+    /// Parses the next line of the body.
+    pub fn push_line(&mut self, line: Line) {
+        if line.is_trivia() {
+            self.push(line.into_elements().collect());
+            return;
+        }
+
+        match line_role(self.d_type, &line) {
+            LineRole::Statement => {
+                let offset = line.span.start;
+                let statement = SyntaxNode::new(
+                    SyntaxKind::Statement,
+                    line.into_elements().collect(),
+                    offset,
+                );
+                self.push(vec![SyntaxElement::Node(statement)]);
+            }
+            LineRole::Open(closer) => {
+                let closes_previous = |b: &OpenBlock| match (b.closer, closer) {
+                    (BlockCloser::Section, BlockCloser::Section) => true,
+                    (BlockCloser::Indentation(open), BlockCloser::Indentation(new)) => open >= new,
+                    _ => false,
+                };
+                while self.stack.last().is_some_and(closes_previous) {
+                    self.close(None);
+                }
+
+                self.stack.push(OpenBlock {
+                    closer,
+                    start: line,
+                    children: vec![],
+                });
+            }
+            LineRole::Close(closer) => match self.stack.iter().rposition(|b| b.closer == closer) {
+                Some(position) => {
+                    // Blocks opened after the one being closed were never closed.
+                    while self.stack.len() > position + 1 {
+                        self.close(None);
+                    }
+                    self.close(Some(line));
+                }
+                None => {
+                    let offset = line.span.start;
+                    let error =
+                        SyntaxNode::new(SyntaxKind::Error, line.into_elements().collect(), offset);
+                    self.push(vec![SyntaxElement::Node(error)]);
+                }
+            },
+        }
+    }
+
+    /// Checks if the parser could continue with `next`, an element that was parsed
+    /// at the top level of the body with every block closed, and get the same result.
+    pub fn can_resume_at(&self, next: &SyntaxElement) -> bool {
+        let is_section = next
+            .as_node()
+            .is_some_and(|n| n.kind == SyntaxKind::Block && n.block_end().is_none());
+
+        self.stack
+            .iter()
+            .all(|b| b.closer == BlockCloser::Section && is_section)
+    }
+
+    /// Closes every block that remains open and returns the parsed elements.
+    pub fn finish(mut self) -> Vec<SyntaxElement> {
+        while !self.stack.is_empty() {
+            self.close(None);
+        }
+        self.root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLOWCHART: &str = r#"---
+title: Test title
+---
+%% A comment before the header
+flowchart TD
+    A[Start] --> B{Is it?}
+    subgraph one [The first one]
+        B -- Yes --> C[OK]
+
+        %% Comment inside the subgraph
+        C --> D[Rethink]
+    end
+    D --> B
+    B -- No ----> E[End]
+"#;
+
+    fn node_kinds(node: &SyntaxNode) -> Vec<SyntaxKind> {
+        node.child_nodes().map(SyntaxNode::kind).collect()
+    }
+
+    #[test]
+    fn parse_is_lossless() {
+        let inputs = [
+            FLOWCHART,
+            "",
+            "\n\n",
+            "---\ntitle: unclosed\n",
+            "flowchart LR\r\n  A-->B\r\n  end\r\n",
+            "mindmap\n  root\n    a\n      b\n    c\n",
+            "classDiagram\nclass A {\n  +int x\n}\n}",
+        ];
+
+        for input in inputs {
+            let tree = SyntaxTree::parse(input.to_string());
+            assert_eq!(tree.root().text(), input);
+            assert_eq!(tree.root().span(), TextSpan::new(0, input.len()));
+        }
+    }
+
+    #[test]
+    fn parse_flowchart_structure() {
+        let tree = SyntaxTree::parse(FLOWCHART.to_string());
+
+        assert_eq!(
+            node_kinds(tree.root()),
+            vec![
+                SyntaxKind::Frontmatter,
+                SyntaxKind::DiagramHeader,
+                SyntaxKind::Statement,
+                SyntaxKind::Block,
+                SyntaxKind::Statement,
+                SyntaxKind::Statement,
+            ]
+        );
+        assert_eq!(tree.diagram_type(), MermaidDiagramTypes::Flowchart);
+
+        let block = tree
+            .root()
+            .child_nodes()
+            .find(|n| n.kind() == SyntaxKind::Block)
+            .unwrap();
+        assert_eq!(
+            node_kinds(block),
+            vec![
+                SyntaxKind::BlockStart,
+                SyntaxKind::Statement,
+                SyntaxKind::Statement,
+                SyntaxKind::BlockEnd,
+            ]
+        );
+        assert_eq!(block.block_end().unwrap().text().trim(), "end");
+        assert!(block
+            .tokens()
+            .any(|t| t.text() == "%% Comment inside the subgraph"));
+    }
+
+    #[test]
+    fn parse_unclosed_and_stray_blocks() {
+        let tree = SyntaxTree::parse("flowchart TD\nend\nsubgraph A\nB --> C\n".to_string());
+
+        assert_eq!(
+            node_kinds(tree.root()),
+            vec![
+                SyntaxKind::DiagramHeader,
+                SyntaxKind::Error,
+                SyntaxKind::Block
+            ]
+        );
+        let block = tree.root().child_nodes().last().unwrap();
+        assert!(block.block_end().is_none());
+    }
+
+    #[test]
+    fn parse_mindmap_indentation() {
+        let tree = SyntaxTree::parse("mindmap\n  root\n    a\n      b\n    c\n".to_string());
+
+        let root_block = tree.root().child_nodes().last().unwrap();
+        assert_eq!(root_block.kind(), SyntaxKind::Block);
+        assert_eq!(
+            node_kinds(root_block),
+            vec![
+                SyntaxKind::BlockStart,
+                SyntaxKind::Block,
+                SyntaxKind::Statement
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_gantt_sections() {
+        let tree = SyntaxTree::parse(
+            "gantt\n    title A\n    section One\n    a :a1, 2024-01-01, 1d\n    section Two\n    b :after a1, 1d\n"
+                .to_string(),
+        );
+
+        assert_eq!(
+            node_kinds(tree.root()),
+            vec![
+                SyntaxKind::DiagramHeader,
+                SyntaxKind::Statement,
+                SyntaxKind::Block,
+                SyntaxKind::Block
+            ]
+        );
+    }
+
+    #[test]
+    fn range_of_span() {
+        let tree = SyntaxTree::parse(FLOWCHART.to_string());
+        let header = tree.diagram_header().unwrap();
+
+        let range = tree.range(header.span());
+
+        assert_eq!(
+            range.start,
+            Position {
+                line: 4,
+                character: 0
+            }
+        );
+        assert_eq!(
+            range.end,
+            Position {
+                line: 5,
+                character: 0
+            }
+        );
+    }
+}
@@ -0,0 +1,539 @@
+use std::collections::HashSet;
+
+use super::{
+    cst::{split_lines, SyntaxKind, SyntaxTree, TextSpan},
+    diagram_body::parse_diagram_type,
+    lint::{apply_suppressions, lint, LintConfig},
+    DiagramData, MermaidAST, MermaidDiagramTypes, MermaidToken, ParseHeaderErrors,
+    DIAGRAM_KEYWORDS,
+};
+
+/// Characters that break the label of a flowchart node unless it's quoted.
+const RESERVED_LABEL_CHARS: [char; 7] = ['(', ')', '[', ']', '{', '}', '|'];
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+/// The kind of problem found by a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticCode {
+    /// A `subgraph`, `loop` or `{` that is never closed.
+    UnclosedBlock,
+    /// An `end` or `}` without a block to close.
+    UnexpectedBlockEnd,
+    /// The first line doesn't declare a known diagram type.
+    UnknownDiagramType,
+    /// The `---` delimiters of the frontmatter are malformed.
+    InvalidFrontmatter,
+    /// A sequence participant that is used but not declared with the others.
+    UndeclaredParticipant,
+    /// A label with characters that must be quoted.
+    UnquotedLabel,
+    /// A violation of the lint rule with the given code.
+    Lint(&'static str),
+    /// A suppression comment that doesn't silence any lint diagnostic.
+    UnusedSuppression,
+}
+
+impl DiagnosticCode {
+    /// The code shown to users, like `unclosed-block`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticCode::UnclosedBlock => "unclosed-block",
+            DiagnosticCode::UnexpectedBlockEnd => "unexpected-block-end",
+            DiagnosticCode::UnknownDiagramType => "unknown-diagram-type",
+            DiagnosticCode::InvalidFrontmatter => "invalid-frontmatter",
+            DiagnosticCode::UndeclaredParticipant => "undeclared-participant",
+            DiagnosticCode::UnquotedLabel => "unquoted-label",
+            DiagnosticCode::Lint(code) => code,
+            DiagnosticCode::UnusedSuppression => "unused-suppression",
+        }
+    }
+}
+
+/// A replacement of some text of the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagramEdit {
+    pub span: TextSpan,
+    pub new_text: String,
+}
+
+/// A set of edits that fixes the problem of a diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagramFix {
+    /// A short description of the fix, like `Insert missing end`.
+    pub title: String,
+    pub edits: Vec<DiagramEdit>,
+}
+
+/// A problem found inside a diagram.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagramDiagnostic {
+    pub code: DiagnosticCode,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    pub span: TextSpan,
+
+    /// The fix offered as a quick fix, if the problem can be fixed automatically.
+    pub fix: Option<DiagramFix>,
+}
+
+impl DiagramDiagnostic {
+    fn new(
+        code: DiagnosticCode,
+        severity: DiagnosticSeverity,
+        span: TextSpan,
+        message: String,
+    ) -> Self {
+        DiagramDiagnostic {
+            code,
+            severity,
+            message,
+            span,
+            fix: None,
+        }
+    }
+
+    fn with_fix(mut self, title: String, edits: Vec<DiagramEdit>) -> Self {
+        self.fix = Some(DiagramFix { title, edits });
+        self
+    }
+}
+
+/// Finds every problem of a document, sorted by their position.
+///
+/// The violations of the lint rules enabled by `config` are included, unless a comment suppresses them.
+pub fn diagnostics(ast: &MermaidAST, config: &LintConfig) -> Vec<DiagramDiagnostic> {
+    let tree = &ast.cst;
+    let mut diagnostics: Vec<_> = frontmatter_diagnostics(tree).into_iter().collect();
+    diagnostics.extend(diagram_type_diagnostics(tree));
+    diagnostics.extend(block_diagnostics(tree));
+    diagnostics.extend(lint(ast, config));
+
+    match &ast.diagram.data {
+        DiagramData::Sequence(sequence) => {
+            let mut reported: HashSet<_> = sequence
+                .participants
+                .iter()
+                .map(|p| p.id.content.as_str())
+                .collect();
+            // Participants can be declared implicitly, only mixing both styles is reported.
+            if let Some(last) = sequence.participants.last() {
+                let mut uses: Vec<_> = sequence
+                    .messages
+                    .iter()
+                    .flat_map(|m| [&m.from, &m.to])
+                    .chain(sequence.references.iter())
+                    .collect();
+                uses.sort_by_key(|t| t.span.start);
+                // Each participant is reported once, where it's used for the first time.
+                let undeclared = uses
+                    .into_iter()
+                    .filter(|t| reported.insert(t.content.as_str()));
+                let insert_at = line_end(tree, last.span.end);
+                let indentation = line_indentation(tree, last.span.start);
+
+                diagnostics.extend(undeclared.map(|token| {
+                    DiagramDiagnostic::new(
+                        DiagnosticCode::UndeclaredParticipant,
+                        DiagnosticSeverity::Warning,
+                        token.span,
+                        format!("The participant `{}` is not declared", token.content),
+                    )
+                    .with_fix(
+                        format!("Declare participant `{}`", token.content),
+                        vec![insert_line(
+                            tree,
+                            insert_at,
+                            &format!("{}participant {}", indentation, token.content),
+                        )],
+                    )
+                }));
+            }
+        }
+        DiagramData::Flowchart(flowchart) => {
+            let labels = flowchart
+                .nodes
+                .iter()
+                .filter_map(|n| n.label.as_ref())
+                .chain(flowchart.edges.iter().filter_map(|e| e.label.as_ref()));
+            diagnostics.extend(labels.filter_map(unquoted_label));
+        }
+        _ => {}
+    }
+
+    let mut diagnostics = apply_suppressions(tree, diagnostics);
+    diagnostics.sort_by_key(|d| (d.span.start, d.span.end));
+    diagnostics
+}
+
+/// Reports frontmatter delimiters that aren't exactly `---`.
+fn frontmatter_diagnostics(tree: &SyntaxTree) -> Option<DiagramDiagnostic> {
+    let is_delimiter_like =
+        |line: &str| line.trim().len() >= 2 && line.trim().chars().all(|c| c == '-');
+    let delimiter_span =
+        |start: usize, line: &str| TextSpan::new(start, start + line.trim_end().len());
+    let mut lines = split_lines(tree.text(), 0).skip_while(|(_, l)| l.trim().is_empty());
+
+    let (top_start, top) = lines.next().filter(|(_, l)| is_delimiter_like(l))?;
+    let top = delimiter_span(top_start, top);
+
+    // The frontmatter ends with the next delimiter, or before the diagram type if it was never closed.
+    let mut bottom = None;
+    let mut diagram_start = None;
+    for (start, line) in lines {
+        if is_delimiter_like(line) {
+            bottom = Some(delimiter_span(start, line));
+            break;
+        }
+        if parse_diagram_type(line) != MermaidDiagramTypes::Unknown {
+            diagram_start = Some(start);
+            break;
+        }
+    }
+
+    let error = match (tree.slice(top), bottom) {
+        ("---", Some(bottom)) if tree.slice(bottom) == "---" => return None,
+        ("---", _) => ParseHeaderErrors::InvalidBottomDelimiterFormat,
+        _ => ParseHeaderErrors::InvalidTopDelimiterFormat,
+    };
+    let (span, message) = match (&error, bottom) {
+        (ParseHeaderErrors::InvalidBottomDelimiterFormat, Some(bottom)) => {
+            (bottom, "The frontmatter must be closed with `---`")
+        }
+        (ParseHeaderErrors::InvalidBottomDelimiterFormat, None) => {
+            (top, "The frontmatter is never closed with `---`")
+        }
+        _ => (top, "The frontmatter must be opened with `---`"),
+    };
+
+    let mut edits: Vec<_> = [Some(top), bottom]
+        .into_iter()
+        .flatten()
+        .filter(|span| tree.slice(*span) != "---")
+        .map(|span| DiagramEdit {
+            span,
+            new_text: "---".to_string(),
+        })
+        .collect();
+    match (bottom, diagram_start) {
+        (Some(_), _) => {}
+        (None, Some(start)) => edits.push(DiagramEdit {
+            span: TextSpan::new(start, start),
+            new_text: format!("---{}", line_ending(tree)),
+        }),
+        // There's nowhere to close the frontmatter.
+        (None, None) => edits.clear(),
+    }
+
+    let diagnostic = DiagramDiagnostic::new(
+        DiagnosticCode::InvalidFrontmatter,
+        DiagnosticSeverity::Error,
+        span,
+        message.to_string(),
+    );
+    Some(match edits.is_empty() {
+        true => diagnostic,
+        false => diagnostic.with_fix("Fix frontmatter delimiters".to_string(), edits),
+    })
+}
+
+/// Reports a first line that doesn't declare a known diagram type.
+fn diagram_type_diagnostics(tree: &SyntaxTree) -> Option<DiagramDiagnostic> {
+    let header = tree.diagram_header()?;
+    let span = header.content_span()?;
+    let keyword = tree.slice(span).split_whitespace().next()?;
+    // A broken frontmatter is reported on its own.
+    if tree.diagram_type() != MermaidDiagramTypes::Unknown || keyword.chars().all(|c| c == '-') {
+        return None;
+    }
+
+    let span = TextSpan::new(span.start, span.start + keyword.len());
+    let diagnostic = DiagramDiagnostic::new(
+        DiagnosticCode::UnknownDiagramType,
+        DiagnosticSeverity::Error,
+        span,
+        format!("Unknown diagram type `{}`", keyword),
+    );
+
+    let closest = DIAGRAM_KEYWORDS
+        .iter()
+        .map(|k| {
+            (
+                k,
+                strsim::levenshtein(&keyword.to_lowercase(), &k.to_lowercase()),
+            )
+        })
+        .min_by_key(|(_, distance)| *distance)
+        .filter(|(_, distance)| *distance <= (keyword.len() / 2).max(2));
+
+    Some(match closest {
+        Some((closest, _)) => diagnostic.with_fix(
+            format!("Replace with `{}`", closest),
+            vec![DiagramEdit {
+                span,
+                new_text: closest.to_string(),
+            }],
+        ),
+        None => diagnostic,
+    })
+}
+
+/// Reports blocks that are never closed and closers without a block.
+fn block_diagnostics(tree: &SyntaxTree) -> Vec<DiagramDiagnostic> {
+    let closer = match tree.diagram_type() {
+        MermaidDiagramTypes::Flowchart | MermaidDiagramTypes::Sequence => "end",
+        MermaidDiagramTypes::Class
+        | MermaidDiagramTypes::State
+        | MermaidDiagramTypes::EntityRelationship
+        | MermaidDiagramTypes::Requirement => "}",
+        _ => return vec![],
+    };
+
+    tree.root()
+        .descendants()
+        .filter_map(|e| e.as_node())
+        .filter_map(|node| match node.kind() {
+            SyntaxKind::Block if node.block_end().is_none() => {
+                let start = node.block_start()?;
+                let span = start.content_span()?;
+                let insert_at = line_end(tree, node.content_span()?.end);
+                Some(
+                    DiagramDiagnostic::new(
+                        DiagnosticCode::UnclosedBlock,
+                        DiagnosticSeverity::Error,
+                        span,
+                        format!("This block is never closed with `{}`", closer),
+                    )
+                    .with_fix(
+                        format!("Insert missing `{}`", closer),
+                        vec![insert_line(
+                            tree,
+                            insert_at,
+                            &format!("{}{}", start.indentation(), closer),
+                        )],
+                    ),
+                )
+            }
+            SyntaxKind::Error => Some(
+                DiagramDiagnostic::new(
+                    DiagnosticCode::UnexpectedBlockEnd,
+                    DiagnosticSeverity::Error,
+                    node.content_span()?,
+                    format!("There is no block to close with `{}`", closer),
+                )
+                .with_fix(
+                    format!("Remove the unexpected `{}`", closer),
+                    vec![DiagramEdit {
+                        span: node.span(),
+                        new_text: String::new(),
+                    }],
+                ),
+            ),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Reports a label with reserved characters that isn't quoted.
+fn unquoted_label(label: &MermaidToken) -> Option<DiagramDiagnostic> {
+    let is_quoted = label.content.starts_with('"') && label.content.ends_with('"');
+    if is_quoted || !label.content.contains(RESERVED_LABEL_CHARS) {
+        return None;
+    }
+
+    Some(
+        DiagramDiagnostic::new(
+            DiagnosticCode::UnquotedLabel,
+            DiagnosticSeverity::Error,
+            label.span,
+            "Labels with brackets or `|` must be quoted".to_string(),
+        )
+        .with_fix(
+            "Quote the label".to_string(),
+            vec![DiagramEdit {
+                span: label.span,
+                new_text: format!("\"{}\"", label.content.replace('"', "#quot;")),
+            }],
+        ),
+    )
+}
+
+/// The offset where the line after the one containing `offset` starts, or the end of the document.
+fn line_end(tree: &SyntaxTree, offset: usize) -> usize {
+    match tree.text()[offset..].find('\n') {
+        Some(i) => offset + i + 1,
+        None => tree.text().len(),
+    }
+}
+
+/// The leading whitespace of the line containing `offset`.
+fn line_indentation(tree: &SyntaxTree, offset: usize) -> &str {
+    let line = tree.position(offset).line as usize;
+    let start = tree.line_index().line_start(line).unwrap_or_default();
+    let rest = &tree.text()[start..];
+    &rest[..rest.len() - rest.trim_start_matches([' ', '\t']).len()]
+}
+
+/// The line terminator used by the document.
+pub(super) fn line_ending(tree: &SyntaxTree) -> &'static str {
+    match tree.text().find('\n') {
+        Some(i) if tree.text()[..i].ends_with('\r') => "\r\n",
+        _ => "\n",
+    }
+}
+
+/// An edit that inserts `line` as a new line starting at `offset`, the start of a line or the end of the document.
+pub(super) fn insert_line(tree: &SyntaxTree, offset: usize, line: &str) -> DiagramEdit {
+    let ending = line_ending(tree);
+    let new_text = match offset == tree.text().len() && !tree.text().ends_with('\n') {
+        true => format!("{}{}", ending, line),
+        false => format!("{}{}", line, ending),
+    };
+
+    DiagramEdit {
+        span: TextSpan::new(offset, offset),
+        new_text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed(content: &str, code: DiagnosticCode) -> String {
+        let ast = MermaidAST::from_content(content.to_string());
+        let diagnostic = diagnostics(&ast, &LintConfig::default())
+            .into_iter()
+            .find(|d| d.code == code)
+            .expect("The diagnostic wasn't found!");
+
+        let mut edits = diagnostic.fix.expect("The diagnostic has no fix!").edits;
+        edits.sort_by_key(|e| std::cmp::Reverse(e.span.start));
+        let mut text = content.to_string();
+        for edit in edits {
+            text.replace_range(edit.span.start..edit.span.end, &edit.new_text);
+        }
+        text
+    }
+
+    #[test]
+    fn diagnostics_valid_document() {
+        let ast = MermaidAST::from_content(
+            include_str!("../../../file_tests/flowchart.mermaid").to_string(),
+        );
+
+        assert_eq!(diagnostics(&ast, &LintConfig::default()), vec![]);
+    }
+
+    #[test]
+    fn fix_unclosed_block() {
+        assert_eq!(
+            fixed(
+                "flowchart TD\n    subgraph one\n        A --> B\n\nC",
+                DiagnosticCode::UnclosedBlock
+            ),
+            "flowchart TD\n    subgraph one\n        A --> B\n\nC\n    end"
+        );
+        assert_eq!(
+            fixed(
+                "classDiagram\n  class A {\n    +int x\n",
+                DiagnosticCode::UnclosedBlock
+            ),
+            "classDiagram\n  class A {\n    +int x\n  }\n"
+        );
+    }
+
+    #[test]
+    fn fix_unexpected_block_end() {
+        assert_eq!(
+            fixed(
+                "flowchart TD\n    A\n    end\n    B\n",
+                DiagnosticCode::UnexpectedBlockEnd
+            ),
+            "flowchart TD\n    A\n    B\n"
+        );
+    }
+
+    #[test]
+    fn fix_unknown_diagram_type() {
+        assert_eq!(
+            fixed("flowchat TD\n    A\n", DiagnosticCode::UnknownDiagramType),
+            "flowchart TD\n    A\n"
+        );
+        assert_eq!(
+            fixed("SequenceDiagram\n", DiagnosticCode::UnknownDiagramType),
+            "sequenceDiagram\n"
+        );
+    }
+
+    #[test]
+    fn fix_frontmatter_delimiters() {
+        assert_eq!(
+            fixed(
+                "--\ntitle: x\n----\nflowchart TD\n",
+                DiagnosticCode::InvalidFrontmatter
+            ),
+            "---\ntitle: x\n---\nflowchart TD\n"
+        );
+        assert_eq!(
+            fixed(
+                "---\ntitle: x\nflowchart TD\n",
+                DiagnosticCode::InvalidFrontmatter
+            ),
+            "---\ntitle: x\n---\nflowchart TD\n"
+        );
+        let ast = MermaidAST::from_content("---\nconfig:\n  theme: dark\n---\npie\n".to_string());
+        assert_eq!(diagnostics(&ast, &LintConfig::default()), vec![]);
+    }
+
+    #[test]
+    fn fix_undeclared_participant() {
+        assert_eq!(
+            fixed(
+                "sequenceDiagram\n    participant A\n    A->>B: Hi\n",
+                DiagnosticCode::UndeclaredParticipant
+            ),
+            "sequenceDiagram\n    participant A\n    participant B\n    A->>B: Hi\n"
+        );
+        let ast = MermaidAST::from_content("sequenceDiagram\n    A->>B: Hi\n".to_string());
+        assert_eq!(diagnostics(&ast, &LintConfig::default()), vec![]);
+
+        let ast = MermaidAST::from_content(
+            "sequenceDiagram\n    participant A\n    A->>B: Hi\n    B->>A: Hi\n    A->>B: Bye\n"
+                .to_string(),
+        );
+        let reported: Vec<_> = diagnostics(&ast, &LintConfig::default())
+            .into_iter()
+            .map(|d| (d.code, d.span.start))
+            .collect();
+        assert_eq!(reported, vec![(DiagnosticCode::UndeclaredParticipant, 42)]);
+    }
+
+    #[test]
+    fn fix_unquoted_label() {
+        assert_eq!(
+            fixed(
+                "flowchart TD\n    A[Hello (big) world] --> B\n",
+                DiagnosticCode::UnquotedLabel
+            ),
+            "flowchart TD\n    A[\"Hello (big) world\"] --> B\n"
+        );
+        assert_eq!(
+            fixed(
+                "flowchart TD\n    A -->|a \"b\" [c]| B\n",
+                DiagnosticCode::UnquotedLabel
+            ),
+            "flowchart TD\n    A -->|\"a #quot;b#quot; [c]\"| B\n"
+        );
+        let ast =
+            MermaidAST::from_content("flowchart TD\n    A[\"Hello (world)\"] --> B\n".to_string());
+        assert_eq!(diagnostics(&ast, &LintConfig::default()), vec![]);
+    }
+}
@@ -1,21 +1,70 @@
-use super::{DiagramAST, MermaidDiagramTypes};
+use serde::Serialize;
 
-#[derive(Debug)]
-pub enum ParseDiagramBodyErrors {}
+use super::{
+    class::{parse_class_diagram, ClassDiagram},
+    entity_relationship::{parse_entity_relationship, EntityRelationshipDiagram},
+    flowchart::parse_flowchart,
+    gantt::{parse_gantt, GanttDiagram},
+    mindmap::{parse_mindmap, Mindmap},
+    sequence::{parse_sequence, SequenceDiagram},
+    state::{parse_state_diagram, StateDiagram},
+    DiagramAST, Flowchart, MermaidDiagramTypes, SyntaxTree,
+};
+
+/// The data specific to each type of diagram.
+#[derive(Debug, Clone, Serialize, Default)]
+pub enum DiagramData {
+    /// The diagram type is unknown or its body is not analyzed yet.
+    #[default]
+    Unsupported,
+    Flowchart(Flowchart),
+    Sequence(SequenceDiagram),
+    Class(ClassDiagram),
+    State(StateDiagram),
+    EntityRelationship(EntityRelationshipDiagram),
+    Gantt(GanttDiagram),
+    Mindmap(Mindmap),
+}
 
 /// Parses an entire diagram content into a struct
-pub fn parse_diagram(content: &str) -> DiagramAST {
-    let d_type = parse_diagram_type(content);
+pub fn parse_diagram(tree: &SyntaxTree) -> DiagramAST {
+    let d_type = tree.diagram_type();
     let data = match d_type {
-        MermaidDiagramTypes::Unknown => DiagramAST::default(),
-        MermaidDiagramTypes::Flowchart => todo!(),
-        _ => todo!(),
+        MermaidDiagramTypes::Flowchart => DiagramData::Flowchart(parse_flowchart(tree)),
+        MermaidDiagramTypes::Sequence => DiagramData::Sequence(parse_sequence(tree)),
+        MermaidDiagramTypes::Class => DiagramData::Class(parse_class_diagram(tree)),
+        MermaidDiagramTypes::State => DiagramData::State(parse_state_diagram(tree)),
+        MermaidDiagramTypes::EntityRelationship => {
+            DiagramData::EntityRelationship(parse_entity_relationship(tree))
+        }
+        MermaidDiagramTypes::Gantt => DiagramData::Gantt(parse_gantt(tree)),
+        MermaidDiagramTypes::Mindmap => DiagramData::Mindmap(parse_mindmap(tree)),
+        _ => DiagramData::Unsupported,
     };
-    DiagramAST { d_type }
+    DiagramAST { d_type, data }
 }
 
+/// Every keyword that declares the type of a diagram.
+pub const DIAGRAM_KEYWORDS: [&str; 15] = [
+    "flowchart",
+    "sequenceDiagram",
+    "classDiagram",
+    "stateDiagram",
+    "stateDiagram-v2",
+    "erDiagram",
+    "journey",
+    "gantt",
+    "pie",
+    "quadrantChart",
+    "requirementDiagram",
+    "gitGraph",
+    "mindmap",
+    "timeline",
+    "zenuml",
+];
+
 /// Attempts to parse a diagram type from a line
-fn parse_diagram_type(type_line: &str) -> MermaidDiagramTypes {
+pub(crate) fn parse_diagram_type(type_line: &str) -> MermaidDiagramTypes {
     let type_string = type_line.split_whitespace().next();
     match type_string {
         Some("flowchart") => MermaidDiagramTypes::Flowchart,
@@ -51,8 +100,16 @@ flowchart TD
     B -- No ----> E[End]
 "#;
 
-        let result = parse_diagram(diagram);
-        assert_eq!(result.d_type, MermaidDiagramTypes::Flowchart)
+        let result = parse_diagram(&SyntaxTree::parse(diagram.to_string()));
+        assert_eq!(result.d_type, MermaidDiagramTypes::Flowchart);
+        assert!(matches!(result.data, DiagramData::Flowchart(f) if f.edges.len() == 5));
+    }
+
+    #[test]
+    fn diagram_keywords_are_known() {
+        for keyword in DIAGRAM_KEYWORDS {
+            assert_ne!(parse_diagram_type(keyword), MermaidDiagramTypes::Unknown);
+        }
     }
 
     #[test]
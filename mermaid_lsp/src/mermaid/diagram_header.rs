@@ -1,8 +1,15 @@
+use serde::Serialize;
+
+use super::TextSpan;
+
 /// The header of a Mermaid diagram
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MermaidDiagramHeader {
     /// The title of a Mermaid diagram
-    title: String,
+    pub title: String,
+
+    /// The bytes the header occupies, including both `---` delimiters
+    pub span: TextSpan,
 }
 
 /// Enum that contains errors when parsing a diagram header
@@ -16,7 +23,8 @@ pub enum ParseHeaderErrors {
 
 /// Function that attempts to extract a header from a mermaid content file
 pub fn parse_header(content: &str) -> Result<(String, MermaidDiagramHeader), ParseHeaderErrors> {
-    let mut lines = content.trim_start().lines();
+    let trimmed = content.trim_start();
+    let mut lines = trimmed.lines();
 
     let header_top_delim = lines
         .next()
@@ -39,9 +47,20 @@ pub fn parse_header(content: &str) -> Result<(String, MermaidDiagramHeader), Par
 
     let title = parse_title(title_line).map_err(ParseHeaderErrors::TitleFormatError)?;
 
+    let start = content.len() - trimmed.len();
+    let end = start
+        + trimmed
+            .split_inclusive('\n')
+            .take(3)
+            .map(str::len)
+            .sum::<usize>();
+
     Ok((
         lines.fold(String::new(), |acc, e| acc + "\n" + e),
-        MermaidDiagramHeader { title },
+        MermaidDiagramHeader {
+            title,
+            span: TextSpan::new(start, end),
+        },
     ))
 }
 
@@ -111,6 +130,7 @@ title:{}
         let (rest, header) = parse_header(&content).unwrap();
 
         assert_eq!(header.title, title);
+        assert_eq!(header.span, TextSpan::new(0, content.len()));
         assert_eq!(rest, "");
     }
 
@@ -0,0 +1,259 @@
+use serde::Serialize;
+
+use super::{
+    cst::{tokens_span, SyntaxKind, SyntaxNode, SyntaxToken, SyntaxTree, TextSpan},
+    MermaidToken,
+};
+
+/// All the data relevant to an entity relationship diagram.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
+pub struct EntityRelationshipDiagram {
+    /// Every entity of the diagram, declared with a body or through a relationship.
+    pub entities: Vec<Entity>,
+
+    /// Every relationship between two entities, in order.
+    pub relationships: Vec<EntityRelationship>,
+}
+
+/// An entity of the diagram, like `CUSTOMER`.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct Entity {
+    /// The name of the entity, the first place where it appears.
+    pub name: MermaidToken,
+
+    /// The attributes declared inside the body of the entity.
+    pub attributes: Vec<EntityAttribute>,
+
+    /// The span of the declaration, for entities with a body it's the whole block.
+    pub span: TextSpan,
+}
+
+/// An attribute of an entity, like `string name PK "The name"`.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct EntityAttribute {
+    pub attribute_type: MermaidToken,
+    pub name: MermaidToken,
+
+    /// The span of the whole attribute line.
+    pub span: TextSpan,
+}
+
+/// A relationship between two entities, like `CUSTOMER ||--o{ ORDER : places`.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct EntityRelationship {
+    pub from: MermaidToken,
+    pub to: MermaidToken,
+
+    /// The cardinality of the relationship, like `||--o{`.
+    pub cardinality: MermaidToken,
+
+    /// The text after the `:`.
+    pub label: Option<MermaidToken>,
+}
+
+impl EntityRelationshipDiagram {
+    /// Finds an entity by name, creating it if it doesn't exist yet.
+    fn entity_mut(&mut self, tree: &SyntaxTree, name: TextSpan, span: TextSpan) -> &mut Entity {
+        let content = tree.slice(name);
+        match self.entities.iter().position(|e| e.name.content == content) {
+            Some(i) => &mut self.entities[i],
+            None => {
+                self.entities.push(Entity {
+                    name: MermaidToken::new(tree, name),
+                    attributes: vec![],
+                    span,
+                });
+                self.entities
+                    .last_mut()
+                    .expect("An entity was just pushed!")
+            }
+        }
+    }
+}
+
+/// Parses all the data relevant to an entity relationship diagram.
+pub fn parse_entity_relationship(tree: &SyntaxTree) -> EntityRelationshipDiagram {
+    let mut diagram = EntityRelationshipDiagram::default();
+
+    for child in tree.root().child_nodes() {
+        match child.kind() {
+            SyntaxKind::Statement => parse_statement(tree, child, &mut diagram),
+            SyntaxKind::Block => {
+                let Some(start) = child.block_start() else {
+                    continue;
+                };
+                let tokens: Vec<_> = start.significant_tokens().collect();
+                let Some(name) = entity_names(&tokens).into_iter().next() else {
+                    continue;
+                };
+
+                let attributes: Vec<_> = child
+                    .child_nodes()
+                    .filter(|n| n.kind() == SyntaxKind::Statement)
+                    .filter_map(|n| parse_attribute(tree, n))
+                    .collect();
+                let entity = diagram.entity_mut(tree, name, child.span());
+                entity.span = child.span();
+                entity.attributes.extend(attributes);
+            }
+            _ => {}
+        }
+    }
+
+    diagram
+}
+
+/// Parses a relationship statement.
+fn parse_statement(
+    tree: &SyntaxTree,
+    statement: &SyntaxNode,
+    diagram: &mut EntityRelationshipDiagram,
+) {
+    let tokens: Vec<_> = statement.significant_tokens().collect();
+    let Some(span) = statement.content_span() else {
+        return;
+    };
+    let Some(arrow) = tokens.iter().position(|t| {
+        t.kind() == SyntaxKind::Arrow && (t.text().contains("--") || t.text().contains(".."))
+    }) else {
+        // A lonely entity without attributes
+        if let [name] = entity_names(&tokens).as_slice() {
+            diagram.entity_mut(tree, *name, span);
+        }
+        return;
+    };
+
+    let adjacent = |a: &SyntaxToken, b: &SyntaxToken| a.span().end == b.span().start;
+    let mut start = arrow;
+    while start > 0 && adjacent(tokens[start - 1], tokens[start]) {
+        start -= 1;
+    }
+    let mut end = arrow;
+    while end + 1 < tokens.len() && adjacent(tokens[end], tokens[end + 1]) {
+        end += 1;
+    }
+
+    let colon = tokens
+        .iter()
+        .position(|t| t.is_punct(':'))
+        .unwrap_or(tokens.len())
+        .max(end + 1);
+    let (Some(from), Some(to)) = (
+        entity_names(&tokens[..start]).last().copied(),
+        entity_names(&tokens[end + 1..colon]).first().copied(),
+    ) else {
+        return;
+    };
+    let label = tokens_span(tokens.get(colon + 1..).unwrap_or_default());
+
+    diagram.entity_mut(tree, from, span);
+    diagram.entity_mut(tree, to, span);
+    diagram.relationships.push(EntityRelationship {
+        from: MermaidToken::new(tree, from),
+        to: MermaidToken::new(tree, to),
+        cardinality: MermaidToken::new(tree, tokens[start].span().cover(tokens[end].span())),
+        label: label.map(|l| MermaidToken::new(tree, l)),
+    });
+}
+
+/// Parses an attribute line like `string name PK "comment"`.
+fn parse_attribute(tree: &SyntaxTree, statement: &SyntaxNode) -> Option<EntityAttribute> {
+    let tokens: Vec<_> = statement.significant_tokens().collect();
+    let names = entity_names(&tokens);
+    let (attribute_type, name) = (names.first()?, names.get(1)?);
+
+    Some(EntityAttribute {
+        attribute_type: MermaidToken::new(tree, *attribute_type),
+        name: MermaidToken::new(tree, *name),
+        span: statement.content_span()?,
+    })
+}
+
+/// Groups identifiers joined by `-`, like `LINE-ITEM`, and returns their spans.
+fn entity_names(tokens: &[&SyntaxToken]) -> Vec<TextSpan> {
+    let mut names: Vec<TextSpan> = vec![];
+    let mut joining = false;
+
+    for token in tokens {
+        match token.kind() {
+            SyntaxKind::Ident => match names.last_mut() {
+                Some(last) if joining && last.end + 1 == token.span().start => {
+                    last.end = token.span().end
+                }
+                _ => names.push(token.span()),
+            },
+            SyntaxKind::Arrow if token.text() == "-" => {
+                joining = names.last().is_some_and(|l| l.end == token.span().start);
+                continue;
+            }
+            SyntaxKind::Punct | SyntaxKind::String => break,
+            _ => {}
+        }
+        joining = false;
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIAGRAM: &str = r#"erDiagram
+    CUSTOMER ||--o{ ORDER : places
+    ORDER ||--|{ LINE-ITEM : contains
+    CUSTOMER {
+        string name PK "The name"
+        int age
+    }
+"#;
+
+    #[test]
+    fn parse_entity_relationship_success() {
+        let tree = SyntaxTree::parse(DIAGRAM.to_string());
+
+        let diagram = parse_entity_relationship(&tree);
+
+        let entities: Vec<_> = diagram
+            .entities
+            .iter()
+            .map(|e| {
+                (
+                    e.name.content.as_str(),
+                    e.attributes
+                        .iter()
+                        .map(|a| (a.attribute_type.content.as_str(), a.name.content.as_str()))
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            entities,
+            vec![
+                ("CUSTOMER", vec![("string", "name"), ("int", "age")]),
+                ("ORDER", vec![]),
+                ("LINE-ITEM", vec![]),
+            ]
+        );
+
+        let relationships: Vec<_> = diagram
+            .relationships
+            .iter()
+            .map(|r| {
+                (
+                    r.from.content.as_str(),
+                    r.cardinality.content.as_str(),
+                    r.to.content.as_str(),
+                    r.label.as_ref().map(|l| l.content.as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            relationships,
+            vec![
+                ("CUSTOMER", "||--o{", "ORDER", Some("places")),
+                ("ORDER", "||--|{", "LINE-ITEM", Some("contains")),
+            ]
+        );
+    }
+}
@@ -1,34 +1,481 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 
-use super::{MermaidDiagramDirection, MermaidToken, TokenType};
+use serde::Serialize;
 
-/// Parses all the data relevant to a flow chart
-fn parse_flowchart(content: &str) -> HashMap<TokenType, Vec<MermaidToken>> {
-    let mut tokens = HashMap::default();
+use super::{
+    cst::{SyntaxKind, SyntaxNode, SyntaxToken, SyntaxTree, TextSpan},
+    MermaidDiagramDirection, MermaidToken,
+};
+
+/// All the data relevant to a flowchart diagram.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
+pub struct Flowchart {
+    /// The direction of the diagram, defaults to top to bottom.
+    pub direction: MermaidDiagramDirection,
+
+    /// The token that declared the direction in the diagram header.
+    pub direction_token: Option<MermaidToken>,
+
+    /// Every appearance of a node inside statements, in order.
+    pub nodes: Vec<FlowchartNode>,
+
+    /// Every link between two nodes, in order.
+    pub edges: Vec<FlowchartEdge>,
+
+    /// All the subgraphs of the diagram, in order.
+    pub subgraphs: Vec<FlowchartSubgraph>,
+
+    /// The names defined with `classDef`.
+    pub class_defs: Vec<MermaidToken>,
+
+    /// The class names applied to nodes with `class` statements or `:::`.
+    pub class_usages: Vec<MermaidToken>,
+}
+
+/// A node of a flowchart, like `A` or `A[Some label]`.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct FlowchartNode {
+    /// The id of the node.
+    pub id: MermaidToken,
+
+    /// The text inside the shape delimiters of the node, if it had one.
+    pub label: Option<MermaidToken>,
+
+    /// The span of the id and the shape of the node.
+    pub span: TextSpan,
+}
+
+/// A link between two flowchart nodes, like `A -- text --> B`.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct FlowchartEdge {
+    /// The id of the node where the edge starts.
+    pub from: MermaidToken,
 
-    let direction = parse_direction(content);
-    tokens
-        .entry(TokenType::DiagramDirection)
-        .or_insert(vec![direction]);
+    /// The id of the node where the edge ends.
+    pub to: MermaidToken,
 
-    tokens
+    /// The arrow token of the link, for `A -- text --> B` it's `-->`.
+    pub arrow: MermaidToken,
+
+    /// The text of the link, either between the arrow parts or between `|`.
+    pub label: Option<MermaidToken>,
+
+    /// The span of the whole link, without the nodes.
+    pub link: TextSpan,
+}
+
+/// Where the text of a link is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkLabelStyle {
+    /// Inside the arrow, like `A -- text --> B`.
+    Inline,
+    /// After the arrow between pipes, like `A -->|text| B`.
+    Piped,
+}
+
+impl FlowchartEdge {
+    /// Where the label of the link is written, `None` if it has no label.
+    pub fn label_style(&self) -> Option<LinkLabelStyle> {
+        let label = self.label.as_ref()?;
+        match label.span.start < self.arrow.span.start {
+            true => Some(LinkLabelStyle::Inline),
+            false => Some(LinkLabelStyle::Piped),
+        }
+    }
+
+    /// The text of the link with its label written in `style`.
+    ///
+    /// `None` if the link has no label, or if its arrow has no inline form, like `--x` or `<-->`.
+    pub fn link_with_label_style(&self, style: LinkLabelStyle) -> Option<String> {
+        let label = &self.label.as_ref()?.content;
+        let arrow = &self.arrow.content;
+        // The inline form of `-.->` is `-. text .->`, the arrow token only keeps `.->`.
+        let is_dotted = match self.label_style()? {
+            LinkLabelStyle::Inline => arrow.starts_with('.'),
+            LinkLabelStyle::Piped => arrow.starts_with("-."),
+        };
+
+        match style {
+            LinkLabelStyle::Piped if is_dotted && arrow.starts_with('.') => {
+                Some(format!("-{}|{}|", arrow, label))
+            }
+            LinkLabelStyle::Piped => Some(format!("{}|{}|", arrow, label)),
+            LinkLabelStyle::Inline => {
+                let (start, end) = match is_dotted {
+                    true => ("-.", arrow.trim_start_matches('-')),
+                    false => (arrow.get(..2)?, arrow.as_str()),
+                };
+                let is_plain = matches!(start, "--" | "==" | "-.")
+                    && end.len() > 1
+                    && end.chars().all(|c| matches!(c, '-' | '=' | '.' | '>'));
+                is_plain.then(|| format!("{} {} {}", start, label, end))
+            }
+        }
+    }
+}
+
+impl Flowchart {
+    /// The label style of most of the labelled links, ties go to the style of the first one.
+    ///
+    /// Links shared by many edges, like `A & B -->|text| C`, are counted once.
+    /// `None` if no link has a label.
+    pub fn dominant_label_style(&self) -> Option<LinkLabelStyle> {
+        let mut links = HashSet::new();
+        let styles: Vec<_> = self
+            .edges
+            .iter()
+            .filter(|e| links.insert(e.link.start))
+            .filter_map(|e| e.label_style())
+            .collect();
+        let inline = styles
+            .iter()
+            .filter(|s| **s == LinkLabelStyle::Inline)
+            .count();
+        let piped = styles.len() - inline;
+
+        match inline.cmp(&piped) {
+            std::cmp::Ordering::Greater => Some(LinkLabelStyle::Inline),
+            std::cmp::Ordering::Less => Some(LinkLabelStyle::Piped),
+            std::cmp::Ordering::Equal => styles.first().copied(),
+        }
+    }
+}
+
+/// A `subgraph ... end` block of a flowchart.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct FlowchartSubgraph {
+    /// The id of the subgraph.
+    pub id: MermaidToken,
+
+    /// The title of the subgraph if it was declared like `subgraph id [title]`.
+    pub title: Option<MermaidToken>,
+
+    /// The span of the whole block.
+    pub span: TextSpan,
+}
+
+/// Parses all the data relevant to a flow chart
+pub fn parse_flowchart(tree: &SyntaxTree) -> Flowchart {
+    let direction_token = parse_direction(tree);
+    let mut flowchart = Flowchart {
+        direction: direction_token
+            .as_ref()
+            .and_then(|t| MermaidDiagramDirection::from_keyword(&t.content))
+            .unwrap_or_default(),
+        direction_token,
+        ..Default::default()
+    };
+
+    parse_statements(tree, tree.root(), &mut flowchart);
+    flowchart
 }
 
 /// Parses a flowchart direction
-fn parse_direction(content: &str) -> MermaidToken {
-    let line = content.lines().next().unwrap_or("flowchart TD");
-    let direction = line.split(' ').last().unwrap_or("TD");
+fn parse_direction(tree: &SyntaxTree) -> Option<MermaidToken> {
+    let header = tree.diagram_header()?;
+    header
+        .significant_tokens()
+        .nth(1)
+        .filter(|t| MermaidDiagramDirection::from_keyword(t.text()).is_some())
+        .map(|t| MermaidToken::new(tree, t.span()))
+}
+
+/// Walks the statements below `node` and stores the data they define.
+fn parse_statements(tree: &SyntaxTree, node: &SyntaxNode, flowchart: &mut Flowchart) {
+    for child in node.child_nodes() {
+        match child.kind() {
+            SyntaxKind::Statement => parse_statement(tree, child, flowchart),
+            SyntaxKind::Block => {
+                if let Some(subgraph) = child
+                    .block_start()
+                    .and_then(|start| parse_subgraph(tree, start, child.span()))
+                {
+                    flowchart.subgraphs.push(subgraph);
+                }
+                parse_statements(tree, child, flowchart);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parses the id and title of a `subgraph` statement.
+fn parse_subgraph(
+    tree: &SyntaxTree,
+    start: &SyntaxNode,
+    span: TextSpan,
+) -> Option<FlowchartSubgraph> {
+    let tokens: Vec<_> = start.significant_tokens().skip(1).collect();
+    let first = tokens.first()?;
+    let last = tokens.last()?;
+
+    let (id, title) = match tokens.get(1) {
+        Some(next) if first.kind() == SyntaxKind::Ident && next.is_punct('[') => {
+            let title = shape_label(&tokens, 1).map(|(label, _)| label);
+            (first.span(), title)
+        }
+        None if first.kind() == SyntaxKind::Ident => (first.span(), None),
+        _ => {
+            let span = TextSpan::new(first.span().start, last.span().end);
+            (span, Some(span))
+        }
+    };
+
+    Some(FlowchartSubgraph {
+        id: MermaidToken::new(tree, id),
+        title: title.map(|t| MermaidToken::new(tree, t)),
+        span,
+    })
+}
+
+/// Parses a single statement of a flowchart, statements separated by `;` are supported.
+fn parse_statement(tree: &SyntaxTree, statement: &SyntaxNode, flowchart: &mut Flowchart) {
+    let tokens: Vec<_> = statement.significant_tokens().collect();
+
+    for tokens in tokens.split(|t| t.is_punct(';')) {
+        let Some(first) = tokens.first() else {
+            continue;
+        };
+
+        match first.text() {
+            "classDef" => flowchart.class_defs.extend(
+                tokens
+                    .get(1)
+                    .into_iter()
+                    .map(|t| MermaidToken::new(tree, t.span())),
+            ),
+            "class" => flowchart.class_usages.extend(
+                tokens
+                    .last()
+                    .filter(|_| tokens.len() > 2)
+                    .map(|t| MermaidToken::new(tree, t.span())),
+            ),
+            "direction" | "style" | "linkStyle" | "click" | "end" => {}
+            _ => parse_chain(tree, tokens, flowchart),
+        }
+    }
+}
+
+/// Parses a chain of nodes and links like `A & B --> C -- text --> D`.
+fn parse_chain(tree: &SyntaxTree, tokens: &[&SyntaxToken], flowchart: &mut Flowchart) {
+    let mut i = 0;
+    let mut previous: Vec<FlowchartNode> = vec![];
+
+    while i < tokens.len() {
+        let link = match tokens[i].kind() {
+            SyntaxKind::Arrow if !previous.is_empty() => {
+                let (link, next) = parse_link(tree, tokens, i);
+                i = next;
+                Some(link)
+            }
+            _ => None,
+        };
+
+        let mut group = vec![];
+        while let Some((node, next)) = parse_node(tree, tokens, i, flowchart) {
+            group.push(node);
+            i = next;
+            match tokens.get(i) {
+                Some(t) if t.is_punct('&') => i += 1,
+                _ => break,
+            }
+        }
 
-    MermaidToken {
-        content: direction.to_string(),
-        line: 1,
-        column: 11,
+        if group.is_empty() {
+            // Not a node nor a link, skip the token.
+            i += 1;
+            continue;
+        }
+
+        if let Some((arrow, label, link)) = link {
+            for from in previous.iter() {
+                for to in group.iter() {
+                    flowchart.edges.push(FlowchartEdge {
+                        from: from.id.clone(),
+                        to: to.id.clone(),
+                        arrow: arrow.clone(),
+                        label: label.clone(),
+                        link,
+                    });
+                }
+            }
+        }
+
+        flowchart.nodes.extend(group.iter().cloned());
+        previous = group;
+    }
+}
+
+/// Parses a link starting at the arrow in index `i`.
+///
+/// Returns the arrow, the label, the span of the whole link and the index after the link.
+fn parse_link(
+    tree: &SyntaxTree,
+    tokens: &[&SyntaxToken],
+    mut i: usize,
+) -> ((MermaidToken, Option<MermaidToken>, TextSpan), usize) {
+    let start = tokens[i].span().start;
+    let mut arrow = tokens[i];
+    let mut label = None;
+    i += 1;
+
+    // Links with the text inside the arrow, like `A -- text --> B`
+    if matches!(arrow.text(), "--" | "==" | "-.") {
+        if let Some(end) = tokens[i..]
+            .iter()
+            .position(|t| t.kind() == SyntaxKind::Arrow)
+            .map(|p| p + i)
+        {
+            if end > i {
+                label = Some(TextSpan::new(
+                    tokens[i].span().start,
+                    tokens[end - 1].span().end,
+                ));
+            }
+            arrow = tokens[end];
+            i = end + 1;
+        }
+    }
+    let mut end = arrow.span().end;
+
+    // Links with the text after the arrow, like `A -->|text| B`
+    if tokens.get(i).is_some_and(|t| t.is_punct('|')) {
+        if let Some(close) = tokens[i + 1..]
+            .iter()
+            .position(|t| t.is_punct('|'))
+            .map(|p| p + i + 1)
+        {
+            if close > i + 1 {
+                label = Some(TextSpan::new(
+                    tokens[i + 1].span().start,
+                    tokens[close - 1].span().end,
+                ));
+            }
+            end = tokens[close].span().end;
+            i = close + 1;
+        }
+    }
+
+    (
+        (
+            MermaidToken::new(tree, arrow.span()),
+            label.map(|l| MermaidToken::new(tree, l)),
+            TextSpan::new(start, end),
+        ),
+        i,
+    )
+}
+
+/// Parses a node starting at index `i`, like `A`, `A[label]` or `A((label)):::class`.
+///
+/// Returns the node and the index after it.
+fn parse_node(
+    tree: &SyntaxTree,
+    tokens: &[&SyntaxToken],
+    i: usize,
+    flowchart: &mut Flowchart,
+) -> Option<(FlowchartNode, usize)> {
+    let id = tokens.get(i).filter(|t| t.kind() == SyntaxKind::Ident)?;
+    let mut next = i + 1;
+    let mut span = id.span();
+
+    let mut label = None;
+    if tokens.get(next).is_some_and(|t| t.span().start == span.end) {
+        if let Some((l, end)) = shape_label(tokens, next) {
+            label = Some(MermaidToken::new(tree, l));
+            span.end = tokens[end].span().end;
+            next = end + 1;
+        }
+    }
+
+    // Classes applied with `A:::class`
+    if let [a, b, c, class] = tokens.get(next..next + 4).unwrap_or_default() {
+        if [a, b, c].iter().all(|t| t.is_punct(':')) && class.kind() == SyntaxKind::Ident {
+            flowchart
+                .class_usages
+                .push(MermaidToken::new(tree, class.span()));
+            next += 4;
+        }
     }
+
+    Some((
+        FlowchartNode {
+            id: MermaidToken::new(tree, id.span()),
+            label,
+            span,
+        },
+        next,
+    ))
+}
+
+/// Checks if the token can open the shape of a node.
+fn is_shape_open(token: &SyntaxToken) -> bool {
+    ['[', '(', '{'].iter().any(|c| token.is_punct(*c))
+}
+
+/// Checks if the token can close the shape of a node.
+fn is_shape_close(token: &SyntaxToken) -> bool {
+    [']', ')', '}'].iter().any(|c| token.is_punct(*c))
+}
+
+/// Parses the label of a shape that starts at index `i`, like `[label]` or `((label))`.
+///
+/// Returns the span of the label and the index of the last token of the shape.
+pub(crate) fn shape_label(tokens: &[&SyntaxToken], i: usize) -> Option<(TextSpan, usize)> {
+    let open = tokens.get(i)?;
+    let is_asymmetric = open.kind() == SyntaxKind::Arrow && open.text() == ">";
+    if !is_shape_open(open) && !is_asymmetric {
+        return None;
+    }
+
+    let close = if is_asymmetric {
+        tokens[i..].iter().position(|t| t.is_punct(']'))? + i
+    } else {
+        let mut depth = 0;
+        tokens[i..].iter().position(|t| {
+            if is_shape_open(t) {
+                depth += 1;
+            } else if is_shape_close(t) {
+                depth -= 1;
+            }
+            depth == 0
+        })? + i
+    };
+
+    let is_delimiter = |t: &SyntaxToken| {
+        is_shape_open(t) || is_shape_close(t) || t.is_punct('/') || t.is_punct('\\')
+    };
+    let adjacent = |a: &SyntaxToken, b: &SyntaxToken| a.span().end == b.span().start;
+
+    let mut label_start = i + 1;
+    while label_start < close
+        && is_delimiter(tokens[label_start])
+        && adjacent(tokens[label_start - 1], tokens[label_start])
+    {
+        label_start += 1;
+    }
+    let mut label_end = close;
+    while label_end > label_start
+        && is_delimiter(tokens[label_end - 1])
+        && adjacent(tokens[label_end - 1], tokens[label_end])
+    {
+        label_end -= 1;
+    }
+
+    let label = (label_start < label_end).then(|| {
+        TextSpan::new(
+            tokens[label_start].span().start,
+            tokens[label_end - 1].span().end,
+        )
+    })?;
+
+    Some((label, close))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::jsonrpc::Position;
     const SUCCESS_DIAGRAM: &str = r#"flowchart LR
     A[Start] --> B{Is it?}
     B -- Yes --> C[OK]
@@ -37,55 +484,190 @@ mod tests {
     B -- No ----> E[End]
 "#;
 
+    fn ids(nodes: &[FlowchartNode]) -> Vec<&str> {
+        nodes.iter().map(|n| n.id.content.as_str()).collect()
+    }
+
     #[test]
     fn parse_direction_success() {
-        let expected = MermaidToken {
-            content: "LR".to_string(),
-            line: 1,
-            column: 11,
-        };
-        let actual = parse_direction(SUCCESS_DIAGRAM);
+        let tree = SyntaxTree::parse(SUCCESS_DIAGRAM.to_string());
+
+        let actual = parse_direction(&tree).unwrap();
 
-        assert_eq!(expected, actual);
+        assert_eq!(actual.content, "LR");
+        assert_eq!(actual.span, TextSpan::new(10, 12));
+        assert_eq!(
+            actual.range.start,
+            Position {
+                line: 0,
+                character: 10
+            }
+        );
     }
 
     #[test]
     fn parse_flowchart_success() {
-        let expected_nodes = vec![
-            MermaidToken {
-                content: "A".to_string(),
-                line: 4,
-                column: 1,
-            },
-            MermaidToken {
-                content: "B".to_string(),
-                line: 2,
-                column: 17,
-            },
-            MermaidToken {
-                content: "C".to_string(),
-                line: 3,
-                column: 17,
-            },
-            MermaidToken {
-                content: "D".to_string(),
-                line: 4,
-                column: 10,
-            },
-            MermaidToken {
-                content: "E".to_string(),
-                line: 6,
-                column: 18,
-            },
+        let expected_nodes = [
+            ("A", 1, 4),
+            ("B", 1, 17),
+            ("B", 2, 4),
+            ("C", 2, 17),
+            ("C", 3, 4),
+            ("D", 3, 10),
+            ("D", 4, 4),
+            ("B", 4, 10),
+            ("B", 5, 4),
+            ("E", 5, 18),
         ];
-        let tokens = parse_flowchart(SUCCESS_DIAGRAM);
+        let tree = SyntaxTree::parse(SUCCESS_DIAGRAM.to_string());
 
-        let nodes = &tokens[&TokenType::Node];
+        let flowchart = parse_flowchart(&tree);
 
         println!("EXPECTED: {:?}", expected_nodes);
-        println!("ACTUAL: {:?}", nodes);
+        println!("ACTUAL: {:?}", flowchart.nodes);
+
+        let actual_nodes: Vec<_> = flowchart
+            .nodes
+            .iter()
+            .map(|n| {
+                (
+                    n.id.content.as_str(),
+                    n.id.range.start.line,
+                    n.id.range.start.character,
+                )
+            })
+            .collect();
+        assert_eq!(actual_nodes, expected_nodes);
+        assert_eq!(flowchart.direction, MermaidDiagramDirection::LeftToRight);
+        assert_eq!(flowchart.edges.len(), 5);
+    }
+
+    #[test]
+    fn parse_node_labels() {
+        let tree = SyntaxTree::parse(
+            "flowchart TD\n    A[(Database)] --> B((Circle)) & C>Flag] --> D{{\"Hex [x]\"}}\n"
+                .to_string(),
+        );
+
+        let flowchart = parse_flowchart(&tree);
+
+        let labels: Vec<_> = flowchart
+            .nodes
+            .iter()
+            .map(|n| n.label.as_ref().map(|l| l.content.as_str()))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                Some("Database"),
+                Some("Circle"),
+                Some("Flag"),
+                Some("\"Hex [x]\"")
+            ]
+        );
+        assert_eq!(ids(&flowchart.nodes), vec!["A", "B", "C", "D"]);
+
+        let edges: Vec<_> = flowchart
+            .edges
+            .iter()
+            .map(|e| (e.from.content.as_str(), e.to.content.as_str()))
+            .collect();
+        assert_eq!(edges, vec![("A", "B"), ("A", "C"), ("B", "D"), ("C", "D")]);
+    }
+
+    #[test]
+    fn parse_edge_labels() {
+        let tree = SyntaxTree::parse(
+            "flowchart TD\n    A -- some text --> B\n    B -->|other text| C; C-.->D\n".to_string(),
+        );
+
+        let flowchart = parse_flowchart(&tree);
+
+        let edges: Vec<_> = flowchart
+            .edges
+            .iter()
+            .map(|e| {
+                (
+                    e.arrow.content.as_str(),
+                    e.label.as_ref().map(|l| l.content.as_str()),
+                    tree.slice(e.link),
+                )
+            })
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("-->", Some("some text"), "-- some text -->"),
+                ("-->", Some("other text"), "-->|other text|"),
+                ("-.->", None, "-.->"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_subgraphs_and_classes() {
+        let tree = SyntaxTree::parse(
+            r#"flowchart TD
+    classDef important fill:#f00
+    subgraph one [The first]
+        A:::important --> B
+    end
+    subgraph Two words
+        C
+    end
+    class C important
+"#
+            .to_string(),
+        );
+
+        let flowchart = parse_flowchart(&tree);
+
+        let subgraphs: Vec<_> = flowchart
+            .subgraphs
+            .iter()
+            .map(|s| {
+                (
+                    s.id.content.as_str(),
+                    s.title.as_ref().map(|t| t.content.as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            subgraphs,
+            vec![("one", Some("The first")), ("Two words", Some("Two words"))]
+        );
+        assert_eq!(ids(&flowchart.nodes), vec!["A", "B", "C"]);
+        assert_eq!(flowchart.class_defs[0].content, "important");
+        assert_eq!(flowchart.class_usages.len(), 2);
+    }
+
+    #[test]
+    fn link_label_styles() {
+        let tree = SyntaxTree::parse(
+            "flowchart LR\n    A -->|one| B\n    B -.->|two| C\n    C ==>|three| D\n    D <-->|four| A\n    A -- five --> C\n"
+                .to_string(),
+        );
+        let flowchart = parse_flowchart(&tree);
+
+        let inline: Vec<_> = flowchart
+            .edges
+            .iter()
+            .map(|e| e.link_with_label_style(LinkLabelStyle::Inline))
+            .collect();
 
-        let all_are_in_expected = nodes.iter().all(|n| expected_nodes.contains(n));
-        assert!(all_are_in_expected);
+        assert_eq!(
+            inline,
+            vec![
+                Some("-- one -->".to_string()),
+                Some("-. two .->".to_string()),
+                Some("== three ==>".to_string()),
+                None,
+                Some("-- five -->".to_string()),
+            ]
+        );
+        assert_eq!(
+            flowchart.dominant_label_style(),
+            Some(LinkLabelStyle::Piped)
+        );
     }
 }
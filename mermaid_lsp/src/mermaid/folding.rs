@@ -0,0 +1,177 @@
+use super::cst::{SyntaxKind, SyntaxNode, SyntaxTree, TextSpan};
+
+/// What a fold of the document contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagramFoldKind {
+    /// The `---` delimited frontmatter.
+    Frontmatter,
+    /// A block of statements, like a subgraph or a gantt section.
+    Block,
+    /// Two or more consecutive lines with only `%%` comments.
+    Comment,
+}
+
+/// A range of lines of the document that can be folded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiagramFold {
+    /// The zero-based line where the fold starts, it stays visible after folding.
+    pub start_line: u32,
+
+    /// The zero-based line where the fold ends, inclusive.
+    pub end_line: u32,
+
+    pub kind: DiagramFoldKind,
+}
+
+/// Finds every range of lines that can be folded, sorted by their start line.
+pub fn folding_ranges(tree: &SyntaxTree) -> Vec<DiagramFold> {
+    let mut folds = vec![];
+
+    if let Some(span) = tree.frontmatter().and_then(SyntaxNode::content_span) {
+        folds.extend(fold(tree, span, DiagramFoldKind::Frontmatter));
+    }
+
+    folds.extend(
+        tree.root()
+            .descendants()
+            .filter_map(|e| e.as_node())
+            .filter(|n| n.kind() == SyntaxKind::Block)
+            .filter_map(|block| block_fold(tree, block)),
+    );
+
+    folds.extend(comment_folds(tree));
+    folds.sort_by_key(|f| (f.start_line, std::cmp::Reverse(f.end_line)));
+    folds
+}
+
+/// The fold of the lines covered by `span`, `None` if the span is on a single line.
+fn fold(tree: &SyntaxTree, span: TextSpan, kind: DiagramFoldKind) -> Option<DiagramFold> {
+    let range = tree.range(span);
+    (range.start.line < range.end.line).then_some(DiagramFold {
+        start_line: range.start.line,
+        end_line: range.end.line,
+        kind,
+    })
+}
+
+/// The fold of a block, its `end` or `}` stays visible after folding like its first line does.
+fn block_fold(tree: &SyntaxTree, block: &SyntaxNode) -> Option<DiagramFold> {
+    let fold = fold(tree, block.content_span()?, DiagramFoldKind::Block)?;
+    let end_line = match block.block_end().and_then(SyntaxNode::content_span) {
+        Some(end) if starts_line(tree, end.start) => tree.position(end.start).line - 1,
+        _ => fold.end_line,
+    };
+
+    (fold.start_line < end_line).then_some(DiagramFold { end_line, ..fold })
+}
+
+/// Checks if there's only whitespace before `offset` on its line.
+fn starts_line(tree: &SyntaxTree, offset: usize) -> bool {
+    let line = tree.position(offset).line as usize;
+    let line_start = tree.line_index().line_start(line).unwrap_or_default();
+    tree.slice(TextSpan::new(line_start, offset))
+        .trim()
+        .is_empty()
+}
+
+/// Groups consecutive lines that only have a comment.
+fn comment_folds(tree: &SyntaxTree) -> Vec<DiagramFold> {
+    let comment_lines = tree
+        .root()
+        .tokens()
+        .filter(|t| t.kind() == SyntaxKind::Comment)
+        .filter(|t| starts_line(tree, t.span().start))
+        .map(|t| tree.position(t.span().start).line);
+
+    let mut folds: Vec<DiagramFold> = vec![];
+    let mut run: Option<(u32, u32)> = None;
+    for line in comment_lines {
+        run = match run {
+            Some((start, end)) if end + 1 == line => Some((start, line)),
+            Some((start, end)) => {
+                folds.extend(comment_run(start, end));
+                Some((line, line))
+            }
+            None => Some((line, line)),
+        };
+    }
+    if let Some((start, end)) = run {
+        folds.extend(comment_run(start, end));
+    }
+
+    folds
+}
+
+fn comment_run(start_line: u32, end_line: u32) -> Option<DiagramFold> {
+    (start_line < end_line).then_some(DiagramFold {
+        start_line,
+        end_line,
+        kind: DiagramFoldKind::Comment,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folds(content: &str) -> Vec<(u32, u32, DiagramFoldKind)> {
+        let tree = SyntaxTree::parse(content.to_string());
+        folding_ranges(&tree)
+            .into_iter()
+            .map(|f| (f.start_line, f.end_line, f.kind))
+            .collect()
+    }
+
+    #[test]
+    fn folding_ranges_flowchart() {
+        let content = r#"---
+title: Test title
+---
+%% The first comment
+%% The second comment
+flowchart TD
+    subgraph one
+        A --> B
+        subgraph two
+            C
+        end
+    end
+    %% A lonely comment
+"#;
+
+        assert_eq!(
+            folds(content),
+            vec![
+                (0, 2, DiagramFoldKind::Frontmatter),
+                (3, 4, DiagramFoldKind::Comment),
+                (6, 10, DiagramFoldKind::Block),
+                (8, 9, DiagramFoldKind::Block),
+            ]
+        );
+    }
+
+    #[test]
+    fn folding_ranges_blocks() {
+        assert_eq!(
+            folds("sequenceDiagram\n    loop Every minute\n        A->>B: Hi\n    end\n"),
+            vec![(1, 2, DiagramFoldKind::Block)]
+        );
+        assert_eq!(
+            folds("classDiagram\n    class Duck {\n        +swim()\n    }\n    class Single {}\n"),
+            vec![(1, 2, DiagramFoldKind::Block)]
+        );
+        assert_eq!(
+            folds("stateDiagram-v2\n    state Active {\n        [*] --> Idle\n    }\n"),
+            vec![(1, 2, DiagramFoldKind::Block)]
+        );
+        // Nothing is left to fold between a block's first line and its closer.
+        assert_eq!(folds("flowchart TD\n    subgraph one\n    end\n"), vec![]);
+        assert_eq!(
+            folds("gantt\n    section One\n    Task :a1, 1d\n    section Two\n    Other :a2, 1d\n"),
+            vec![
+                (1, 2, DiagramFoldKind::Block),
+                (3, 4, DiagramFoldKind::Block)
+            ]
+        );
+    }
+}
@@ -0,0 +1,713 @@
+use std::collections::HashMap;
+
+use super::{
+    class::relation_operator,
+    cst::{split_lines, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken, SyntaxTree, TextSpan},
+    flowchart::parse_flowchart,
+    gantt::GANTT_KEYWORDS,
+    MermaidDiagramTypes,
+};
+
+/// Options that change how a document is formatted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Size of a tab in spaces.
+    pub tab_size: u32,
+
+    /// Prefer spaces over tabs.
+    pub insert_spaces: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            tab_size: 4,
+            insert_spaces: true,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// The indentation of a line nested `depth` times.
+    fn indent(&self, depth: usize) -> String {
+        match self.insert_spaces {
+            true => " ".repeat(self.tab_size as usize * depth),
+            false => "\t".repeat(depth),
+        }
+    }
+}
+
+/// Formats the whole document.
+pub fn format(tree: &SyntaxTree, options: &FormatOptions) -> String {
+    format_lines(tree, options).concat()
+}
+
+/// Formats the document line by line.
+///
+/// The element `i` replaces the line `i` of the document, including its line
+/// terminator. Removed lines are replaced with an empty string and lines that
+/// need a blank line before them start with a line terminator, so the lines
+/// of a range can be formatted without touching the rest of the document.
+pub fn format_lines(tree: &SyntaxTree, options: &FormatOptions) -> Vec<String> {
+    let lines: Vec<_> = split_lines(tree.text(), 0).collect();
+    let d_type = tree.diagram_type();
+    let layouts = line_layouts(tree, &d_type);
+    let links = link_rewrites(tree, &d_type);
+
+    let rendered: Vec<Option<String>> = lines
+        .iter()
+        .zip(layouts.iter())
+        .map(|((_, line), layout)| {
+            if line.trim().is_empty() {
+                return None;
+            }
+            Some(match layout {
+                LineLayout::Verbatim => line.trim_end().to_string(),
+                LineLayout::Frontmatter => format_frontmatter_line(line),
+                LineLayout::Header(node) => {
+                    render_statement(tree, node, &Style::WORDS, &LinkRewrites::new())
+                }
+                LineLayout::Trivia(depth) => {
+                    let content = line.trim();
+                    format!("{}{}", options.indent(*depth), content)
+                }
+                LineLayout::Statement(node, depth) => format!(
+                    "{}{}",
+                    options.indent(statement_depth(&d_type, node, *depth)),
+                    render_statement(tree, node, &Style::of(&d_type, node), &links)
+                ),
+            })
+        })
+        .collect();
+
+    let line_ending = match tree.text().find('\n') {
+        Some(i) if tree.text()[..i].ends_with('\r') => "\r\n",
+        _ => "\n",
+    };
+    let first = rendered.iter().position(Option::is_some);
+    let last = rendered.iter().rposition(Option::is_some);
+
+    let mut output: Vec<String> = rendered
+        .iter()
+        .enumerate()
+        .map(|(i, line)| match line {
+            Some(text) => format!("{}{}", text, line_ending),
+            // Blank lines are kept once between lines with content.
+            None if first.is_some_and(|f| f < i)
+                && last.is_some_and(|l| i < l)
+                && rendered[i - 1].is_some() =>
+            {
+                line_ending.to_string()
+            }
+            None => String::new(),
+        })
+        .collect();
+
+    // Sections are separated by a blank line, comments right above a section belong to it.
+    for (i, layout) in layouts.iter().enumerate() {
+        if !matches!(layout, LineLayout::Statement(node, _) if is_section_start(&d_type, node)) {
+            continue;
+        }
+        let mut start = i;
+        while start > 0
+            && rendered[start - 1].is_some()
+            && matches!(layouts[start - 1], LineLayout::Trivia(_))
+        {
+            start -= 1;
+        }
+        let needs_blank = start > 0
+            && rendered[start - 1].is_some()
+            && !matches!(layouts[start - 1], LineLayout::Header(_));
+        if needs_blank {
+            output[start].insert_str(0, line_ending);
+        }
+    }
+
+    output
+}
+
+/// The indentation that the line `line` should have, `None` if it can't be known.
+///
+/// Lines after the end of the document are considered part of the innermost
+/// block that is still open, so the indentation of a line that is being typed
+/// can be computed.
+pub fn indentation_at(tree: &SyntaxTree, options: &FormatOptions, line: usize) -> Option<String> {
+    let d_type = tree.diagram_type();
+    let layouts = line_layouts(tree, &d_type);
+
+    let depth = match layouts.get(line) {
+        Some(LineLayout::Statement(node, depth)) => statement_depth(&d_type, node, *depth),
+        Some(LineLayout::Trivia(depth)) => *depth,
+        Some(_) => return None,
+        None if d_type == MermaidDiagramTypes::Unknown => return None,
+        None => {
+            let mut depth = 1;
+            let mut node = tree.root();
+            while let Some(block) = node
+                .child_nodes()
+                .last()
+                .filter(|n| n.kind() == SyntaxKind::Block && n.block_end().is_none())
+            {
+                depth += inner_depth(&d_type, 0);
+                node = block;
+            }
+            depth
+        }
+    };
+
+    Some(options.indent(depth))
+}
+
+/// Decides the layout of every line of the document.
+fn line_layouts<'a>(tree: &'a SyntaxTree, d_type: &MermaidDiagramTypes) -> Vec<LineLayout<'a>> {
+    let mut layouts = vec![LineLayout::Verbatim; split_lines(tree.text(), 0).count()];
+
+    // Without a known diagram the structure of the document is a guess, only whitespace is trimmed.
+    if *d_type != MermaidDiagramTypes::Unknown {
+        let mut builder = LayoutBuilder {
+            tree,
+            d_type,
+            layouts: &mut layouts,
+        };
+        builder.visit_root();
+    }
+
+    layouts
+}
+
+/// The depth of the statements inside a block nested `depth` times.
+fn inner_depth(d_type: &MermaidDiagramTypes, depth: usize) -> usize {
+    match d_type {
+        // Sections aren't nested, their tasks stay at the same level.
+        MermaidDiagramTypes::Gantt
+        | MermaidDiagramTypes::UserJourney
+        | MermaidDiagramTypes::Timeline => depth,
+        _ => depth + 1,
+    }
+}
+
+/// The depth of a statement found `depth` blocks deep.
+fn statement_depth(d_type: &MermaidDiagramTypes, node: &SyntaxNode, depth: usize) -> usize {
+    match is_continuation(d_type, node) {
+        true => depth.saturating_sub(1).max(1),
+        false => depth,
+    }
+}
+
+/// How a line of the document must be formatted.
+#[derive(Debug, Clone, Copy)]
+enum LineLayout<'a> {
+    /// A line that is kept as it is, without trailing whitespace.
+    Verbatim,
+    /// A line of the frontmatter.
+    Frontmatter,
+    /// The line that declares the diagram type.
+    Header(&'a SyntaxNode),
+    /// A blank line or a line with only comments and directives, nested `depth` times.
+    Trivia(usize),
+    /// A statement nested `depth` times.
+    Statement(&'a SyntaxNode, usize),
+}
+
+/// Walks the syntax tree and decides the layout of every line.
+struct LayoutBuilder<'a, 'b> {
+    tree: &'a SyntaxTree,
+    d_type: &'b MermaidDiagramTypes,
+    layouts: &'b mut Vec<LineLayout<'a>>,
+}
+
+impl<'a> LayoutBuilder<'a, '_> {
+    fn line(&self, offset: usize) -> usize {
+        self.tree.position(offset).line as usize
+    }
+
+    fn set(&mut self, offset: usize, layout: LineLayout<'a>) {
+        let line = self.line(offset);
+        if let Some(l) = self.layouts.get_mut(line) {
+            *l = layout;
+        }
+    }
+
+    fn visit_root(&mut self) {
+        let mut depth = 0;
+        for child in self.tree.root().children() {
+            match child {
+                SyntaxElement::Node(node) => match node.kind() {
+                    SyntaxKind::Frontmatter => {
+                        let lines = self.line(node.span().start)
+                            ..=self.line(node.span().end.saturating_sub(1));
+                        for line in lines {
+                            if let Some(l) = self.layouts.get_mut(line) {
+                                *l = LineLayout::Frontmatter;
+                            }
+                        }
+                    }
+                    SyntaxKind::DiagramHeader => {
+                        self.set(node.span().start, LineLayout::Header(node));
+                        depth = 1;
+                    }
+                    SyntaxKind::Block => self.visit_block(node, 1),
+                    _ => self.set(node.span().start, LineLayout::Statement(node, 1)),
+                },
+                SyntaxElement::Token(token) => self.visit_token(token, depth),
+            }
+        }
+    }
+
+    fn visit_block(&mut self, block: &'a SyntaxNode, depth: usize) {
+        let inner = inner_depth(self.d_type, depth);
+
+        for child in block.children() {
+            match child {
+                SyntaxElement::Node(node) => match node.kind() {
+                    SyntaxKind::BlockStart | SyntaxKind::BlockEnd => {
+                        self.set(node.span().start, LineLayout::Statement(node, depth))
+                    }
+                    SyntaxKind::Block => self.visit_block(node, inner),
+                    _ => self.set(node.span().start, LineLayout::Statement(node, inner)),
+                },
+                SyntaxElement::Token(token) => self.visit_token(token, inner),
+            }
+        }
+    }
+
+    /// Tokens outside of statements belong to lines with only comments or blank lines.
+    fn visit_token(&mut self, token: &SyntaxToken, depth: usize) {
+        self.set(token.span().start, LineLayout::Trivia(depth));
+    }
+}
+
+/// Checks if a statement continues the block it's inside, like `else` in an `alt` block.
+/// These statements are aligned with the line that opened the block.
+fn is_continuation(d_type: &MermaidDiagramTypes, node: &SyntaxNode) -> bool {
+    *d_type == MermaidDiagramTypes::Sequence
+        && node.kind() == SyntaxKind::Statement
+        && node
+            .significant_tokens()
+            .next()
+            .is_some_and(|t| t.is_ident("else") || t.is_ident("and") || t.is_ident("option"))
+}
+
+/// Checks if a statement opens a section of a gantt, journey or timeline diagram.
+fn is_section_start(d_type: &MermaidDiagramTypes, node: &SyntaxNode) -> bool {
+    matches!(
+        d_type,
+        MermaidDiagramTypes::Gantt
+            | MermaidDiagramTypes::UserJourney
+            | MermaidDiagramTypes::Timeline
+    ) && node.kind() == SyntaxKind::BlockStart
+}
+
+/// Trims a frontmatter line and leaves a single space after the `:` of a key.
+fn format_frontmatter_line(line: &str) -> String {
+    let line = line.trim_end();
+    if line == "---" {
+        return line.to_string();
+    }
+
+    let content = line.trim_start();
+    let indent = &line[..line.len() - content.len()];
+    match content.split_once(':') {
+        // `key:value` is a plain string in YAML, only `key: value` is a mapping.
+        Some((key, value))
+            if !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+                && (value.is_empty() || value.starts_with(char::is_whitespace)) =>
+        {
+            match value.trim_start() {
+                "" => format!("{}{}:", indent, key),
+                value => format!("{}{}: {}", indent, key, value),
+            }
+        }
+        _ => line.to_string(),
+    }
+}
+
+/// The links written again with the label style of the document, by the offset where they start.
+type LinkRewrites = HashMap<usize, (TextSpan, String)>;
+
+/// Rewrites the labelled links of a flowchart that don't use the style of most of them,
+/// so `A -- text --> B` and `A -->|text| B` aren't mixed in the same diagram.
+fn link_rewrites(tree: &SyntaxTree, d_type: &MermaidDiagramTypes) -> LinkRewrites {
+    if *d_type != MermaidDiagramTypes::Flowchart {
+        return LinkRewrites::new();
+    }
+    let flowchart = parse_flowchart(tree);
+    let Some(style) = flowchart.dominant_label_style() else {
+        return LinkRewrites::new();
+    };
+
+    flowchart
+        .edges
+        .iter()
+        .filter(|e| e.label_style().is_some_and(|s| s != style))
+        .filter_map(|e| Some((e.link.start, (e.link, e.link_with_label_style(style)?))))
+        .collect()
+}
+
+/// How the `:` that separates a statement from its text is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColonStyle {
+    /// `A->>B: text`
+    Tight,
+    /// `A --> B : text`
+    Spaced,
+    /// `Task :a1, 1d`
+    Leading,
+}
+
+/// The normalizations applied to the statements of a diagram.
+#[derive(Debug, Clone, Copy)]
+struct Style {
+    /// Keep the statement as it is, only reindent it.
+    verbatim: bool,
+
+    /// Surround flowchart links with spaces and glue their `|text|` to them.
+    links: bool,
+
+    /// Surround the relation operators of classes like `<|--` with spaces.
+    relations: bool,
+
+    /// Keep the spacing inside `[]`, `()` and `{}`, labels of nodes live there.
+    brackets: bool,
+
+    /// The text after the first `:` is kept as it is.
+    colon: Option<ColonStyle>,
+}
+
+impl Style {
+    /// Single spaces between words.
+    const WORDS: Style = Style {
+        verbatim: false,
+        links: false,
+        relations: false,
+        brackets: true,
+        colon: None,
+    };
+
+    fn of(d_type: &MermaidDiagramTypes, node: &SyntaxNode) -> Style {
+        match d_type {
+            MermaidDiagramTypes::Flowchart => Style {
+                links: true,
+                ..Style::WORDS
+            },
+            MermaidDiagramTypes::State => Style {
+                links: true,
+                colon: Some(ColonStyle::Spaced),
+                ..Style::WORDS
+            },
+            MermaidDiagramTypes::Sequence => Style {
+                colon: Some(ColonStyle::Tight),
+                ..Style::WORDS
+            },
+            MermaidDiagramTypes::Class => Style {
+                relations: true,
+                colon: Some(ColonStyle::Spaced),
+                ..Style::WORDS
+            },
+            // Cardinalities like `}o--||` would be confused with brackets.
+            MermaidDiagramTypes::EntityRelationship => Style {
+                brackets: false,
+                colon: Some(ColonStyle::Spaced),
+                ..Style::WORDS
+            },
+            MermaidDiagramTypes::Gantt => {
+                let is_keyword = node
+                    .significant_tokens()
+                    .next()
+                    .is_some_and(|t| GANTT_KEYWORDS.contains(&t.text()));
+                Style {
+                    colon: (!is_keyword).then_some(ColonStyle::Leading),
+                    ..Style::WORDS
+                }
+            }
+            _ => Style {
+                verbatim: true,
+                ..Style::WORDS
+            },
+        }
+    }
+}
+
+/// Renders the content of a single line statement, without indentation.
+fn render_statement(
+    tree: &SyntaxTree,
+    node: &SyntaxNode,
+    style: &Style,
+    links: &LinkRewrites,
+) -> String {
+    let tokens: Vec<_> = node.significant_tokens().collect();
+    let comments = node
+        .tokens()
+        .filter(|t| matches!(t.kind(), SyntaxKind::Comment | SyntaxKind::Directive))
+        .map(|t| t.text().trim_end());
+
+    let mut content = match (style.verbatim, node.content_span()) {
+        (true, Some(span)) => tree.slice(span).to_string(),
+        (true, None) => String::new(),
+        (false, _) => {
+            let colon = style
+                .colon
+                .and_then(|c| top_level_colon(&tokens, style).map(|i| (c, i)));
+            match colon {
+                Some((colon_style, i)) => {
+                    let left = render_words(tree, node, &tokens[..i], style, links);
+                    let right = match (tokens.get(i + 1), tokens.last()) {
+                        (Some(first), Some(last)) => {
+                            tree.slice(first.span().cover(last.span())).to_string()
+                        }
+                        _ => String::new(),
+                    };
+                    let rendered = match (colon_style, right.is_empty()) {
+                        (ColonStyle::Tight, true) => format!("{}:", left),
+                        (ColonStyle::Tight, false) => format!("{}: {}", left, right),
+                        (ColonStyle::Spaced, true) => format!("{} :", left),
+                        (ColonStyle::Spaced, false) => format!("{} : {}", left, right),
+                        (ColonStyle::Leading, _) => format!("{} :{}", left, right),
+                    };
+                    rendered.trim_start().to_string()
+                }
+                None => render_words(tree, node, &tokens, style, links),
+            }
+        }
+    };
+
+    for comment in comments {
+        if !content.is_empty() {
+            content.push(' ');
+        }
+        content.push_str(comment);
+    }
+    content
+}
+
+/// Finds the first `:` outside of any bracket.
+fn top_level_colon(tokens: &[&SyntaxToken], style: &Style) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate() {
+        if depth == 0 && token.is_punct(':') {
+            return Some(i);
+        }
+        if style.brackets {
+            depth = update_depth(depth, token);
+        }
+    }
+    None
+}
+
+/// The bracket depth after `token`.
+fn update_depth(depth: usize, token: &SyntaxToken) -> usize {
+    if ['[', '(', '{'].iter().any(|c| token.is_punct(*c)) {
+        depth + 1
+    } else if [']', ')', '}'].iter().any(|c| token.is_punct(*c)) {
+        depth.saturating_sub(1)
+    } else {
+        depth
+    }
+}
+
+/// Checks if an arrow token is a flowchart link like `-->`, `==>` or `-.->`.
+fn is_link(token: &SyntaxToken) -> bool {
+    token.kind() == SyntaxKind::Arrow
+        && token.text().len() > 1
+        && token
+            .text()
+            .chars()
+            .any(|c| matches!(c, '-' | '=' | '.' | '~'))
+}
+
+/// Joins the tokens of a statement with normalized spacing.
+///
+/// Tokens that were together stay together and tokens separated by any amount
+/// of whitespace are separated by a single space. The text inside brackets and
+/// link labels is kept as it is. The links found in `links` are replaced with their new text.
+fn render_words(
+    tree: &SyntaxTree,
+    node: &SyntaxNode,
+    tokens: &[&SyntaxToken],
+    style: &Style,
+    links: &LinkRewrites,
+) -> String {
+    let mut output = String::new();
+    let mut depth = 0usize;
+    let mut in_label = false;
+    let mut prev: Option<&SyntaxToken> = None;
+    let mut prev_opened_label = false;
+    let mut prev_closed_label = false;
+    let mut skip_until = 0;
+    let relation = style.relations.then(|| relation_operator(tokens)).flatten();
+
+    for (i, token) in tokens.iter().enumerate() {
+        if token.span().start < skip_until {
+            prev = Some(token);
+            continue;
+        }
+        if let Some((span, new_text)) = links.get(&token.span().start) {
+            if prev.is_some() {
+                output.push(' ');
+            }
+            output.push_str(new_text);
+            skip_until = span.end;
+            // The rest of the statement is separated from the link like from a closed label.
+            prev_opened_label = false;
+            prev_closed_label = true;
+            prev = Some(token);
+            continue;
+        }
+
+        let opens_label = style.links
+            && !in_label
+            && depth == 0
+            && token.is_punct('|')
+            && prev.is_some_and(is_link);
+        let closes_label = in_label && token.is_punct('|');
+        let opens_body =
+            node.kind() == SyntaxKind::BlockStart && i + 1 == tokens.len() && token.is_punct('{');
+
+        if let Some(prev) = prev {
+            let gap = tree.slice(TextSpan::new(prev.span().end, token.span().start));
+            let separator = if in_label {
+                match closes_label || prev_opened_label {
+                    true => "",
+                    false => gap,
+                }
+            } else if relation.is_some_and(|(start, end)| i > start && i <= end) {
+                ""
+            } else if relation.is_some_and(|(start, end)| i == start || i == end + 1) {
+                " "
+            } else if depth > 0 && !opens_body {
+                gap
+            } else if opens_label {
+                ""
+            } else if opens_body
+                || prev_closed_label
+                || style.links && (is_link(prev) || is_link(token) || prev.is_punct(';'))
+                || !gap.is_empty()
+            {
+                " "
+            } else {
+                ""
+            };
+            output.push_str(separator);
+        }
+        output.push_str(token.text());
+
+        prev_opened_label = opens_label;
+        prev_closed_label = closes_label;
+        if opens_label {
+            in_label = true;
+        } else if closes_label {
+            in_label = false;
+        } else if style.brackets && !in_label {
+            depth = update_depth(depth, token);
+        }
+        prev = Some(token);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn format_text(text: &str, options: &FormatOptions) -> String {
+        format(&SyntaxTree::parse(text.to_string()), options)
+    }
+
+    #[test]
+    fn format_golden_files() {
+        let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../file_tests/formatting");
+        let mut inputs: Vec<_> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| !p.to_string_lossy().ends_with(".formatted.mermaid"))
+            .collect();
+        inputs.sort();
+        assert!(!inputs.is_empty());
+
+        for input in inputs {
+            let expected_path = input.with_extension("formatted.mermaid");
+            let text = std::fs::read_to_string(&input).unwrap();
+            let expected = std::fs::read_to_string(&expected_path).unwrap();
+
+            let formatted = format_text(&text, &FormatOptions::default());
+
+            assert_eq!(
+                formatted, expected,
+                "{:?} wasn't formatted as expected",
+                input
+            );
+            assert_eq!(
+                format_text(&formatted, &FormatOptions::default()),
+                formatted,
+                "Formatting {:?} isn't idempotent",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn format_is_idempotent() {
+        let inputs = [
+            include_str!("../../../file_tests/flowchart.mermaid"),
+            "",
+            "\n\n",
+            "---\ntitle: unclosed\n",
+            "flowchart LR\r\n  A-->B\r\n  end\r\n",
+            "mindmap\n  root\n      a\n        b\n      c\n",
+            "classDiagram\nclass A {\n  +int x\n}\n}",
+            "sequenceDiagram\nalt ok\nA->>B:hi\nelse\nB-->>A:  bye\nend",
+        ];
+
+        for input in inputs {
+            let formatted = format_text(input, &FormatOptions::default());
+            assert_eq!(
+                format_text(&formatted, &FormatOptions::default()),
+                formatted
+            );
+        }
+    }
+
+    #[test]
+    fn format_honors_options() {
+        let text = "flowchart TD\nsubgraph one\nA-->B\nend\n";
+
+        assert_eq!(
+            format_text(
+                text,
+                &FormatOptions {
+                    tab_size: 2,
+                    insert_spaces: true
+                }
+            ),
+            "flowchart TD\n  subgraph one\n    A --> B\n  end\n"
+        );
+        assert_eq!(
+            format_text(
+                text,
+                &FormatOptions {
+                    tab_size: 4,
+                    insert_spaces: false
+                }
+            ),
+            "flowchart TD\n\tsubgraph one\n\t\tA --> B\n\tend\n"
+        );
+    }
+
+    #[test]
+    fn format_lines_keeps_line_mapping() {
+        let tree = SyntaxTree::parse("flowchart TD\n\n\n  A-->B\ngantt".to_string());
+
+        let lines = format_lines(&tree, &FormatOptions::default());
+
+        assert_eq!(
+            lines,
+            vec!["flowchart TD\n", "\n", "", "    A --> B\n", "    gantt\n"]
+        );
+    }
+}
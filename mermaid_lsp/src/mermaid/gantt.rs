@@ -0,0 +1,163 @@
+use serde::Serialize;
+
+use super::{
+    cst::{tokens_span, SyntaxKind, SyntaxNode, SyntaxTree, TextSpan},
+    MermaidToken,
+};
+
+/// All the data relevant to a gantt diagram.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
+pub struct GanttDiagram {
+    /// The sections of the diagram, in order.
+    pub sections: Vec<GanttSection>,
+
+    /// Every task of the diagram, in order.
+    pub tasks: Vec<GanttTask>,
+}
+
+/// A `section` of a gantt diagram, it contains every task until the next section.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct GanttSection {
+    /// The text after the `section` keyword.
+    pub name: MermaidToken,
+
+    /// The span of the whole section.
+    pub span: TextSpan,
+}
+
+/// A task of a gantt diagram, like `Write docs :a1, 2024-01-01, 3d`.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct GanttTask {
+    /// The text before the `:`.
+    pub name: MermaidToken,
+
+    /// The data after the `:`, like the id, dates and tags.
+    pub metadata: Option<MermaidToken>,
+
+    /// The span of the section that contains the task, if any.
+    pub section: Option<TextSpan>,
+
+    /// The span of the whole statement.
+    pub span: TextSpan,
+}
+
+/// Keywords of statements that configure the diagram instead of declaring tasks.
+pub(crate) const GANTT_KEYWORDS: [&str; 12] = [
+    "title",
+    "dateFormat",
+    "axisFormat",
+    "tickInterval",
+    "excludes",
+    "includes",
+    "todayMarker",
+    "weekday",
+    "weekend",
+    "displayMode",
+    "accTitle",
+    "accDescr",
+];
+
+/// Parses all the data relevant to a gantt diagram.
+pub fn parse_gantt(tree: &SyntaxTree) -> GanttDiagram {
+    let mut diagram = GanttDiagram::default();
+
+    for child in tree.root().child_nodes() {
+        match child.kind() {
+            SyntaxKind::Statement => diagram.tasks.extend(parse_task(tree, child, None)),
+            SyntaxKind::Block => {
+                let name = child
+                    .block_start()
+                    .map(|s| s.significant_tokens().skip(1).collect::<Vec<_>>())
+                    .and_then(|tokens| tokens_span(&tokens));
+                if let Some(name) = name {
+                    diagram.sections.push(GanttSection {
+                        name: MermaidToken::new(tree, name),
+                        span: child.span(),
+                    });
+                }
+
+                diagram.tasks.extend(
+                    child
+                        .child_nodes()
+                        .filter(|n| n.kind() == SyntaxKind::Statement)
+                        .filter_map(|n| parse_task(tree, n, Some(child.span()))),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    diagram
+}
+
+/// Parses a task statement, `None` if it's a configuration statement.
+fn parse_task(
+    tree: &SyntaxTree,
+    statement: &SyntaxNode,
+    section: Option<TextSpan>,
+) -> Option<GanttTask> {
+    let tokens: Vec<_> = statement.significant_tokens().collect();
+    let first = tokens.first()?;
+    if GANTT_KEYWORDS.contains(&first.text()) {
+        return None;
+    }
+
+    let colon = tokens.iter().position(|t| t.is_punct(':'))?;
+    let name = tokens_span(&tokens[..colon])?;
+
+    Some(GanttTask {
+        name: MermaidToken::new(tree, name),
+        metadata: tokens_span(&tokens[colon + 1..]).map(|m| MermaidToken::new(tree, m)),
+        section,
+        span: statement.content_span()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIAGRAM: &str = r#"gantt
+    title A Gantt Diagram
+    dateFormat YYYY-MM-DD
+    Loose task :2014-01-01, 1d
+    section Section
+    A task :a1, 2014-01-01, 30d
+    Another task :after a1, 20d
+    section Another
+    Task in Another :2014-01-12, 12d
+"#;
+
+    #[test]
+    fn parse_gantt_success() {
+        let tree = SyntaxTree::parse(DIAGRAM.to_string());
+
+        let diagram = parse_gantt(&tree);
+
+        let sections: Vec<_> = diagram
+            .sections
+            .iter()
+            .map(|s| s.name.content.as_str())
+            .collect();
+        assert_eq!(sections, vec!["Section", "Another"]);
+
+        let tasks: Vec<_> = diagram
+            .tasks
+            .iter()
+            .map(|t| (t.name.content.as_str(), t.section.is_some()))
+            .collect();
+        assert_eq!(
+            tasks,
+            vec![
+                ("Loose task", false),
+                ("A task", true),
+                ("Another task", true),
+                ("Task in Another", true),
+            ]
+        );
+        assert_eq!(
+            diagram.tasks[1].metadata.as_ref().unwrap().content,
+            "a1, 2014-01-01, 30d"
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use super::cst::{
+    lex_lines, BodyParser, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxTree, TextSpan,
+};
+
+impl SyntaxTree {
+    /// Replaces the text inside `span` with `new_text` and updates the tree.
+    ///
+    /// Only the top level statements and blocks touched by the edit are lexed and parsed again,
+    /// the rest of the tree is reused. The elements after the edit still have their spans moved
+    /// one by one, so the cost grows with the size of the document, just far slower. Edits to
+    /// the frontmatter or the diagram header parse the whole document again since
+    /// they can change how every other line is interpreted.
+    pub fn edit(&mut self, span: TextSpan, new_text: &str) {
+        let (mut text, root, mut line_index) = std::mem::take(self).into_parts();
+        let span = TextSpan::new(span.start.min(text.len()), span.end.min(text.len()));
+        text.replace_range(span.start..span.end, new_text);
+        let delta = new_text.len() as isize - span.len() as isize;
+
+        let mut children = root.into_children();
+        let header = children
+            .iter()
+            .position(|c| c.kind() == SyntaxKind::DiagramHeader);
+        let header = match header {
+            Some(i)
+                if span.start >= children[i].span().end
+                    && text[..children[i].span().end].ends_with('\n') =>
+            {
+                i
+            }
+            _ => {
+                *self = SyntaxTree::parse(text);
+                return;
+            }
+        };
+
+        let d_type = super::diagram_body::parse_diagram_type(
+            &text[children[header].span().start..children[header].span().end],
+        );
+        let body = header + 1;
+
+        // The first and last top level elements touched by the edit, expanded to whole lines.
+        let first = children[body..]
+            .iter()
+            .position(|c| c.span().touches(span))
+            .map(|p| p + body);
+        let (mut first, last) = match first {
+            Some(first) => {
+                let last = children[first..]
+                    .iter()
+                    .rposition(|c| c.span().touches(span))
+                    .map(|p| p + first)
+                    .unwrap_or(first);
+                (
+                    line_start(&children, first, body),
+                    line_end(&children, last),
+                )
+            }
+            // Nothing after the header was touched, so the edit appended text at the end.
+            None => (children.len(), children.len().saturating_sub(1)),
+        };
+
+        // Blocks closed by the next section or indentation might swallow the edited lines.
+        while first > body
+            && children[first - 1]
+                .as_node()
+                .is_some_and(|n| n.kind() == SyntaxKind::Block && n.block_end().is_none())
+        {
+            first = line_start(&children, first - 1, body);
+        }
+
+        let region_start = children
+            .get(first)
+            .map(|c| c.span().start)
+            .unwrap_or(children[header].span().end);
+        let region_end = children
+            .get(last)
+            .filter(|_| last >= first)
+            .map(|c| c.span().end.saturating_add_signed(delta))
+            .unwrap_or(text.len())
+            .max(region_start);
+
+        let mut parser = BodyParser::new(&d_type);
+        lex_lines(&text[region_start..region_end], region_start)
+            .into_iter()
+            .for_each(|line| parser.push_line(line));
+
+        // Keep parsing the following lines until the parser is back at the top level.
+        let mut next = (last + 1).max(first);
+        while next < children.len() && !parser.can_resume_at(&children[next]) {
+            let end = line_end(&children, next);
+            let start = children[next].span().start.saturating_add_signed(delta);
+            let stop = children[end].span().end.saturating_add_signed(delta);
+            lex_lines(&text[start..stop], start)
+                .into_iter()
+                .for_each(|line| parser.push_line(line));
+            next = end + 1;
+        }
+
+        let reparsed = parser.finish();
+        let reparsed_len = reparsed.len();
+        children.splice(first..next, reparsed);
+        children[first + reparsed_len..]
+            .iter_mut()
+            .for_each(|c| c.shift(delta));
+
+        line_index.edit(span, new_text);
+        *self = SyntaxTree::from_parts(
+            text,
+            SyntaxNode::new(SyntaxKind::Root, children, 0),
+            line_index,
+        );
+    }
+}
+
+/// Index of the first element of the line where `children[i]` is, never before `min`.
+fn line_start(children: &[SyntaxElement], mut i: usize, min: usize) -> usize {
+    while i > min
+        && matches!(&children[i - 1], SyntaxElement::Token(t) if t.kind() != SyntaxKind::Newline)
+    {
+        i -= 1;
+    }
+    i
+}
+
+/// Index of the last element of the line where `children[i]` is.
+fn line_end(children: &[SyntaxElement], mut i: usize) -> usize {
+    while i + 1 < children.len()
+        && matches!(&children[i], SyntaxElement::Token(t) if t.kind() != SyntaxKind::Newline)
+    {
+        i += 1;
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIAGRAMS: [&str; 5] = [
+        r#"---
+title: Incremental
+---
+flowchart TD
+    A[Start] --> B{Is it?}
+    subgraph one [The first]
+        B -- Yes --> C[OK]
+        %% A comment
+        C --> D[Rethink]
+    end
+
+    D --> B
+    B -- No ----> E[End]
+"#,
+        "sequenceDiagram\n    Alice->>Bob: Hi\n    loop Every minute\n        Bob-->>Alice: Hello\n    end\n",
+        "gantt\n    title A\n    section One\n    a :a1, 2024-01-01, 1d\n\n    section Two\n    b :after a1, 1d\n",
+        "classDiagram\nclass A {\n  +int x\n}\nA <|-- B",
+        "mindmap\n  root\n    a\n      b\n    c\n",
+    ];
+
+    const INSERTIONS: [&str; 10] = [
+        "",
+        "x",
+        "\n",
+        "end\n",
+        "subgraph s\n",
+        "}",
+        "section Three\n",
+        " --> Z",
+        "\n  d",
+        "%%",
+    ];
+
+    /// Applies an edit incrementally and checks it matches parsing from scratch.
+    fn assert_edit(text: &str, span: TextSpan, new_text: &str) {
+        let mut tree = SyntaxTree::parse(text.to_string());
+        tree.edit(span, new_text);
+
+        let mut expected_text = text.to_string();
+        expected_text.replace_range(span.start..span.end, new_text);
+        let expected = SyntaxTree::parse(expected_text);
+
+        assert_eq!(
+            tree, expected,
+            "Edit {:?} -> {:?} of {:?}",
+            span, new_text, text
+        );
+    }
+
+    #[test]
+    fn edit_matches_full_parse() {
+        for text in DIAGRAMS {
+            let boundaries: Vec<_> = (0..=text.len())
+                .filter(|i| text.is_char_boundary(*i))
+                .collect();
+
+            for (n, start) in boundaries.iter().enumerate() {
+                for end in boundaries[n..].iter().take(8) {
+                    for new_text in INSERTIONS {
+                        assert_edit(text, TextSpan::new(*start, *end), new_text);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn edit_reuses_unchanged_statements() {
+        let text = DIAGRAMS[0];
+        let mut tree = SyntaxTree::parse(text.to_string());
+        let offset = text.find("D --> B").unwrap();
+
+        tree.edit(TextSpan::new(offset, offset + 1), "F");
+
+        let block = tree
+            .root()
+            .child_nodes()
+            .find(|n| n.kind() == SyntaxKind::Block)
+            .unwrap();
+        assert_eq!(block.span().start, text.find("    subgraph").unwrap());
+        assert!(tree.root().text().contains("F --> B"));
+    }
+}
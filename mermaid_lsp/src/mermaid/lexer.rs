@@ -0,0 +1,169 @@
+use std::{iter::Peekable, str::CharIndices};
+
+use super::cst::{SyntaxKind, SyntaxToken, TextSpan};
+
+/// Characters that can be part of an arrow like `-->`, `==>`, `-.->` or `<<-->>`.
+const ARROW_CHARS: [char; 6] = ['-', '=', '.', '<', '>', '~'];
+
+/// Splits a single line of a mermaid diagram into lossless tokens.
+///
+/// `offset` is the byte offset of the line inside the whole document, it's
+/// used so every token knows its absolute position. The line may include its
+/// line terminator, which will be emitted as a `Newline` token.
+pub fn lex_line(line: &str, offset: usize) -> Vec<SyntaxToken> {
+    let mut tokens = vec![];
+    let mut chars = line.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let kind = match c {
+            '\r' | '\n' => {
+                while chars.next_if(|(_, c)| *c == '\r' || *c == '\n').is_some() {}
+                SyntaxKind::Newline
+            }
+            c if c.is_whitespace() => {
+                while chars
+                    .next_if(|(_, c)| c.is_whitespace() && *c != '\r' && *c != '\n')
+                    .is_some()
+                {}
+                SyntaxKind::Whitespace
+            }
+            '%' if line[start..].starts_with("%%") => {
+                let kind = if line[start..].starts_with("%%{") {
+                    SyntaxKind::Directive
+                } else {
+                    SyntaxKind::Comment
+                };
+                while chars.next_if(|(_, c)| *c != '\r' && *c != '\n').is_some() {}
+                kind
+            }
+            '"' => {
+                // Strings can't span multiple lines, an unterminated string ends with the line.
+                while let Some((_, c)) = chars.next_if(|(_, c)| *c != '\r' && *c != '\n') {
+                    if c == '"' {
+                        break;
+                    }
+                }
+                SyntaxKind::String
+            }
+            // The circle or cross at the start of an arrow like `o--o` or `x==x`.
+            'o' | 'x'
+                if tokens
+                    .last()
+                    .is_some_and(|t: &SyntaxToken| t.kind() == SyntaxKind::Whitespace)
+                    && (line[start + 1..].starts_with("--")
+                        || line[start + 1..].starts_with("==")) =>
+            {
+                lex_arrow(&mut chars, line, start);
+                SyntaxKind::Arrow
+            }
+            c if is_ident_char(c) => {
+                while chars.next_if(|(_, c)| is_ident_char(*c)).is_some() {}
+                SyntaxKind::Ident
+            }
+            c if ARROW_CHARS.contains(&c) => {
+                lex_arrow(&mut chars, line, start);
+                SyntaxKind::Arrow
+            }
+            c if c.is_ascii_punctuation() => SyntaxKind::Punct,
+            _ => SyntaxKind::Text,
+        };
+
+        let end = chars.peek().map(|(i, _)| *i).unwrap_or(line.len());
+        tokens.push(SyntaxToken::new(
+            kind,
+            line[start..end].to_string(),
+            TextSpan::new(offset + start, offset + end),
+        ));
+    }
+
+    tokens
+}
+
+/// Consumes the rest of an arrow that starts at `start`.
+///
+/// Arrows like `--o` and `--x` end with the letter of a circle or a cross, even when a name
+/// follows without a space. Mermaid reads `A--oB` as a circle link from `A` to `B`.
+fn lex_arrow(chars: &mut Peekable<CharIndices>, line: &str, start: usize) {
+    while chars.next_if(|(_, c)| ARROW_CHARS.contains(c)).is_some() {}
+
+    let end = chars.peek().map_or(line.len(), |(i, _)| *i);
+    let arrow = &line[start..end];
+    if arrow.len() > 1 && arrow.ends_with(['-', '=']) {
+        chars.next_if(|(_, c)| matches!(c, 'o' | 'x'));
+    }
+}
+
+/// Checks if a character can be part of an identifier.
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds_and_text(line: &str) -> Vec<(SyntaxKind, String)> {
+        lex_line(line, 0)
+            .into_iter()
+            .map(|t| (t.kind(), t.text().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn lex_line_is_lossless() {
+        let line = "    A[Start] --> B{Is it?} %% comment\r\n";
+
+        let text: String = lex_line(line, 0).iter().map(|t| t.text()).collect();
+
+        assert_eq!(text, line);
+    }
+
+    #[test]
+    fn lex_line_spans_are_absolute() {
+        let tokens = lex_line("A-->B", 10);
+
+        let spans: Vec<_> = tokens.iter().map(|t| t.span()).collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                TextSpan::new(10, 11),
+                TextSpan::new(11, 14),
+                TextSpan::new(14, 15)
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_line_arrows() {
+        let tokens = kinds_and_text("B -- No ----> E --o F --xylophone G o--o H x==x I<-->J o");
+
+        let arrows: Vec<_> = tokens
+            .into_iter()
+            .filter(|(k, _)| *k == SyntaxKind::Arrow)
+            .map(|(_, t)| t)
+            .collect();
+
+        assert_eq!(
+            arrows,
+            vec!["--", "---->", "--o", "--x", "o--o", "x==x", "<-->"]
+        );
+    }
+
+    #[test]
+    fn lex_line_strings_and_comments() {
+        let tokens = kinds_and_text(r#"A["a (label)"] %%{init: {}}%%"#);
+
+        assert_eq!(
+            tokens,
+            vec![
+                (SyntaxKind::Ident, "A".to_string()),
+                (SyntaxKind::Punct, "[".to_string()),
+                (SyntaxKind::String, r#""a (label)""#.to_string()),
+                (SyntaxKind::Punct, "]".to_string()),
+                (SyntaxKind::Whitespace, " ".to_string()),
+                (SyntaxKind::Directive, "%%{init: {}}%%".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use super::TextSpan;
+use crate::jsonrpc::Position;
+
+/// Maps byte offsets of a document into LSP positions and back.
+///
+/// LSP positions count characters in UTF-16 code units, so the index needs the
+/// text of the document to translate columns of lines with non ASCII characters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineIndex {
+    /// Byte offset where each line starts, the first line always starts at 0.
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    /// Creates a new index from the text of a document.
+    pub fn new(text: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        LineIndex { line_starts }
+    }
+
+    /// Updates the index after the text inside `span` was replaced with `new_text`.
+    ///
+    /// Only the lines touched by the edit are scanned, the lines after it are just shifted.
+    pub fn edit(&mut self, span: TextSpan, new_text: &str) {
+        let delta = new_text.len() as isize - span.len() as isize;
+        let first = self
+            .line_starts
+            .partition_point(|start| *start <= span.start);
+        let last = self.line_starts.partition_point(|start| *start <= span.end);
+
+        let inserted = new_text
+            .match_indices('\n')
+            .map(|(i, _)| span.start + i + 1);
+        self.line_starts.splice(first..last, inserted);
+
+        let shifted = first + new_text.matches('\n').count();
+        self.line_starts[shifted..]
+            .iter_mut()
+            .for_each(|start| *start = start.saturating_add_signed(delta));
+    }
+
+    /// The amount of lines the document has.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Byte offset where the given line starts, `None` if the line doesn't exist.
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        self.line_starts.get(line).copied()
+    }
+
+    /// Converts a byte offset of `text` into an LSP position.
+    ///
+    /// Offsets past the end of the text are clamped to the end of the document.
+    pub fn position(&self, text: &str, offset: usize) -> Position {
+        let offset = floor_char_boundary(text, offset.min(text.len()));
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let line_start = self.line_starts[line];
+        let character = utf16_len(&text[line_start..offset]);
+
+        Position {
+            line: line as u32,
+            character: character as u32,
+        }
+    }
+
+    /// Converts an LSP position into a byte offset of `text`.
+    ///
+    /// Positions past the end of a line are clamped to the end of that line and
+    /// lines past the end of the document are clamped to the end of the text.
+    pub fn offset(&self, text: &str, position: Position) -> usize {
+        let Some(line_start) = self.line_start(position.line as usize) else {
+            return text.len();
+        };
+        let line_end = self
+            .line_start(position.line as usize + 1)
+            .unwrap_or(text.len());
+        let line = text[line_start..line_end].trim_end_matches(['\n', '\r']);
+
+        let mut utf16_count = 0;
+        for (i, c) in line.char_indices() {
+            if utf16_count >= position.character as usize {
+                return line_start + i;
+            }
+            utf16_count += c.len_utf16();
+        }
+
+        line_start + line.len()
+    }
+}
+
+/// The length of a text in UTF-16 code units, without encoding it when it's ASCII.
+fn utf16_len(text: &str) -> usize {
+    match text.is_ascii() {
+        true => text.len(),
+        false => text.chars().map(char::len_utf16).sum(),
+    }
+}
+
+/// Finds the closest char boundary that is less or equal than `offset`.
+fn floor_char_boundary(text: &str, mut offset: usize) -> usize {
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "flowchart TD\n    A[Ñandú] --> B\n";
+
+    #[test]
+    fn position_success() {
+        let index = LineIndex::new(TEXT);
+        let offset = TEXT.find("-->").unwrap();
+
+        let position = index.position(TEXT, offset);
+
+        assert_eq!(
+            position,
+            Position {
+                line: 1,
+                character: 13
+            }
+        );
+    }
+
+    #[test]
+    fn offset_roundtrips_position() {
+        let index = LineIndex::new(TEXT);
+
+        for (offset, _) in TEXT.char_indices() {
+            let position = index.position(TEXT, offset);
+            assert_eq!(index.offset(TEXT, position), offset, "{:?}", position);
+        }
+    }
+
+    #[test]
+    fn edit_matches_new_index() {
+        let edits = [
+            (0, 0, "x"),
+            (3, 19, "\n\n"),
+            (12, 13, ""),
+            (13, 13, "a\nb"),
+            (0, 34, ""),
+            (21, 34, "\n"),
+        ];
+
+        for (start, end, new_text) in edits {
+            let mut index = LineIndex::new(TEXT);
+            let mut text = TEXT.to_string();
+            text.replace_range(start..end, new_text);
+
+            index.edit(TextSpan::new(start, end), new_text);
+
+            assert_eq!(index, LineIndex::new(&text), "{:?}", (start, end, new_text));
+        }
+    }
+
+    #[test]
+    fn offset_clamps_to_line_end() {
+        let index = LineIndex::new(TEXT);
+
+        let offset = index.offset(
+            TEXT,
+            Position {
+                line: 0,
+                character: 100,
+            },
+        );
+
+        assert_eq!(offset, "flowchart TD".len());
+    }
+}
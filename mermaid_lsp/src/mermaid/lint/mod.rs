@@ -0,0 +1,232 @@
+mod rules;
+mod suppression;
+
+use std::collections::HashMap;
+#[cfg(feature = "server")]
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::{DiagnosticCode, DiagnosticSeverity, DiagramDiagnostic, MermaidAST, TextSpan};
+
+pub use rules::RULES;
+pub use suppression::{apply_suppressions, suppression_fix, DISABLE, DISABLE_NEXT_LINE, ENABLE};
+
+/// The name of the project config file, read from the root of every workspace folder.
+pub const CONFIG_FILE_NAME: &str = ".mermaid-lsp.json";
+
+/// A check that finds questionable but valid constructs of a diagram.
+#[derive(Debug)]
+pub struct LintRule {
+    /// The code of the rule, like `self-loop`, used to configure it and shown with its diagnostics.
+    pub code: &'static str,
+
+    /// What the rule checks.
+    pub description: &'static str,
+
+    /// The severity of the rule when it isn't configured.
+    pub default_severity: DiagnosticSeverity,
+
+    /// Finds the violations of the rule.
+    pub check: fn(&MermaidAST) -> Vec<LintViolation>,
+}
+
+/// A place where a rule is broken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintViolation {
+    pub span: TextSpan,
+    pub message: String,
+}
+
+impl LintViolation {
+    fn new(span: TextSpan, message: String) -> Self {
+        LintViolation { span, message }
+    }
+}
+
+/// The severity configured for a rule, `off` disables it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleSeverity {
+    Off,
+    Error,
+    Warning,
+    #[serde(alias = "info")]
+    Information,
+    Hint,
+}
+
+impl RuleSeverity {
+    /// The severity of the diagnostics, `None` if the rule is disabled.
+    pub fn diagnostic_severity(&self) -> Option<DiagnosticSeverity> {
+        match self {
+            RuleSeverity::Off => None,
+            RuleSeverity::Error => Some(DiagnosticSeverity::Error),
+            RuleSeverity::Warning => Some(DiagnosticSeverity::Warning),
+            RuleSeverity::Information => Some(DiagnosticSeverity::Information),
+            RuleSeverity::Hint => Some(DiagnosticSeverity::Hint),
+        }
+    }
+}
+
+/// The severity of each rule, rules that aren't listed keep their default severity.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct LintConfig {
+    pub rules: HashMap<String, RuleSeverity>,
+}
+
+/// The contents of the project config file.
+#[cfg(feature = "server")]
+#[derive(Debug, Default, Deserialize)]
+struct ProjectConfig {
+    #[serde(default)]
+    lint: LintConfig,
+}
+
+#[derive(Debug)]
+pub enum LintConfigErrors {
+    ReadError(std::io::Error),
+    InvalidConfig(serde_json::Error),
+}
+
+impl LintConfig {
+    /// Reads the `lint` section of the project config file inside `folder`.
+    ///
+    /// A folder without a config file gets the default config.
+    #[cfg(feature = "server")]
+    pub fn from_folder(folder: &Path) -> Result<Self, LintConfigErrors> {
+        let path = folder.join(CONFIG_FILE_NAME);
+        if !path.exists() {
+            return Ok(LintConfig::default());
+        }
+
+        let content = std::fs::read_to_string(path).map_err(LintConfigErrors::ReadError)?;
+        let config: ProjectConfig =
+            serde_json::from_str(&content).map_err(LintConfigErrors::InvalidConfig)?;
+        Ok(config.lint)
+    }
+
+    /// Overrides the severities of this config with the ones of `other`.
+    pub fn merge(&mut self, other: LintConfig) {
+        self.rules.extend(other.rules);
+    }
+
+    /// The severity of a rule, `None` if it's disabled.
+    pub fn severity(&self, rule: &LintRule) -> Option<DiagnosticSeverity> {
+        match self.rules.get(rule.code) {
+            Some(severity) => severity.diagnostic_severity(),
+            None => Some(rule.default_severity),
+        }
+    }
+}
+
+/// The lint config of the server, the client settings override the project config files.
+///
+/// Each layer is replaced as a whole when its source changes, so a rule removed from the
+/// client settings gets back the severity of the project config, or its default one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintSettings {
+    project: LintConfig,
+    client: LintConfig,
+    combined: LintConfig,
+}
+
+impl LintSettings {
+    /// Replaces the severities read from the project config files.
+    pub fn set_project(&mut self, config: LintConfig) {
+        self.project = config;
+        self.combine();
+    }
+
+    /// Replaces the severities of the client settings.
+    pub fn set_client(&mut self, config: LintConfig) {
+        self.client = config;
+        self.combine();
+    }
+
+    /// The severities of both layers together.
+    pub fn config(&self) -> &LintConfig {
+        &self.combined
+    }
+
+    fn combine(&mut self) {
+        self.combined = self.project.clone();
+        self.combined.merge(self.client.clone());
+    }
+}
+
+/// Runs every enabled rule on a document.
+pub fn lint(ast: &MermaidAST, config: &LintConfig) -> Vec<DiagramDiagnostic> {
+    RULES
+        .iter()
+        .filter_map(|rule| Some((rule, config.severity(rule)?)))
+        .flat_map(|(rule, severity)| {
+            (rule.check)(ast)
+                .into_iter()
+                .map(move |violation| DiagramDiagnostic {
+                    code: DiagnosticCode::Lint(rule.code),
+                    severity,
+                    message: violation.message,
+                    span: violation.span,
+                    fix: None,
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lint_rules_have_unique_codes() {
+        let mut codes: Vec<_> = RULES.iter().map(|r| r.code).collect();
+        codes.sort();
+        codes.dedup();
+
+        assert_eq!(codes.len(), RULES.len());
+    }
+
+    #[test]
+    fn lint_config_severities() {
+        let ast = MermaidAST::from_content("flowchart TD\n    A --> A\n".to_string());
+        let config: LintConfig = serde_json::from_value(serde_json::json!({
+            "self-loop": "error",
+            "unreferenced-node": "off",
+        }))
+        .unwrap();
+
+        let diagnostics = lint(&ast, &config);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::Lint("self-loop"));
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+
+        let config: LintConfig =
+            serde_json::from_value(serde_json::json!({ "self-loop": "off" })).unwrap();
+        assert_eq!(lint(&ast, &config), vec![]);
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn lint_config_from_folder() {
+        let folder = tempfile::tempdir().unwrap();
+        assert_eq!(
+            LintConfig::from_folder(folder.path()).unwrap(),
+            LintConfig::default()
+        );
+
+        std::fs::write(
+            folder.path().join(CONFIG_FILE_NAME),
+            r#"{ "lint": { "duplicate-edge": "info" } }"#,
+        )
+        .unwrap();
+        let config = LintConfig::from_folder(folder.path()).unwrap();
+
+        assert_eq!(
+            config.rules.get("duplicate-edge"),
+            Some(&RuleSeverity::Information)
+        );
+    }
+}
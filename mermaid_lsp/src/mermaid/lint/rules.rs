@@ -0,0 +1,315 @@
+use std::collections::HashSet;
+
+use super::{LintRule, LintViolation};
+use crate::mermaid::{DiagnosticSeverity, DiagramData, MermaidAST, START_END_STATE};
+
+/// Every rule known by the linter.
+pub const RULES: [LintRule; 8] = [
+    LintRule {
+        code: "unused-class-def",
+        description: "A `classDef` that no node uses",
+        default_severity: DiagnosticSeverity::Warning,
+        check: unused_class_def,
+    },
+    LintRule {
+        code: "unreferenced-node",
+        description: "A flowchart node without links",
+        default_severity: DiagnosticSeverity::Hint,
+        check: unreferenced_node,
+    },
+    LintRule {
+        code: "duplicate-edge",
+        description: "The same link between two nodes declared twice",
+        default_severity: DiagnosticSeverity::Warning,
+        check: duplicate_edge,
+    },
+    LintRule {
+        code: "self-loop",
+        description: "A link from a node to itself",
+        default_severity: DiagnosticSeverity::Information,
+        check: self_loop,
+    },
+    LintRule {
+        code: "conflicting-node-label",
+        description: "A node declared again with a different label",
+        default_severity: DiagnosticSeverity::Warning,
+        check: conflicting_node_label,
+    },
+    LintRule {
+        code: "unreachable-state",
+        description: "A state that can't be reached from the start state",
+        default_severity: DiagnosticSeverity::Warning,
+        check: unreachable_state,
+    },
+    LintRule {
+        code: "unused-participant",
+        description: "A sequence participant declared but never used",
+        default_severity: DiagnosticSeverity::Warning,
+        check: unused_participant,
+    },
+    LintRule {
+        code: "task-without-section",
+        description: "A gantt task outside of every section",
+        default_severity: DiagnosticSeverity::Information,
+        check: task_without_section,
+    },
+];
+
+fn unused_class_def(ast: &MermaidAST) -> Vec<LintViolation> {
+    let DiagramData::Flowchart(flowchart) = &ast.diagram.data else {
+        return vec![];
+    };
+
+    flowchart
+        .class_defs
+        .iter()
+        .filter(|d| {
+            !flowchart
+                .class_usages
+                .iter()
+                .any(|u| u.content == d.content)
+        })
+        .map(|d| LintViolation::new(d.span, format!("The class `{}` is never used", d.content)))
+        .collect()
+}
+
+fn unreferenced_node(ast: &MermaidAST) -> Vec<LintViolation> {
+    let DiagramData::Flowchart(flowchart) = &ast.diagram.data else {
+        return vec![];
+    };
+
+    let linked: HashSet<_> = flowchart
+        .edges
+        .iter()
+        .flat_map(|e| [&e.from.content, &e.to.content])
+        .collect();
+    let mut reported = HashSet::new();
+    flowchart
+        .nodes
+        .iter()
+        .filter(|n| !linked.contains(&n.id.content) && reported.insert(&n.id.content))
+        .map(|n| {
+            LintViolation::new(
+                n.id.span,
+                format!("The node `{}` has no links", n.id.content),
+            )
+        })
+        .collect()
+}
+
+fn duplicate_edge(ast: &MermaidAST) -> Vec<LintViolation> {
+    let DiagramData::Flowchart(flowchart) = &ast.diagram.data else {
+        return vec![];
+    };
+
+    let mut seen = HashSet::new();
+    flowchart
+        .edges
+        .iter()
+        .filter(|e| {
+            let label = e.label.as_ref().map(|l| &l.content);
+            !seen.insert((&e.from.content, &e.to.content, &e.arrow.content, label))
+        })
+        .map(|e| {
+            LintViolation::new(
+                e.link,
+                format!(
+                    "The link from `{}` to `{}` is declared twice",
+                    e.from.content, e.to.content
+                ),
+            )
+        })
+        .collect()
+}
+
+fn self_loop(ast: &MermaidAST) -> Vec<LintViolation> {
+    let DiagramData::Flowchart(flowchart) = &ast.diagram.data else {
+        return vec![];
+    };
+
+    flowchart
+        .edges
+        .iter()
+        .filter(|e| e.from.content == e.to.content)
+        .map(|e| {
+            LintViolation::new(
+                e.link,
+                format!("The node `{}` links to itself", e.from.content),
+            )
+        })
+        .collect()
+}
+
+fn conflicting_node_label(ast: &MermaidAST) -> Vec<LintViolation> {
+    let DiagramData::Flowchart(flowchart) = &ast.diagram.data else {
+        return vec![];
+    };
+
+    let labelled: Vec<_> = flowchart
+        .nodes
+        .iter()
+        .filter_map(|n| Some((&n.id, n.label.as_ref()?)))
+        .collect();
+    labelled
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (id, label))| {
+            let (_, first) = labelled[..i]
+                .iter()
+                .find(|(other, l)| other.content == id.content && l.content != label.content)?;
+            Some(LintViolation::new(
+                label.span,
+                format!(
+                    "The node `{}` was already declared with the label `{}`",
+                    id.content, first.content
+                ),
+            ))
+        })
+        .collect()
+}
+
+/// States are reached from every `[*]`, composite states start their own states from their `[*]`.
+fn unreachable_state(ast: &MermaidAST) -> Vec<LintViolation> {
+    let DiagramData::State(diagram) = &ast.diagram.data else {
+        return vec![];
+    };
+    if !diagram
+        .transitions
+        .iter()
+        .any(|t| t.from.content == START_END_STATE)
+    {
+        return vec![];
+    }
+
+    let mut reached = HashSet::from([START_END_STATE]);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for transition in &diagram.transitions {
+            if reached.contains(transition.from.content.as_str()) {
+                changed |= reached.insert(transition.to.content.as_str());
+            }
+        }
+    }
+
+    diagram
+        .states
+        .iter()
+        .filter(|s| s.id.content != START_END_STATE && !reached.contains(s.id.content.as_str()))
+        .map(|s| {
+            LintViolation::new(
+                s.id.span,
+                format!("The state `{}` can't be reached", s.id.content),
+            )
+        })
+        .collect()
+}
+
+fn unused_participant(ast: &MermaidAST) -> Vec<LintViolation> {
+    let DiagramData::Sequence(diagram) = &ast.diagram.data else {
+        return vec![];
+    };
+
+    let used: HashSet<_> = diagram
+        .messages
+        .iter()
+        .flat_map(|m| [&m.from.content, &m.to.content])
+        .chain(diagram.references.iter().map(|r| &r.content))
+        .collect();
+    diagram
+        .participants
+        .iter()
+        .filter(|p| !used.contains(&p.id.content))
+        .map(|p| {
+            LintViolation::new(
+                p.id.span,
+                format!("The participant `{}` is never used", p.id.content),
+            )
+        })
+        .collect()
+}
+
+fn task_without_section(ast: &MermaidAST) -> Vec<LintViolation> {
+    let DiagramData::Gantt(diagram) = &ast.diagram.data else {
+        return vec![];
+    };
+
+    diagram
+        .tasks
+        .iter()
+        .filter(|t| t.section.is_none())
+        .map(|t| {
+            LintViolation::new(
+                t.name.span,
+                format!("The task `{}` is not inside a section", t.name.content),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn violations(code: &str, content: &str) -> Vec<String> {
+        let ast = MermaidAST::from_content(content.to_string());
+        let rule = RULES.iter().find(|r| r.code == code).unwrap();
+
+        (rule.check)(&ast)
+            .into_iter()
+            .map(|v| ast.cst.slice(v.span).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn lint_flowchart_rules() {
+        let content = r#"flowchart TD
+    classDef used fill:#f9f
+    classDef unused fill:#fff
+    A[Start]:::used --> B
+    A --> B
+    B --> B
+    A[Other] --> C
+    D
+"#;
+
+        assert_eq!(violations("unused-class-def", content), vec!["unused"]);
+        assert_eq!(violations("unreferenced-node", content), vec!["D"]);
+        assert_eq!(violations("duplicate-edge", content), vec!["-->"]);
+        assert_eq!(violations("self-loop", content), vec!["-->"]);
+        assert_eq!(violations("conflicting-node-label", content), vec!["Other"]);
+    }
+
+    #[test]
+    fn lint_state_rules() {
+        let content = r#"stateDiagram-v2
+    [*] --> Still
+    Still --> Moving
+    Crash --> [*]
+    state Composite {
+        [*] --> Inner
+    }
+"#;
+
+        assert_eq!(
+            violations("unreachable-state", content),
+            vec!["Crash", "Composite"]
+        );
+        assert!(violations("unreachable-state", "stateDiagram-v2\n    A --> B\n").is_empty());
+    }
+
+    #[test]
+    fn lint_sequence_rules() {
+        let content = "sequenceDiagram\n    participant A\n    participant B\n    participant C\n    A->>B: Hi\n    Note over C: Alone\n    participant D\n";
+
+        assert_eq!(violations("unused-participant", content), vec!["D"]);
+    }
+
+    #[test]
+    fn lint_gantt_rules() {
+        let content =
+            "gantt\n    title Plan\n    Setup :a0, 1d\n    section One\n    Task :a1, 1d\n";
+
+        assert_eq!(violations("task-without-section", content), vec!["Setup"]);
+    }
+}
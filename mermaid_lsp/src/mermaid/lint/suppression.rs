@@ -0,0 +1,275 @@
+use crate::mermaid::{
+    diagnostics::insert_line, DiagnosticCode, DiagnosticSeverity, DiagramDiagnostic, DiagramEdit,
+    DiagramFix, SyntaxKind, SyntaxTree, TextSpan,
+};
+
+/// Disables the rules listed after it on the next line.
+pub const DISABLE_NEXT_LINE: &str = "mermaid-lsp-disable-next-line";
+
+/// Disables the rules listed after it until they are enabled again.
+pub const DISABLE: &str = "mermaid-lsp-disable";
+
+/// Enables the rules listed after it again.
+pub const ENABLE: &str = "mermaid-lsp-enable";
+
+/// What a suppression comment does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SuppressionKind {
+    DisableNextLine,
+    Disable,
+    Enable,
+}
+
+/// A comment like `%% mermaid-lsp-disable-next-line self-loop`.
+#[derive(Debug)]
+struct Suppression {
+    kind: SuppressionKind,
+
+    /// The codes of the rules, every rule if empty.
+    rules: Vec<String>,
+
+    /// The zero-based line of the comment.
+    line: u32,
+
+    /// The span of the comment.
+    span: TextSpan,
+}
+
+impl Suppression {
+    /// Parses a comment, `None` if it isn't a suppression.
+    fn parse(tree: &SyntaxTree, span: TextSpan) -> Option<Self> {
+        let text = tree.slice(span).strip_prefix("%%")?;
+        let mut words = text.split([' ', '\t', ',']).filter(|w| !w.is_empty());
+        let kind = match words.next()? {
+            DISABLE_NEXT_LINE => SuppressionKind::DisableNextLine,
+            DISABLE => SuppressionKind::Disable,
+            ENABLE => SuppressionKind::Enable,
+            _ => return None,
+        };
+
+        Some(Suppression {
+            kind,
+            rules: words.map(str::to_string).collect(),
+            line: tree.position(span.start).line,
+            span,
+        })
+    }
+
+    fn matches(&self, code: &str) -> bool {
+        self.rules.is_empty() || self.rules.iter().any(|r| r == code)
+    }
+}
+
+/// Removes the lint diagnostics silenced by suppression comments.
+///
+/// Every suppression that doesn't silence anything gets a hint, other diagnostics are kept as they are.
+pub fn apply_suppressions(
+    tree: &SyntaxTree,
+    diagnostics: Vec<DiagramDiagnostic>,
+) -> Vec<DiagramDiagnostic> {
+    let suppressions: Vec<_> = tree
+        .root()
+        .tokens()
+        .filter(|t| t.kind() == SyntaxKind::Comment)
+        .filter_map(|t| Suppression::parse(tree, t.span()))
+        .collect();
+    if suppressions.is_empty() {
+        return diagnostics;
+    }
+
+    let mut used = vec![false; suppressions.len()];
+    let mut kept: Vec<_> = diagnostics
+        .into_iter()
+        .filter(|diagnostic| {
+            let DiagnosticCode::Lint(code) = diagnostic.code else {
+                return true;
+            };
+            let line = tree.position(diagnostic.span.start).line;
+
+            match suppressing(&suppressions, code, line) {
+                Some(i) => {
+                    used[i] = true;
+                    false
+                }
+                None => true,
+            }
+        })
+        .collect();
+
+    kept.extend(
+        suppressions
+            .iter()
+            .zip(used)
+            .filter(|(s, used)| !used && s.kind != SuppressionKind::Enable)
+            .map(|(s, _)| unused_suppression(tree, s)),
+    );
+    kept
+}
+
+/// The index of the suppression that silences `code` on `line`, if any.
+fn suppressing(suppressions: &[Suppression], code: &str, line: u32) -> Option<usize> {
+    let next_line = suppressions.iter().position(|s| {
+        s.kind == SuppressionKind::DisableNextLine && s.line + 1 == line && s.matches(code)
+    });
+
+    // The last `disable` before the line wins, unless the rule was enabled again after it.
+    next_line.or_else(|| {
+        let mut disabled = None;
+        for (i, s) in suppressions.iter().enumerate() {
+            if s.line >= line || !s.matches(code) {
+                continue;
+            }
+            match s.kind {
+                SuppressionKind::Disable => disabled = Some(i),
+                SuppressionKind::Enable => disabled = None,
+                SuppressionKind::DisableNextLine => {}
+            }
+        }
+        disabled
+    })
+}
+
+fn unused_suppression(tree: &SyntaxTree, suppression: &Suppression) -> DiagramDiagnostic {
+    let line_start = tree
+        .line_index()
+        .line_start(suppression.line as usize)
+        .unwrap_or_default();
+    let only_comment = tree
+        .slice(TextSpan::new(line_start, suppression.span.start))
+        .trim()
+        .is_empty();
+    // A comment alone on its line is removed with the line.
+    let span = match only_comment {
+        true => {
+            let rest = &tree.text()[suppression.span.end..];
+            let end = rest
+                .find('\n')
+                .map_or(tree.text().len(), |i| suppression.span.end + i + 1);
+            TextSpan::new(line_start, end)
+        }
+        false => suppression.span,
+    };
+
+    DiagramDiagnostic {
+        code: DiagnosticCode::UnusedSuppression,
+        severity: DiagnosticSeverity::Hint,
+        message: "This suppression doesn't silence any diagnostic".to_string(),
+        span: suppression.span,
+        fix: Some(DiagramFix {
+            title: "Remove unused suppression".to_string(),
+            edits: vec![DiagramEdit {
+                span,
+                new_text: String::new(),
+            }],
+        }),
+    }
+}
+
+/// A fix that silences a lint diagnostic with a `disable-next-line` comment above it.
+pub fn suppression_fix(tree: &SyntaxTree, diagnostic: &DiagramDiagnostic) -> Option<DiagramFix> {
+    let DiagnosticCode::Lint(code) = diagnostic.code else {
+        return None;
+    };
+
+    let line = tree.position(diagnostic.span.start).line as usize;
+    let line_start = tree.line_index().line_start(line)?;
+    let rest = &tree.text()[line_start..];
+    let indentation = &rest[..rest.len() - rest.trim_start_matches([' ', '\t']).len()];
+
+    Some(DiagramFix {
+        title: format!("Disable `{}` for this line", code),
+        edits: vec![insert_line(
+            tree,
+            line_start,
+            &format!("{}%% {} {}", indentation, DISABLE_NEXT_LINE, code),
+        )],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mermaid::{
+        diagnostics,
+        lint::{LintConfig, RuleSeverity},
+        MermaidAST,
+    };
+
+    fn codes(content: &str) -> Vec<(&'static str, u32)> {
+        let ast = MermaidAST::from_content(content.to_string());
+        let config = LintConfig {
+            rules: [("unreferenced-node".to_string(), RuleSeverity::Off)].into(),
+        };
+
+        diagnostics(&ast, &config)
+            .iter()
+            .map(|d| (d.code.as_str(), ast.cst.position(d.span.start).line))
+            .collect()
+    }
+
+    #[test]
+    fn suppress_next_line() {
+        let content = r#"flowchart TD
+    %% mermaid-lsp-disable-next-line self-loop
+    A --> A
+    B --> B
+    %% mermaid-lsp-disable-next-line duplicate-edge
+    C --> C
+"#;
+
+        assert_eq!(
+            codes(content),
+            vec![
+                ("self-loop", 3),
+                ("unused-suppression", 4),
+                ("self-loop", 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn suppress_regions() {
+        let content = r#"flowchart TD
+    %% mermaid-lsp-disable
+    A --> A
+    %% mermaid-lsp-enable self-loop
+    B --> B
+    A --> A
+    %% mermaid-lsp-enable
+    C --> C
+"#;
+
+        assert_eq!(
+            codes(content),
+            vec![("self-loop", 4), ("self-loop", 5), ("self-loop", 7)]
+        );
+    }
+
+    #[test]
+    fn suppression_fixes() {
+        let ast = MermaidAST::from_content("flowchart TD\n    A --> A\n".to_string());
+        let diagnostic = &diagnostics(&ast, &LintConfig::default())[0];
+
+        let fix = suppression_fix(&ast.cst, diagnostic).unwrap();
+
+        assert_eq!(fix.title, "Disable `self-loop` for this line");
+        assert_eq!(
+            fix.edits,
+            vec![DiagramEdit {
+                span: TextSpan::new(13, 13),
+                new_text: "    %% mermaid-lsp-disable-next-line self-loop\n".to_string()
+            }]
+        );
+
+        let ast = MermaidAST::from_content(
+            "flowchart TD\n    %% mermaid-lsp-disable self-loop\n    A --> B\n".to_string(),
+        );
+        let unused = &diagnostics(&ast, &LintConfig::default())[0];
+
+        assert_eq!(unused.code, DiagnosticCode::UnusedSuppression);
+        assert_eq!(
+            unused.fix.as_ref().unwrap().edits[0].span,
+            TextSpan::new(13, 50)
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use serde::Serialize;
+
+use super::{
+    cst::{SyntaxKind, SyntaxNode, SyntaxTree, TextSpan},
+    flowchart::shape_label,
+    MermaidToken,
+};
+
+/// All the data relevant to a mindmap diagram.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
+pub struct Mindmap {
+    /// Every node of the mindmap in document order.
+    pub nodes: Vec<MindmapNode>,
+}
+
+/// A node of a mindmap, its children are the nodes with a bigger indentation below it.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct MindmapNode {
+    /// The text shown for the node, the label inside the shape if it has one.
+    pub text: MermaidToken,
+
+    /// The amount of ancestors the node has.
+    pub depth: usize,
+
+    /// The span of the node and all its children.
+    pub span: TextSpan,
+}
+
+/// Parses all the data relevant to a mindmap diagram.
+pub fn parse_mindmap(tree: &SyntaxTree) -> Mindmap {
+    let mut mindmap = Mindmap::default();
+    parse_nodes(tree, tree.root(), 0, &mut mindmap);
+    mindmap
+}
+
+/// Walks the nodes below `node` and stores them.
+fn parse_nodes(tree: &SyntaxTree, node: &SyntaxNode, depth: usize, mindmap: &mut Mindmap) {
+    for child in node.child_nodes() {
+        let (statement, children) = match child.kind() {
+            SyntaxKind::Statement => (child, None),
+            SyntaxKind::Block => match child.block_start() {
+                Some(start) => (start, Some(child)),
+                None => continue,
+            },
+            _ => continue,
+        };
+
+        let tokens: Vec<_> = statement.significant_tokens().collect();
+        // Icons and classes decorate the previous node.
+        let is_decoration = tokens.first().is_some_and(|t| t.is_punct(':'));
+        let text = match tokens
+            .iter()
+            .position(|t| ['[', '(', '{'].iter().any(|c| t.is_punct(*c)))
+        {
+            Some(i) => shape_label(&tokens, i).map(|(label, _)| label),
+            None => statement.content_span(),
+        };
+
+        match text {
+            Some(text) if !is_decoration => mindmap.nodes.push(MindmapNode {
+                text: MermaidToken::new(tree, text),
+                depth,
+                span: child.span(),
+            }),
+            _ => {}
+        }
+
+        if let Some(children) = children {
+            parse_nodes(tree, children, depth + 1, mindmap);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIAGRAM: &str = r#"mindmap
+  root((mindmap))
+    Origins
+      Long history
+      ::icon(fa fa-book)
+    Research
+      On effectiveness<br/>and features
+      id[Shaped node]
+"#;
+
+    #[test]
+    fn parse_mindmap_success() {
+        let tree = SyntaxTree::parse(DIAGRAM.to_string());
+
+        let mindmap = parse_mindmap(&tree);
+
+        let nodes: Vec<_> = mindmap
+            .nodes
+            .iter()
+            .map(|n| (n.text.content.as_str(), n.depth))
+            .collect();
+        assert_eq!(
+            nodes,
+            vec![
+                ("mindmap", 0),
+                ("Origins", 1),
+                ("Long history", 2),
+                ("Research", 1),
+                ("On effectiveness<br/>and features", 2),
+                ("Shaped node", 2),
+            ]
+        );
+    }
+}
@@ -1,28 +1,71 @@
+mod class;
+mod completion;
+mod cst;
+mod diagnostics;
 mod diagram_body;
 mod diagram_header;
+mod entity_relationship;
 mod flowchart;
+mod folding;
+mod format;
+mod gantt;
+mod incremental;
+mod lexer;
+mod line_index;
+pub mod lint;
+mod mindmap;
+mod refactor;
+mod sequence;
+mod state;
+mod symbols;
 
-use self::{
-    diagram_body::parse_diagram,
-    diagram_header::{parse_header, MermaidDiagramHeader},
-};
+pub use class::*;
+pub use completion::*;
+pub use cst::*;
+pub use diagnostics::*;
+pub use diagram_body::{parse_diagram, DiagramData, DIAGRAM_KEYWORDS};
+pub use diagram_header::{parse_header, MermaidDiagramHeader, ParseHeaderErrors, ParseTitleErrors};
+pub use entity_relationship::*;
+pub use flowchart::*;
+pub use folding::*;
+pub use format::*;
+pub use gantt::*;
+pub use line_index::LineIndex;
+pub use mindmap::*;
+pub use refactor::*;
+pub use sequence::*;
+pub use state::*;
+pub use symbols::*;
 
-/// The different types of tokens we analyze from a mermaid diagram
-#[derive(Debug, Hash, PartialEq, Eq)]
-pub enum TokenType {
-    DiagramDirection,
-    Node,
-}
+use serde::Serialize;
+
+use crate::jsonrpc::Range;
 
 /// Represents a token of the mermaid language
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
 pub struct MermaidToken {
+    /// The text of the token.
     pub content: String,
-    pub line: u32,
-    pub column: u32,
+
+    /// The bytes the token occupies in the document.
+    pub span: TextSpan,
+
+    /// The LSP range of the token in the document.
+    pub range: Range,
+}
+
+impl MermaidToken {
+    /// Creates a token with the content of the tree inside the given span.
+    pub fn new(tree: &SyntaxTree, span: TextSpan) -> Self {
+        MermaidToken {
+            content: tree.slice(span).to_string(),
+            span,
+            range: tree.range(span),
+        }
+    }
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
 pub enum MermaidDiagramTypes {
     /// Type that represents when the server couldn't figure out the diagram type
     #[default]
@@ -43,7 +86,7 @@ pub enum MermaidDiagramTypes {
     Zenumi,
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
 pub enum MermaidDiagramDirection {
     #[default]
     TopToBottom,
@@ -52,26 +95,58 @@ pub enum MermaidDiagramDirection {
     LeftToRight,
 }
 
-#[derive(Debug, Default)]
+impl MermaidDiagramDirection {
+    /// Parses a direction keyword like `TD` or `LR`.
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "TB" | "TD" => Some(MermaidDiagramDirection::TopToBottom),
+            "BT" => Some(MermaidDiagramDirection::BottomToTop),
+            "RL" => Some(MermaidDiagramDirection::RightToLeft),
+            "LR" => Some(MermaidDiagramDirection::LeftToRight),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
 pub struct DiagramAST {
-    d_type: MermaidDiagramTypes,
+    pub d_type: MermaidDiagramTypes,
+
+    /// The data specific to the type of the diagram.
+    pub data: DiagramData,
 }
 
 /// Represents the state of a mermaid file.
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Serialize, Default)]
 pub struct MermaidAST {
-    header: Option<MermaidDiagramHeader>,
-    diagram: DiagramAST,
+    pub header: Option<MermaidDiagramHeader>,
+    pub diagram: DiagramAST,
+
+    /// The lossless syntax tree the rest of the AST was derived from.
+    #[serde(skip)]
+    pub cst: SyntaxTree,
 }
 
 impl MermaidAST {
     pub fn from_content(content: String) -> Self {
-        let (rest, header) = match parse_header(&content).ok() {
-            Some((r, h)) => (r, Some(h)),
-            None => (content, None),
-        };
-        let diagram = parse_diagram(&rest);
+        Self::from_cst(SyntaxTree::parse(content))
+    }
+
+    /// Derives the AST from an already parsed syntax tree.
+    pub fn from_cst(cst: SyntaxTree) -> Self {
+        let header = cst.frontmatter().and_then(|frontmatter| {
+            let (_, header) = parse_header(&frontmatter.text()).ok()?;
+            Some(MermaidDiagramHeader {
+                span: frontmatter.span(),
+                ..header
+            })
+        });
+        let diagram = parse_diagram(&cst);
 
-        MermaidAST { header, diagram }
+        MermaidAST {
+            header,
+            diagram,
+            cst,
+        }
     }
 }
@@ -0,0 +1,353 @@
+use super::{
+    diagnostics::{insert_line, line_ending},
+    split_lines, DiagramData, DiagramEdit, Flowchart, LinkLabelStyle, MermaidAST, SyntaxKind,
+    SyntaxNode, SyntaxTree, TextSpan,
+};
+
+/// The kind of change a refactor makes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefactorKind {
+    /// Moves some statements into a new construct, like a subgraph.
+    Extract,
+    /// Rewrites the document without changing what it renders.
+    Rewrite,
+}
+
+/// A change of the document that keeps it valid, offered on demand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagramRefactor {
+    /// A short description of the refactor, like `Extract into subgraph`.
+    pub title: String,
+    pub kind: RefactorKind,
+    pub edits: Vec<DiagramEdit>,
+}
+
+/// Finds every refactor available with `span` selected.
+///
+/// Only flowcharts have refactors, most of them apply to the whole document regardless of the selection.
+pub fn refactors(ast: &MermaidAST, span: TextSpan) -> Vec<DiagramRefactor> {
+    let DiagramData::Flowchart(flowchart) = &ast.diagram.data else {
+        return vec![];
+    };
+    let tree = &ast.cst;
+
+    [
+        extract_subgraph(tree, flowchart, span),
+        flip_direction(flowchart),
+        move_labels_to_top(tree, flowchart),
+        inline_link_labels(flowchart),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Wraps the statements touched by `span` in a new `subgraph`.
+fn extract_subgraph(
+    tree: &SyntaxTree,
+    flowchart: &Flowchart,
+    span: TextSpan,
+) -> Option<DiagramRefactor> {
+    let statements = selected_statements(tree.root(), span);
+    let (first, last) = (statements.first()?, statements.last()?);
+
+    let indentation = statement_indentation(first);
+    let unit = match indentation.contains('\t') {
+        true => "\t",
+        false => "    ",
+    };
+    let ending = line_ending(tree);
+
+    let lines = TextSpan::new(first.span().start, last.span().end);
+    let mut body: String = split_lines(tree.slice(lines), 0)
+        .map(|(_, line)| match line.trim().is_empty() {
+            true => line.to_string(),
+            false => format!("{}{}", unit, line),
+        })
+        .collect();
+    let closing_ending = match body.ends_with('\n') {
+        true => ending,
+        false => {
+            body.push_str(ending);
+            ""
+        }
+    };
+
+    let id = (1..).map(|i| format!("subgraph{}", i)).find(|id| {
+        let ids = flowchart.nodes.iter().map(|n| &n.id);
+        !ids.chain(flowchart.subgraphs.iter().map(|s| &s.id))
+            .any(|t| t.content == *id)
+    })?;
+
+    Some(DiagramRefactor {
+        title: "Extract into subgraph".to_string(),
+        kind: RefactorKind::Extract,
+        edits: vec![DiagramEdit {
+            span: lines,
+            new_text: format!(
+                "{indentation}subgraph {id}{ending}{body}{indentation}end{closing_ending}"
+            ),
+        }],
+    })
+}
+
+/// The consecutive statements of the innermost container whose statements are touched by `span`.
+fn selected_statements(container: &SyntaxNode, span: TextSpan) -> Vec<&SyntaxNode> {
+    let selects = |node: &SyntaxNode| {
+        node.content_span().is_some_and(|c| match span.is_empty() {
+            true => c.touches(span),
+            false => c.start < span.end && span.start < c.end,
+        })
+    };
+    let selected: Vec<_> = container
+        .child_nodes()
+        .filter(|n| matches!(n.kind(), SyntaxKind::Statement | SyntaxKind::Block))
+        .filter(|n| selects(n))
+        .collect();
+
+    match selected.as_slice() {
+        [block] if block.kind() == SyntaxKind::Block => {
+            let delimiters = [block.block_start(), block.block_end()];
+            match delimiters.into_iter().flatten().any(selects) {
+                true => selected,
+                false => selected_statements(block, span),
+            }
+        }
+        _ => selected,
+    }
+}
+
+/// The leading whitespace of a statement or the opening line of a block.
+fn statement_indentation(node: &SyntaxNode) -> &str {
+    match node.block_start() {
+        Some(start) => start.indentation(),
+        None => node.indentation(),
+    }
+}
+
+/// Swaps a vertical direction of the header with a horizontal one, like `TD` with `LR`.
+fn flip_direction(flowchart: &Flowchart) -> Option<DiagramRefactor> {
+    let token = flowchart.direction_token.as_ref()?;
+    let flipped = match token.content.as_str() {
+        "TD" | "TB" => "LR",
+        "LR" => "TD",
+        "BT" => "RL",
+        "RL" => "BT",
+        _ => return None,
+    };
+
+    Some(DiagramRefactor {
+        title: format!("Convert direction to {}", flipped),
+        kind: RefactorKind::Rewrite,
+        edits: vec![DiagramEdit {
+            span: token.span,
+            new_text: flipped.to_string(),
+        }],
+    })
+}
+
+/// Declares every labelled node on its own line right after the header, links only keep the ids.
+///
+/// Nodes inside subgraphs are left alone, moving them would move them out of the subgraph.
+fn move_labels_to_top(tree: &SyntaxTree, flowchart: &Flowchart) -> Option<DiagramRefactor> {
+    let header = tree.diagram_header()?;
+    let statements: Vec<_> = tree
+        .root()
+        .child_nodes()
+        .filter(|n| n.kind() == SyntaxKind::Statement)
+        .collect();
+
+    let mut declarations: Vec<&str> = vec![];
+    let mut edits = vec![];
+    let mut inline = false;
+    for node in flowchart.nodes.iter().filter(|n| n.label.is_some()) {
+        let Some(statement) = statements
+            .iter()
+            .find(|s| s.span().contains_span(node.span))
+        else {
+            continue;
+        };
+
+        let declaration = tree.slice(node.span);
+        if !declarations.contains(&declaration) {
+            declarations.push(declaration);
+        }
+        match statement.content_span() == Some(node.span) {
+            true => edits.push(DiagramEdit {
+                span: statement.span(),
+                new_text: String::new(),
+            }),
+            false => {
+                inline = true;
+                edits.push(DiagramEdit {
+                    span: node.span,
+                    new_text: node.id.content.clone(),
+                });
+            }
+        }
+    }
+    if !inline {
+        return None;
+    }
+
+    let indentation = statements.first().map_or("    ", |s| s.indentation());
+    let insert_at = header.span().end;
+    let lines: Vec<_> = declarations
+        .iter()
+        .map(|d| insert_line(tree, insert_at, &format!("{}{}", indentation, d)))
+        .collect();
+    edits.insert(
+        0,
+        DiagramEdit {
+            span: TextSpan::new(insert_at, insert_at),
+            new_text: lines.into_iter().map(|e| e.new_text).collect(),
+        },
+    );
+
+    Some(DiagramRefactor {
+        title: "Move node labels to the top".to_string(),
+        kind: RefactorKind::Rewrite,
+        edits,
+    })
+}
+
+/// Rewrites links with the text inside the arrow, like `A -- text --> B`, as `A -->|text| B`.
+fn inline_link_labels(flowchart: &Flowchart) -> Option<DiagramRefactor> {
+    let mut edits: Vec<DiagramEdit> = vec![];
+    for edge in &flowchart.edges {
+        // Links shared by many edges, like `A & B -- text --> C`, are rewritten once.
+        if edge.label_style() != Some(LinkLabelStyle::Inline)
+            || edits.iter().any(|e| e.span == edge.link)
+        {
+            continue;
+        }
+
+        if let Some(new_text) = edge.link_with_label_style(LinkLabelStyle::Piped) {
+            edits.push(DiagramEdit {
+                span: edge.link,
+                new_text,
+            });
+        }
+    }
+
+    (!edits.is_empty()).then(|| DiagramRefactor {
+        title: "Convert link labels to `-->|text|`".to_string(),
+        kind: RefactorKind::Rewrite,
+        edits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn refactored(content: &str, span: TextSpan, title: &str) -> Option<String> {
+        let ast = MermaidAST::from_content(content.to_string());
+        let refactor = refactors(&ast, span)
+            .into_iter()
+            .find(|r| r.title.starts_with(title))?;
+
+        let mut edits = refactor.edits;
+        edits.sort_by_key(|e| std::cmp::Reverse((e.span.start, e.span.end)));
+        let mut text = content.to_string();
+        for edit in edits {
+            text.replace_range(edit.span.start..edit.span.end, &edit.new_text);
+        }
+        Some(text)
+    }
+
+    #[test]
+    fn refactor_extract_subgraph() {
+        let content = "flowchart TD\n    A --> B\n    B --> C\n    C --> D\n";
+
+        assert_eq!(
+            refactored(content, TextSpan::new(29, 42), "Extract"),
+            Some(
+                "flowchart TD\n    A --> B\n    subgraph subgraph1\n        B --> C\n        C --> D\n    end\n"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            refactored(content, TextSpan::new(20, 20), "Extract"),
+            Some(
+                "flowchart TD\n    subgraph subgraph1\n        A --> B\n    end\n    B --> C\n    C --> D\n"
+                    .to_string()
+            )
+        );
+        assert_eq!(refactored(content, TextSpan::new(2, 2), "Extract"), None);
+    }
+
+    #[test]
+    fn refactor_extract_subgraph_inside_block() {
+        let content = "flowchart TD\n    subgraph subgraph1\n        A\n        B\n    end";
+
+        assert_eq!(
+            refactored(content, TextSpan::new(44, 44), "Extract"),
+            Some(
+                "flowchart TD\n    subgraph subgraph1\n        subgraph subgraph2\n            A\n        end\n        B\n    end"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            refactored(content, TextSpan::new(13, 44), "Extract"),
+            Some(
+                "flowchart TD\n    subgraph subgraph2\n        subgraph subgraph1\n            A\n            B\n        end\n    end"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn refactor_flip_direction() {
+        assert_eq!(
+            refactored(
+                "flowchart TD\n    A\n",
+                TextSpan::new(0, 0),
+                "Convert direction"
+            ),
+            Some("flowchart LR\n    A\n".to_string())
+        );
+        assert_eq!(
+            refactored("flowchart RL\n", TextSpan::new(0, 0), "Convert direction"),
+            Some("flowchart BT\n".to_string())
+        );
+        assert_eq!(
+            refactored("flowchart\n", TextSpan::new(0, 0), "Convert direction"),
+            None
+        );
+    }
+
+    #[test]
+    fn refactor_move_labels_to_top() {
+        let content = "flowchart TD\n    A --> B[Second]\n    C[Third]\n    A[First] --> C\n    subgraph s\n        D[Fourth]\n    end\n";
+
+        assert_eq!(
+            refactored(content, TextSpan::new(0, 0), "Move node labels"),
+            Some(
+                "flowchart TD\n    B[Second]\n    C[Third]\n    A[First]\n    A --> B\n    A --> C\n    subgraph s\n        D[Fourth]\n    end\n"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            refactored(
+                "flowchart TD\n    A[First]\n    A --> B\n",
+                TextSpan::new(0, 0),
+                "Move node labels"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn refactor_inline_link_labels() {
+        let content = "flowchart TD\n    A -- one --> B\n    B -. two .-> C & D\n    C == three ==> D\n    D -->|four| A\n";
+
+        assert_eq!(
+            refactored(content, TextSpan::new(0, 0), "Convert link labels"),
+            Some(
+                "flowchart TD\n    A -->|one| B\n    B -.->|two| C & D\n    C ==>|three| D\n    D -->|four| A\n"
+                    .to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use serde::Serialize;
+
+use super::{
+    cst::{tokens_span, SyntaxKind, SyntaxNode, SyntaxToken, SyntaxTree, TextSpan},
+    MermaidToken,
+};
+
+/// All the data relevant to a sequence diagram.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
+pub struct SequenceDiagram {
+    /// Participants and actors declared explicitly, in order.
+    pub participants: Vec<SequenceParticipant>,
+
+    /// Every message sent between participants, in order.
+    pub messages: Vec<SequenceMessage>,
+
+    /// Blocks like `loop`, `alt` or `rect`, in order.
+    pub blocks: Vec<SequenceBlock>,
+
+    /// Participant names referenced by notes, activations and other statements.
+    pub references: Vec<MermaidToken>,
+}
+
+/// A `participant` or `actor` declaration.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct SequenceParticipant {
+    /// Either `participant` or `actor`.
+    pub keyword: MermaidToken,
+
+    /// The id used to reference the participant in messages.
+    pub id: MermaidToken,
+
+    /// The text shown instead of the id, declared with `as`.
+    pub alias: Option<MermaidToken>,
+
+    /// The span of the whole declaration.
+    pub span: TextSpan,
+}
+
+/// A message between two participants, like `Alice->>Bob: Hello`.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct SequenceMessage {
+    pub from: MermaidToken,
+    pub to: MermaidToken,
+    pub arrow: MermaidToken,
+
+    /// The text after the `:`.
+    pub text: Option<MermaidToken>,
+
+    /// The span of the whole statement.
+    pub span: TextSpan,
+}
+
+/// A block of a sequence diagram closed by `end`.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct SequenceBlock {
+    /// The keyword that opened the block, like `loop`.
+    pub keyword: MermaidToken,
+
+    /// The text after the keyword.
+    pub label: Option<MermaidToken>,
+
+    /// The span of the whole block.
+    pub span: TextSpan,
+}
+
+/// Statements that reference participants after their keyword.
+pub(crate) const REFERENCE_KEYWORDS: [&str; 4] = ["activate", "deactivate", "destroy", "links"];
+
+/// Parses all the data relevant to a sequence diagram.
+pub fn parse_sequence(tree: &SyntaxTree) -> SequenceDiagram {
+    let mut diagram = SequenceDiagram::default();
+    parse_statements(tree, tree.root(), &mut diagram);
+    diagram
+}
+
+/// Walks the statements below `node` and stores the data they define.
+fn parse_statements(tree: &SyntaxTree, node: &SyntaxNode, diagram: &mut SequenceDiagram) {
+    for child in node.child_nodes() {
+        match child.kind() {
+            SyntaxKind::Statement => parse_statement(tree, child, diagram),
+            SyntaxKind::Block => {
+                if let Some(start) = child.block_start() {
+                    let tokens: Vec<_> = start.significant_tokens().collect();
+                    if let Some(keyword) = tokens.first() {
+                        diagram.blocks.push(SequenceBlock {
+                            keyword: MermaidToken::new(tree, keyword.span()),
+                            label: tokens_span(&tokens[1..]).map(|s| MermaidToken::new(tree, s)),
+                            span: child.span(),
+                        });
+                    }
+                }
+                parse_statements(tree, child, diagram);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parses a single statement of a sequence diagram.
+fn parse_statement(tree: &SyntaxTree, statement: &SyntaxNode, diagram: &mut SequenceDiagram) {
+    let tokens: Vec<_> = statement.significant_tokens().collect();
+    let Some(first) = tokens.first() else {
+        return;
+    };
+    let span = statement.content_span().unwrap_or(first.span());
+
+    match first.text() {
+        "participant" | "actor" => diagram
+            .participants
+            .extend(parse_participant(tree, &tokens, span)),
+        "create" => diagram
+            .participants
+            .extend(parse_participant(tree, &tokens[1..], span)),
+        "Note" | "note" => {
+            // Note right of A: text | Note over A,B: text
+            let end = tokens
+                .iter()
+                .position(|t| t.is_punct(':'))
+                .unwrap_or(tokens.len());
+            diagram.references.extend(
+                tokens[1..end]
+                    .iter()
+                    .filter(|t| t.kind() == SyntaxKind::Ident)
+                    .filter(|t| !matches!(t.text(), "left" | "right" | "of" | "over"))
+                    .map(|t| MermaidToken::new(tree, t.span())),
+            );
+        }
+        keyword if REFERENCE_KEYWORDS.contains(&keyword) => {
+            diagram.references.extend(
+                tokens
+                    .get(1)
+                    .filter(|t| t.kind() == SyntaxKind::Ident)
+                    .map(|t| MermaidToken::new(tree, t.span())),
+            );
+        }
+        _ => diagram.messages.extend(parse_message(tree, &tokens, span)),
+    }
+}
+
+/// Parses a declaration like `participant A as Alice`, `tokens` starts with the keyword.
+fn parse_participant(
+    tree: &SyntaxTree,
+    tokens: &[&SyntaxToken],
+    span: TextSpan,
+) -> Option<SequenceParticipant> {
+    let keyword = tokens.first()?;
+    let id = tokens.get(1).filter(|t| t.kind() == SyntaxKind::Ident)?;
+    let alias = match tokens.get(2) {
+        Some(t) if t.is_ident("as") => tokens_span(&tokens[3..]),
+        _ => None,
+    };
+
+    Some(SequenceParticipant {
+        keyword: MermaidToken::new(tree, keyword.span()),
+        id: MermaidToken::new(tree, id.span()),
+        alias: alias.map(|a| MermaidToken::new(tree, a)),
+        span,
+    })
+}
+
+/// Parses a message like `Alice->>+Bob: Hello`.
+fn parse_message(
+    tree: &SyntaxTree,
+    tokens: &[&SyntaxToken],
+    span: TextSpan,
+) -> Option<SequenceMessage> {
+    let from = tokens.first().filter(|t| t.kind() == SyntaxKind::Ident)?;
+    let arrow = tokens.get(1).filter(|t| t.kind() == SyntaxKind::Arrow)?;
+    let mut arrow_span = arrow.span();
+    let mut i = 2;
+
+    // Arrows like `-)` are split by the lexer
+    if let Some(t) = tokens.get(i).filter(|t| t.is_punct(')')) {
+        if t.span().start == arrow_span.end {
+            arrow_span.end = t.span().end;
+            i += 1;
+        }
+    }
+    // Activation shorthands like `->>+B`
+    if tokens.get(i).is_some_and(|t| t.is_punct('+')) {
+        i += 1;
+    }
+
+    let to = tokens.get(i).filter(|t| t.kind() == SyntaxKind::Ident)?;
+    let mut to_span = to.span();
+    // Arrows like `-x` are lexed as part of the receiver, `Alice-xBob`.
+    if to_span.start == arrow_span.end && to.text().len() > 1 && to.text().starts_with('x') {
+        arrow_span.end += 1;
+        to_span.start += 1;
+    }
+
+    let text = tokens
+        .iter()
+        .position(|t| t.is_punct(':'))
+        .and_then(|colon| tokens_span(&tokens[colon + 1..]));
+
+    Some(SequenceMessage {
+        from: MermaidToken::new(tree, from.span()),
+        to: MermaidToken::new(tree, to_span),
+        arrow: MermaidToken::new(tree, arrow_span),
+        text: text.map(|t| MermaidToken::new(tree, t)),
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIAGRAM: &str = r#"sequenceDiagram
+    participant A as Alice
+    actor B
+    A->>+B: Hello Bob
+    loop Every minute
+        B-->>-A: Hi
+        B-xC: Lost
+    end
+    Note over A,B: A note
+    activate C
+"#;
+
+    #[test]
+    fn parse_sequence_success() {
+        let tree = SyntaxTree::parse(DIAGRAM.to_string());
+
+        let diagram = parse_sequence(&tree);
+
+        let participants: Vec<_> = diagram
+            .participants
+            .iter()
+            .map(|p| {
+                (
+                    p.keyword.content.as_str(),
+                    p.id.content.as_str(),
+                    p.alias.as_ref().map(|a| a.content.as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            participants,
+            vec![("participant", "A", Some("Alice")), ("actor", "B", None)]
+        );
+
+        let messages: Vec<_> = diagram
+            .messages
+            .iter()
+            .map(|m| {
+                (
+                    m.from.content.as_str(),
+                    m.arrow.content.as_str(),
+                    m.to.content.as_str(),
+                    m.text.as_ref().map(|t| t.content.as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                ("A", "->>", "B", Some("Hello Bob")),
+                ("B", "-->>-", "A", Some("Hi")),
+                ("B", "-x", "C", Some("Lost")),
+            ]
+        );
+
+        assert_eq!(diagram.blocks.len(), 1);
+        assert_eq!(diagram.blocks[0].keyword.content, "loop");
+        assert_eq!(
+            diagram.blocks[0].label.as_ref().unwrap().content,
+            "Every minute"
+        );
+
+        let references: Vec<_> = diagram
+            .references
+            .iter()
+            .map(|r| r.content.as_str())
+            .collect();
+        assert_eq!(references, vec!["A", "B", "C"]);
+    }
+}
@@ -0,0 +1,281 @@
+use serde::Serialize;
+
+use super::{
+    cst::{tokens_span, SyntaxKind, SyntaxNode, SyntaxToken, SyntaxTree, TextSpan},
+    MermaidToken,
+};
+
+/// The text used for the start and end pseudo states.
+pub const START_END_STATE: &str = "[*]";
+
+/// All the data relevant to a state diagram.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
+pub struct StateDiagram {
+    /// Every state of the diagram, declared explicitly or through a transition.
+    pub states: Vec<StateDefinition>,
+
+    /// Every transition between two states, in order.
+    pub transitions: Vec<StateTransition>,
+}
+
+/// A state of the diagram.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct StateDefinition {
+    /// The id of the state, the first place where it appears.
+    pub id: MermaidToken,
+
+    /// The description given with `state "description" as id` or `id : description`.
+    pub description: Option<MermaidToken>,
+
+    /// Checks if the state is a composite with its own states inside.
+    pub composite: bool,
+
+    /// The span of the declaration, for composite states it's the whole block.
+    pub span: TextSpan,
+}
+
+/// A transition between two states, like `Still --> Moving : push`.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct StateTransition {
+    /// The state where the transition starts, `[*]` for the start state.
+    pub from: MermaidToken,
+
+    /// The state where the transition ends, `[*]` for the end state.
+    pub to: MermaidToken,
+
+    /// The text after the `:`.
+    pub label: Option<MermaidToken>,
+
+    /// The span of the composite state that contains the transition, if any.
+    pub scope: Option<TextSpan>,
+}
+
+impl StateDiagram {
+    /// Finds a state by id, creating it if it doesn't exist yet.
+    fn state_mut(
+        &mut self,
+        tree: &SyntaxTree,
+        id: TextSpan,
+        span: TextSpan,
+    ) -> &mut StateDefinition {
+        let content = tree.slice(id);
+        match self.states.iter().position(|s| s.id.content == content) {
+            Some(i) => &mut self.states[i],
+            None => {
+                self.states.push(StateDefinition {
+                    id: MermaidToken::new(tree, id),
+                    description: None,
+                    composite: false,
+                    span,
+                });
+                self.states.last_mut().expect("A state was just pushed!")
+            }
+        }
+    }
+}
+
+/// Parses all the data relevant to a state diagram.
+pub fn parse_state_diagram(tree: &SyntaxTree) -> StateDiagram {
+    let mut diagram = StateDiagram::default();
+    parse_statements(tree, tree.root(), None, &mut diagram);
+    diagram
+}
+
+/// Walks the statements below `node` and stores the data they define.
+fn parse_statements(
+    tree: &SyntaxTree,
+    node: &SyntaxNode,
+    scope: Option<TextSpan>,
+    diagram: &mut StateDiagram,
+) {
+    for child in node.child_nodes() {
+        match child.kind() {
+            SyntaxKind::Statement => parse_statement(tree, child, scope, diagram),
+            SyntaxKind::Block => {
+                let Some(start) = child.block_start() else {
+                    continue;
+                };
+                let tokens: Vec<_> = start.significant_tokens().collect();
+                if let Some((id, description)) = parse_declaration(&tokens) {
+                    let state = diagram.state_mut(tree, id, child.span());
+                    state.composite = true;
+                    state.span = child.span();
+                    if let Some(description) = description {
+                        state.description = Some(MermaidToken::new(tree, description));
+                    }
+                }
+                parse_statements(tree, child, Some(child.span()), diagram);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parses a `state` declaration, returns the span of the id and the description.
+///
+/// Supports `state Id`, `state "Description" as Id` and `state Id <<fork>>`.
+fn parse_declaration(tokens: &[&SyntaxToken]) -> Option<(TextSpan, Option<TextSpan>)> {
+    match tokens {
+        [keyword, description, r#as, id, ..]
+            if keyword.is_ident("state")
+                && description.kind() == SyntaxKind::String
+                && r#as.is_ident("as") =>
+        {
+            Some((id.span(), Some(description.span())))
+        }
+        [keyword, id, ..] if keyword.is_ident("state") && id.kind() == SyntaxKind::Ident => {
+            Some((id.span(), None))
+        }
+        _ => None,
+    }
+}
+
+/// Parses a single statement of a state diagram.
+fn parse_statement(
+    tree: &SyntaxTree,
+    statement: &SyntaxNode,
+    scope: Option<TextSpan>,
+    diagram: &mut StateDiagram,
+) {
+    let tokens: Vec<_> = statement.significant_tokens().collect();
+    let Some(span) = statement.content_span() else {
+        return;
+    };
+
+    if let Some((id, description)) = parse_declaration(&tokens) {
+        let state = diagram.state_mut(tree, id, span);
+        if let Some(description) = description {
+            state.description = Some(MermaidToken::new(tree, description));
+        }
+        return;
+    }
+
+    if let Some(arrow) = tokens
+        .iter()
+        .position(|t| t.kind() == SyntaxKind::Arrow && t.text() == "-->")
+    {
+        let (Some(from), Some(to)) = (
+            state_reference(&tokens[..arrow]),
+            state_reference(&tokens[arrow + 1..]),
+        ) else {
+            return;
+        };
+        let label = tokens
+            .iter()
+            .position(|t| t.is_punct(':'))
+            .and_then(|c| tokens_span(&tokens[c + 1..]));
+
+        for state in [from, to] {
+            if tree.slice(state) != START_END_STATE {
+                diagram.state_mut(tree, state, span);
+            }
+        }
+        diagram.transitions.push(StateTransition {
+            from: MermaidToken::new(tree, from),
+            to: MermaidToken::new(tree, to),
+            label: label.map(|l| MermaidToken::new(tree, l)),
+            scope,
+        });
+        return;
+    }
+
+    // Descriptions declared like `Moving : The state is moving`
+    if let [id, colon, rest @ ..] = tokens.as_slice() {
+        if id.kind() == SyntaxKind::Ident && colon.is_punct(':') {
+            let description = tokens_span(rest).map(|s| MermaidToken::new(tree, s));
+            diagram.state_mut(tree, id.span(), span).description = description;
+        }
+    }
+}
+
+/// Finds the state at the start or at the end of `tokens`, either an id or `[*]`.
+fn state_reference(tokens: &[&SyntaxToken]) -> Option<TextSpan> {
+    match tokens {
+        [open, star, close, ..]
+            if open.is_punct('[') && star.is_punct('*') && close.is_punct(']') =>
+        {
+            Some(open.span().cover(close.span()))
+        }
+        [id, ..] if id.kind() == SyntaxKind::Ident => Some(id.span()),
+        _ => None,
+    }
+    .or_else(|| match tokens {
+        [.., open, star, close]
+            if open.is_punct('[') && star.is_punct('*') && close.is_punct(']') =>
+        {
+            Some(open.span().cover(close.span()))
+        }
+        [.., id] if id.kind() == SyntaxKind::Ident => Some(id.span()),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIAGRAM: &str = r#"stateDiagram-v2
+    [*] --> Still
+    Still --> Moving : push
+    Moving : The state is moving
+    state "Crashed state" as Crash
+    Moving --> Crash
+    state Active {
+        [*] --> Idle
+        Idle --> [*]
+    }
+    Crash --> [*]
+"#;
+
+    #[test]
+    fn parse_state_diagram_success() {
+        let tree = SyntaxTree::parse(DIAGRAM.to_string());
+
+        let diagram = parse_state_diagram(&tree);
+
+        let states: Vec<_> = diagram
+            .states
+            .iter()
+            .map(|s| {
+                (
+                    s.id.content.as_str(),
+                    s.description.as_ref().map(|d| d.content.as_str()),
+                    s.composite,
+                )
+            })
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                ("Still", None, false),
+                ("Moving", Some("The state is moving"), false),
+                ("Crash", Some("\"Crashed state\""), false),
+                ("Active", None, true),
+                ("Idle", None, false),
+            ]
+        );
+
+        let transitions: Vec<_> = diagram
+            .transitions
+            .iter()
+            .map(|t| {
+                (
+                    t.from.content.as_str(),
+                    t.to.content.as_str(),
+                    t.scope.is_some(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            transitions,
+            vec![
+                ("[*]", "Still", false),
+                ("Still", "Moving", false),
+                ("Moving", "Crash", false),
+                ("[*]", "Idle", true),
+                ("Idle", "[*]", true),
+                ("Crash", "[*]", false),
+            ]
+        );
+    }
+}
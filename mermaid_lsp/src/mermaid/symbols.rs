@@ -0,0 +1,419 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{DiagramData, MermaidAST, MermaidToken, TextSpan};
+
+/// The kind of a symbol found in a diagram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagramSymbolKind {
+    /// The title declared in the frontmatter.
+    Title,
+    /// The diagram itself, like `flowchart TD`.
+    Diagram,
+    Subgraph,
+    Node,
+    Participant,
+    /// Blocks of sequence diagrams, like `loop` or `alt`.
+    Block,
+    Namespace,
+    Class,
+    /// An attribute of a class or an entity.
+    Attribute,
+    Method,
+    State,
+    Entity,
+    Section,
+    Task,
+}
+
+/// A named element of a diagram, used for outlines and symbol searches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagramSymbol {
+    pub name: String,
+
+    /// Extra information of the symbol, like the label of a node.
+    pub detail: Option<String>,
+
+    pub kind: DiagramSymbolKind,
+
+    /// The span of the whole symbol, including its children.
+    pub span: TextSpan,
+
+    /// The span that should be selected when navigating to the symbol, like its name.
+    pub selection_span: TextSpan,
+
+    pub children: Vec<DiagramSymbol>,
+}
+
+impl DiagramSymbol {
+    fn new(kind: DiagramSymbolKind, name: &MermaidToken, span: TextSpan) -> Self {
+        DiagramSymbol {
+            name: name.content.clone(),
+            detail: None,
+            kind,
+            span: span.cover(name.span),
+            selection_span: name.span,
+            children: vec![],
+        }
+    }
+
+    fn with_detail(mut self, detail: Option<&MermaidToken>) -> Self {
+        self.detail = detail.map(|d| d.content.clone());
+        self
+    }
+
+    /// Iterates over this symbol and all its descendants.
+    pub fn flatten(&self) -> Vec<&DiagramSymbol> {
+        std::iter::once(self)
+            .chain(self.children.iter().flat_map(DiagramSymbol::flatten))
+            .collect()
+    }
+}
+
+/// Builds the hierarchical outline of a document.
+///
+/// The outline has the title of the diagram, if any, and the diagram itself
+/// with all its symbols nested inside the blocks that contain them.
+pub fn document_symbols(ast: &MermaidAST) -> Vec<DiagramSymbol> {
+    let mut symbols = vec![];
+
+    if let Some(header) = &ast.header {
+        symbols.push(DiagramSymbol {
+            name: header.title.clone(),
+            detail: None,
+            kind: DiagramSymbolKind::Title,
+            span: header.span,
+            selection_span: header.span,
+            children: vec![],
+        });
+    }
+
+    let Some(diagram_header) = ast.cst.diagram_header() else {
+        return symbols;
+    };
+    let Some(selection_span) = diagram_header.content_span() else {
+        return symbols;
+    };
+
+    symbols.push(DiagramSymbol {
+        name: ast.cst.slice(selection_span).to_string(),
+        detail: None,
+        kind: DiagramSymbolKind::Diagram,
+        span: TextSpan::new(diagram_header.span().start, ast.cst.root().span().end),
+        selection_span,
+        children: top_level_symbols(&ast.diagram.data)
+            .into_iter()
+            .chain(nest(diagram_symbols(&ast.diagram.data)))
+            .collect(),
+    });
+
+    symbols
+}
+
+/// The symbols of a diagram that never go inside another one, whatever their span.
+///
+/// Sequence participants belong to the whole diagram, even the implicit ones whose span is
+/// the first message that mentions them, which may be inside a `loop` or an `alt`.
+fn top_level_symbols(data: &DiagramData) -> Vec<DiagramSymbol> {
+    let DiagramData::Sequence(sequence) = data else {
+        return vec![];
+    };
+
+    let mut participants: Vec<DiagramSymbol> = sequence
+        .participants
+        .iter()
+        .map(|p| {
+            DiagramSymbol::new(DiagramSymbolKind::Participant, &p.id, p.span)
+                .with_detail(p.alias.as_ref())
+        })
+        .collect();
+
+    // Participants can also be declared implicitly by sending messages.
+    let mut seen: HashSet<String> = participants.iter().map(|p| p.name.clone()).collect();
+    for token in sequence.messages.iter().flat_map(|m| [&m.from, &m.to]) {
+        if seen.insert(token.content.clone()) {
+            participants.push(DiagramSymbol::new(
+                DiagramSymbolKind::Participant,
+                token,
+                token.span,
+            ));
+        }
+    }
+
+    participants
+}
+
+/// The symbols defined by the body of a diagram, without any nesting.
+fn diagram_symbols(data: &DiagramData) -> Vec<DiagramSymbol> {
+    use DiagramSymbolKind as Kind;
+
+    match data {
+        DiagramData::Unsupported => vec![],
+        DiagramData::Flowchart(flowchart) => {
+            let subgraphs = flowchart.subgraphs.iter().map(|s| {
+                DiagramSymbol::new(Kind::Subgraph, &s.id, s.span)
+                    .with_detail(s.title.as_ref().filter(|t| t.content != s.id.content))
+            });
+
+            let mut nodes: Vec<DiagramSymbol> = vec![];
+            let mut seen: HashMap<&str, usize> = HashMap::new();
+            for node in flowchart.nodes.iter() {
+                let symbol = DiagramSymbol::new(Kind::Node, &node.id, node.span)
+                    .with_detail(node.label.as_ref());
+                match seen.get(node.id.content.as_str()) {
+                    // Prefer the first appearance that declares the label.
+                    Some(&index) if nodes[index].detail.is_none() && symbol.detail.is_some() => {
+                        nodes[index] = symbol
+                    }
+                    Some(_) => {}
+                    None => {
+                        seen.insert(&node.id.content, nodes.len());
+                        nodes.push(symbol);
+                    }
+                }
+            }
+
+            subgraphs.chain(nodes).collect()
+        }
+        // The participants are in `top_level_symbols`.
+        DiagramData::Sequence(sequence) => sequence
+            .blocks
+            .iter()
+            .map(|b| DiagramSymbol {
+                name: match &b.label {
+                    Some(label) => format!("{} {}", b.keyword.content, label.content),
+                    None => b.keyword.content.clone(),
+                },
+                detail: None,
+                kind: Kind::Block,
+                span: b.span,
+                selection_span: b.keyword.span,
+                children: vec![],
+            })
+            .collect(),
+        DiagramData::Class(class_diagram) => {
+            let namespaces = class_diagram
+                .namespaces
+                .iter()
+                .map(|n| DiagramSymbol::new(Kind::Namespace, &n.name, n.span));
+            let classes = class_diagram.classes.iter().map(|c| DiagramSymbol {
+                children: c
+                    .members
+                    .iter()
+                    .map(|m| {
+                        let kind = if m.content.contains('(') {
+                            Kind::Method
+                        } else {
+                            Kind::Attribute
+                        };
+                        DiagramSymbol::new(kind, m, m.span)
+                    })
+                    .collect(),
+                ..DiagramSymbol::new(Kind::Class, &c.name, c.span)
+            });
+
+            namespaces.chain(classes).collect()
+        }
+        DiagramData::State(state_diagram) => state_diagram
+            .states
+            .iter()
+            .map(|s| {
+                DiagramSymbol::new(Kind::State, &s.id, s.span).with_detail(s.description.as_ref())
+            })
+            .collect(),
+        DiagramData::EntityRelationship(er) => er
+            .entities
+            .iter()
+            .map(|e| DiagramSymbol {
+                children: e
+                    .attributes
+                    .iter()
+                    .map(|a| {
+                        DiagramSymbol::new(Kind::Attribute, &a.name, a.span)
+                            .with_detail(Some(&a.attribute_type))
+                    })
+                    .collect(),
+                ..DiagramSymbol::new(Kind::Entity, &e.name, e.span)
+            })
+            .collect(),
+        DiagramData::Gantt(gantt) => {
+            let sections = gantt
+                .sections
+                .iter()
+                .map(|s| DiagramSymbol::new(Kind::Section, &s.name, s.span));
+            let tasks = gantt.tasks.iter().map(|t| {
+                DiagramSymbol::new(Kind::Task, &t.name, t.span).with_detail(t.metadata.as_ref())
+            });
+
+            sections.chain(tasks).collect()
+        }
+        DiagramData::Mindmap(mindmap) => mindmap
+            .nodes
+            .iter()
+            .map(|n| DiagramSymbol::new(Kind::Node, &n.text, n.span))
+            .collect(),
+    }
+}
+
+/// Nests every symbol inside the smallest symbol whose span contains it.
+fn nest(mut symbols: Vec<DiagramSymbol>) -> Vec<DiagramSymbol> {
+    symbols.sort_by_key(|s| (s.span.start, std::cmp::Reverse(s.span.end)));
+
+    let mut roots = vec![];
+    let mut stack: Vec<DiagramSymbol> = vec![];
+
+    fn close(roots: &mut Vec<DiagramSymbol>, stack: &mut Vec<DiagramSymbol>) {
+        if let Some(symbol) = stack.pop() {
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(symbol),
+                None => roots.push(symbol),
+            }
+        }
+    }
+
+    for symbol in symbols {
+        while stack
+            .last()
+            .is_some_and(|top| !top.span.contains_span(symbol.span) || top.span == symbol.span)
+        {
+            close(&mut roots, &mut stack);
+        }
+        stack.push(symbol);
+    }
+    while !stack.is_empty() {
+        close(&mut roots, &mut stack);
+    }
+
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline(symbols: &[DiagramSymbol], depth: usize, lines: &mut Vec<String>) {
+        for symbol in symbols {
+            lines.push(format!(
+                "{}{:?} {}",
+                "  ".repeat(depth),
+                symbol.kind,
+                symbol.name
+            ));
+            outline(&symbol.children, depth + 1, lines);
+        }
+    }
+
+    fn assert_outline(content: &str, expected: &[&str]) {
+        let ast = MermaidAST::from_content(content.to_string());
+        let mut lines = vec![];
+
+        outline(&document_symbols(&ast), 0, &mut lines);
+
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn document_symbols_flowchart() {
+        assert_outline(
+            include_str!("../../../file_tests/flowchart.mermaid"),
+            &[
+                "Title Test title",
+                "Diagram flowchart TD",
+                "  Node A",
+                "  Node B",
+                "  Node C",
+                "  Node D",
+                "  Node E",
+            ],
+        );
+        assert_outline(
+            "flowchart LR\n    subgraph one\n        A --> B\n    end\n    B --> C\n",
+            &[
+                "Diagram flowchart LR",
+                "  Subgraph one",
+                "    Node A",
+                "    Node B",
+                "  Node C",
+            ],
+        );
+    }
+
+    #[test]
+    fn document_symbols_deduplicate_repeated_names() {
+        let ast = MermaidAST::from_content(
+            "flowchart LR\n    A --> B\n    B[Label] --> A\n    A --> B\n".to_string(),
+        );
+        let symbols = document_symbols(&ast);
+        let nodes: Vec<_> = symbols[0]
+            .children
+            .iter()
+            .map(|n| (n.name.as_str(), n.detail.as_deref()))
+            .collect();
+        assert_eq!(nodes, [("A", None), ("B", Some("Label"))]);
+
+        assert_outline(
+            "sequenceDiagram\n    participant A\n    A->>B: Hi\n    B->>A: Hi\n    B->>C: Hi\n",
+            &[
+                "Diagram sequenceDiagram",
+                "  Participant A",
+                "  Participant B",
+                "  Participant C",
+            ],
+        );
+    }
+
+    #[test]
+    fn document_symbols_sequence() {
+        assert_outline(
+            "sequenceDiagram\n    participant A\n    loop Forever\n        A->>B: Hi\n        alt Yes\n            B->>C: Hi\n        end\n    end\n",
+            &[
+                "Diagram sequenceDiagram",
+                "  Participant A",
+                "  Participant B",
+                "  Participant C",
+                "  Block loop Forever",
+                "    Block alt Yes",
+            ],
+        );
+    }
+
+    #[test]
+    fn document_symbols_class_and_er() {
+        assert_outline(
+            "classDiagram\n    class Duck {\n        +String beak\n        +swim()\n    }\n",
+            &[
+                "Diagram classDiagram",
+                "  Class Duck",
+                "    Attribute +String beak",
+                "    Method +swim()",
+            ],
+        );
+        assert_outline(
+            "erDiagram\n    CUSTOMER {\n        string name\n    }\n",
+            &[
+                "Diagram erDiagram",
+                "  Entity CUSTOMER",
+                "    Attribute name",
+            ],
+        );
+    }
+
+    #[test]
+    fn document_symbols_state_gantt_and_mindmap() {
+        assert_outline(
+            "stateDiagram-v2\n    state Active {\n        [*] --> Idle\n    }\n",
+            &[
+                "Diagram stateDiagram-v2",
+                "  State Active",
+                "    State Idle",
+            ],
+        );
+        assert_outline(
+            "gantt\n    section One\n    Task :a1, 1d\n",
+            &["Diagram gantt", "  Section One", "    Task Task"],
+        );
+        assert_outline(
+            "mindmap\n  root\n    child\n",
+            &["Diagram mindmap", "  Node root", "    Node child"],
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{
+    notifications::workspace::{pull_configuration, register_watched_files},
+    ServerState,
+};
+
+/// The initialized notification is sent from the client to the server after the client received the result of the initialize request
+/// but before the client is sending any other request or notification to the server.
+/// The server can use the initialized notification, for example, to dynamically register capabilities.
+///
+/// The `mermaid` settings are pulled with `workspace/configuration` and the diagram files are watched,
+/// if the client supports them.
+pub fn initialized_notification(state: &mut ServerState, _params: serde_json::Value) {
+    pull_configuration(state);
+    register_watched_files(state);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{jsonrpc::LspId, mermaid::lint::RuleSeverity};
+
+    #[test]
+    fn initialized_pulls_settings() {
+        let capabilities = json!({
+            "workspace": { "configuration": true, "didChangeWatchedFiles": { "dynamicRegistration": true } }
+        });
+        let mut state = ServerState {
+            capabilities: serde_json::from_value(capabilities).unwrap(),
+            ..Default::default()
+        };
+
+        initialized_notification(&mut state, json!({}));
+
+        let methods: Vec<_> = state
+            .outgoing
+            .iter()
+            .map(|r| serde_json::to_value(r).unwrap()["method"].clone())
+            .collect();
+        assert_eq!(
+            methods,
+            vec!["workspace/configuration", "client/registerCapability"]
+        );
+
+        state.handle_response(
+            &LspId::from(1),
+            Ok(json!([{ "lint": { "self-loop": "off" } }])),
+        );
+        assert_eq!(
+            state.lint.config().rules.get("self-loop"),
+            Some(&RuleSeverity::Off)
+        );
+    }
+
+    #[test]
+    fn initialized_without_capabilities() {
+        let mut state = ServerState::default();
+
+        initialized_notification(&mut state, json!({}));
+
+        assert!(state.outgoing.is_empty());
+    }
+}
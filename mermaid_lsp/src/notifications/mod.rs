@@ -1 +1,6 @@
+pub mod initialized;
 pub mod text_document;
+pub mod window;
+pub mod workspace;
+
+pub use initialized::*;
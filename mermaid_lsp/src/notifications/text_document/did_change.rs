@@ -0,0 +1,83 @@
+use log::{debug, error, info, trace};
+use serde::Deserialize;
+
+use crate::{
+    jsonrpc::{Range, VersionedTextDocumentIdentifier},
+    ServerState,
+};
+
+/// Params supplied to the `textDocument/didChange` method.
+#[derive(Debug, Deserialize)]
+pub struct DidChangeTextDocumentParams {
+    /// The document that did change. The version number points
+    /// to the version after all provided content changes have
+    /// been applied.
+    #[serde(rename = "textDocument")]
+    text_document: VersionedTextDocumentIdentifier,
+
+    /// The actual content changes. The content changes describe single state
+    /// changes to the document. So if there are two content changes c1 (at
+    /// array index 0) and c2 (at array index 1) for a document in state S then
+    /// c1 moves the document from S to S' and c2 from S' to S''.
+    #[serde(rename = "contentChanges")]
+    content_changes: Vec<TextDocumentContentChangeEvent>,
+}
+
+/// An event describing a change to a text document. If only a text is provided
+/// it is considered to be the full content of the document.
+#[derive(Debug, Deserialize)]
+pub struct TextDocumentContentChangeEvent {
+    /// The range of the document that changed.
+    range: Option<Range>,
+
+    /// The new text for the provided range or the whole document.
+    text: String,
+}
+
+#[derive(Debug)]
+pub enum DidChangeTextDocumentErrors {
+    FileNotOpened,
+}
+
+/// The document change notification is sent from the client to the server to signal changes to a text document.
+/// Before a client can change a text document it must claim ownership of its content using the textDocument/didOpen notification.
+///
+/// Since the server registers `TextDocumentSyncKind.Incremental`, each change contains the range that was replaced.
+/// Only the statements touched by those ranges are parsed again, documents that embed diagrams
+/// like Markdown look for their diagrams again.
+///
+/// Returns the URI of the changed document, so its diagnostics can be published.
+pub fn did_change_notification(
+    state: &mut ServerState,
+    params: DidChangeTextDocumentParams,
+) -> Result<String, DidChangeTextDocumentErrors> {
+    let DidChangeTextDocumentParams {
+        text_document: VersionedTextDocumentIdentifier { uri, version },
+        content_changes,
+    } = params;
+
+    let Some(document) = state.documents.get_mut(&uri) else {
+        error!("The file {} was never opened!", uri);
+        return Err(DidChangeTextDocumentErrors::FileNotOpened);
+    };
+
+    info!(
+        "Applying {} changes to file {} (version {})",
+        content_changes.len(),
+        uri,
+        version
+    );
+    // Requests still running on a snapshot keep the old document, the edits go to a copy.
+    let document = std::sync::Arc::make_mut(document);
+    for TextDocumentContentChangeEvent { range, text } in content_changes {
+        match range {
+            Some(range) => debug!("Replacing {:?} with {:?}", range, text),
+            None => debug!("Replacing the whole document..."),
+        }
+        document.edit(range, &text);
+    }
+
+    info!("{} diagrams updated!", document.diagrams.len());
+    trace!("Diagrams: {:?}", document.diagrams);
+    Ok(uri)
+}
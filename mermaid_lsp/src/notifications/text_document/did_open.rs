@@ -1,7 +1,11 @@
 use log::{debug, error, info};
 use serde::Deserialize;
 
-use crate::{jsonrpc::TextDocumentItem, MermaidAST, ServerState};
+use crate::{
+    host::{HostDocument, HostLanguage},
+    jsonrpc::TextDocumentItem,
+    ServerState,
+};
 
 /// Params supplied to the `textDocument/didOpen` method.
 #[derive(Debug, Deserialize)]
@@ -13,8 +17,6 @@ pub struct DidOpenTextDocumentParams {
 
 #[derive(Debug)]
 pub enum DidOpenTextDocumentErrors {
-    NoParamsSupplied,
-    InvalidParams(serde_json::Error),
     FileAlreadyOpened,
 }
 
@@ -28,32 +30,37 @@ pub enum DidOpenTextDocumentErrors {
 /// The DidOpenTextDocumentParams contain the language id the document is associated with.
 /// If the language id of a document changes, the client needs to send a textDocument/didClose to the server followed by a textDocument/didOpen
 /// with the new language id if the server handles the new language id as well.
+///
+/// Returns the URI of the opened document, so its diagnostics can be published.
 pub fn did_open_notification(
     state: &mut ServerState,
-    params: Option<serde_json::Value>,
-) -> Result<(), DidOpenTextDocumentErrors> {
-    info!("Parsing params to didOpen notification...");
-
-    let params = params.ok_or(DidOpenTextDocumentErrors::NoParamsSupplied)?;
+    params: DidOpenTextDocumentParams,
+) -> Result<String, DidOpenTextDocumentErrors> {
     let DidOpenTextDocumentParams {
-        text_document: TextDocumentItem { uri, text, .. },
-    } = serde_json::from_value(params).map_err(DidOpenTextDocumentErrors::InvalidParams)?;
-    info!("Params parsed!");
+        text_document:
+            TextDocumentItem {
+                uri,
+                language_id,
+                text,
+                ..
+            },
+    } = params;
 
     info!("Trying to open file {}", uri);
-    match state.documents.entry(uri) {
+    match state.documents.entry(uri.clone()) {
         std::collections::hash_map::Entry::Occupied(_) => {
             error!("The file is already opened!");
             Err(DidOpenTextDocumentErrors::FileAlreadyOpened)
         }
         std::collections::hash_map::Entry::Vacant(e) => {
-            info!("Generating abstract tree for file...");
-            let ast = MermaidAST::from_content(text);
-            info!("AST generated! {:?}", ast);
+            info!("Generating abstract trees for {} file...", language_id);
+            let document = HostDocument::new(HostLanguage::from_language_id(&language_id), text);
+            info!("{} diagrams found!", document.diagrams.len());
+            debug!("Diagrams generated! {:?}", document.diagrams);
 
             debug!("Updating state...");
-            e.insert(ast);
-            Ok(())
+            e.insert(document.into());
+            Ok(uri)
         }
     }
 }
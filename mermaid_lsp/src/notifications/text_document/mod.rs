@@ -1,3 +1,7 @@
+pub mod did_change;
 pub mod did_open;
+pub mod publish_diagnostics;
 
+pub use did_change::*;
 pub use did_open::*;
+pub use publish_diagnostics::*;
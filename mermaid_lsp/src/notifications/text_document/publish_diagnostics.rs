@@ -0,0 +1,184 @@
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    host::EmbeddedDiagram,
+    jsonrpc::{Range, ServerNotification},
+    mermaid::{self, DiagramDiagnostic},
+    ServerState,
+};
+
+/// The name of the server shown as the source of every diagnostic.
+pub const DIAGNOSTIC_SOURCE: &str = "mermaid_lsp";
+
+/// Params sent with the `textDocument/publishDiagnostics` notification.
+#[derive(Debug, Serialize)]
+pub struct PublishDiagnosticsParams {
+    /// The URI for which diagnostic information is reported.
+    pub uri: String,
+
+    /// An array of diagnostic information items.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Represents a diagnostic, such as a compiler error or warning.
+/// Diagnostic objects are only valid in the scope of a resource.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// The range at which the message applies.
+    pub range: Range,
+
+    /// The diagnostic's severity. To avoid interpretation mismatches when a
+    /// server is used with different clients it is highly recommended that
+    /// servers always provide a severity value.
+    pub severity: Option<u8>,
+
+    /// The diagnostic's code, which might appear in the user interface.
+    pub code: Option<serde_json::Value>,
+
+    /// A human-readable string describing the source of this
+    /// diagnostic, e.g. 'typescript' or 'super lint'.
+    pub source: Option<String>,
+
+    /// The diagnostic's message.
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Creates the LSP diagnostic of a `DiagramDiagnostic` found in `diagram`.
+    pub fn new(diagram: &EmbeddedDiagram, diagnostic: &DiagramDiagnostic) -> Self {
+        Diagnostic {
+            range: diagram.range(diagnostic.span),
+            severity: Some(DiagnosticSeverity::from(diagnostic.severity) as u8),
+            code: Some(diagnostic.code.as_str().into()),
+            source: Some(DIAGNOSTIC_SOURCE.to_string()),
+            message: diagnostic.message.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticSeverity {
+    /// Reports an error.
+    Error = 1,
+
+    /// Reports a warning.
+    Warning = 2,
+
+    /// Reports an information.
+    Information = 3,
+
+    /// Reports a hint.
+    Hint = 4,
+}
+
+impl From<mermaid::DiagnosticSeverity> for DiagnosticSeverity {
+    fn from(value: mermaid::DiagnosticSeverity) -> Self {
+        match value {
+            mermaid::DiagnosticSeverity::Error => DiagnosticSeverity::Error,
+            mermaid::DiagnosticSeverity::Warning => DiagnosticSeverity::Warning,
+            mermaid::DiagnosticSeverity::Information => DiagnosticSeverity::Information,
+            mermaid::DiagnosticSeverity::Hint => DiagnosticSeverity::Hint,
+        }
+    }
+}
+
+/// Diagnostics notifications are sent from the server to the client to signal results of validation runs.
+///
+/// The diagnostics of an opened document are computed again after every change, so the client
+/// always gets the complete list. A document that isn't opened gets an empty list, clearing it.
+/// Documents that embed many diagrams get the diagnostics of all of them.
+pub fn publish_diagnostics_notification(state: &ServerState, uri: String) -> ServerNotification {
+    let diagnostics = state
+        .documents
+        .get(&uri)
+        .map(|document| {
+            document
+                .diagrams
+                .iter()
+                .flat_map(|diagram| {
+                    mermaid::diagnostics(&diagram.ast, state.lint.config())
+                        .iter()
+                        .map(|d| Diagnostic::new(diagram, d))
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    debug!("Publishing diagnostics for {} {:?}", uri, diagnostics);
+    let params = PublishDiagnosticsParams { uri, diagnostics };
+    ServerNotification::new(
+        "textDocument/publishDiagnostics",
+        serde_json::to_value(params).expect("Diagnostics couldn't be serialized into a value!"),
+    )
+}
+
+/// Publishes the diagnostics of every opened document again, after the lint config changed.
+pub fn publish_all_diagnostics(state: &ServerState) -> Vec<ServerNotification> {
+    state
+        .documents
+        .keys()
+        .map(|uri| publish_diagnostics_notification(state, uri.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{HostDocument, HostLanguage};
+
+    #[test]
+    fn publish_diagnostics_of_opened_document() {
+        let mut state = ServerState::default();
+        state.documents.insert(
+            "file:///a.mermaid".to_string(),
+            HostDocument::mermaid("flowchart TD\n    subgraph one\n".to_string()).into(),
+        );
+
+        let notification = publish_diagnostics_notification(&state, "file:///a.mermaid".into());
+        let value = serde_json::to_value(notification).unwrap();
+
+        assert_eq!(value["method"], "textDocument/publishDiagnostics");
+        assert_eq!(value["params"]["uri"], "file:///a.mermaid");
+        assert_eq!(
+            value["params"]["diagnostics"],
+            serde_json::json!([{
+                "range": {
+                    "start": { "line": 1, "character": 4 },
+                    "end": { "line": 1, "character": 16 }
+                },
+                "severity": 1,
+                "code": "unclosed-block",
+                "source": "mermaid_lsp",
+                "message": "This block is never closed with `end`"
+            }])
+        );
+    }
+
+    #[test]
+    fn publish_diagnostics_of_markdown_document() {
+        let mut state = ServerState::default();
+        state.documents.insert(
+            "file:///README.md".to_string(),
+            HostDocument::new(
+                HostLanguage::Markdown,
+                "# Flow\n\n```mermaid\nflowchart TD\n```\n\n  ```mermaid\n  flowchart TD\n      subgraph one\n  ```\n"
+                    .to_string(),
+            )
+            .into(),
+        );
+
+        let notification = publish_diagnostics_notification(&state, "file:///README.md".into());
+        let value = serde_json::to_value(notification).unwrap();
+
+        assert_eq!(
+            value["params"]["diagnostics"][0]["range"],
+            serde_json::json!({
+                "start": { "line": 8, "character": 6 },
+                "end": { "line": 8, "character": 18 }
+            })
+        );
+        assert_eq!(value["params"]["diagnostics"].as_array().unwrap().len(), 1);
+    }
+}
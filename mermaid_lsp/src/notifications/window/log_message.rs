@@ -0,0 +1,53 @@
+use serde::Serialize;
+
+use crate::jsonrpc::ServerNotification;
+
+/// Params sent with the `window/logMessage` notification.
+#[derive(Debug, Serialize)]
+pub struct LogMessageParams {
+    /// The message type.
+    #[serde(rename = "type")]
+    pub message_type: u8,
+
+    /// The actual message.
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    /// An error message.
+    Error = 1,
+
+    /// A warning message.
+    Warning = 2,
+
+    /// An information message.
+    Info = 3,
+
+    /// A log message.
+    Log = 4,
+}
+
+impl From<log::Level> for MessageType {
+    fn from(value: log::Level) -> Self {
+        match value {
+            log::Level::Error => MessageType::Error,
+            log::Level::Warn => MessageType::Warning,
+            log::Level::Info => MessageType::Info,
+            log::Level::Debug | log::Level::Trace => MessageType::Log,
+        }
+    }
+}
+
+/// The log message notification is sent from the server to the client to ask the client to log a particular message.
+pub fn log_message_notification(message_type: MessageType, message: String) -> ServerNotification {
+    let params = LogMessageParams {
+        message_type: message_type as u8,
+        message,
+    };
+
+    ServerNotification::new(
+        "window/logMessage",
+        serde_json::to_value(params).expect("The log message couldn't be serialized into a value!"),
+    )
+}
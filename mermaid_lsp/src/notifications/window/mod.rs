@@ -0,0 +1,3 @@
+pub mod log_message;
+
+pub use log_message::*;
@@ -0,0 +1,161 @@
+use log::{debug, error, info};
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    client::{configuration_request, ConfigurationParams},
+    mermaid::lint::LintConfig,
+    notifications::text_document::publish_all_diagnostics,
+    ServerState,
+};
+
+/// Params supplied to the `workspace/didChangeConfiguration` method.
+#[derive(Debug, Deserialize)]
+pub struct DidChangeConfigurationParams {
+    /// The actual changed settings, clients that answer `workspace/configuration` usually send `null`.
+    #[serde(default, deserialize_with = "null_as_default")]
+    settings: Settings,
+}
+
+/// The settings of the server, sent by the client inside a `mermaid` section.
+#[derive(Debug, Default, Deserialize)]
+pub struct Settings {
+    #[serde(default, deserialize_with = "null_as_default")]
+    mermaid: MermaidSettings,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct MermaidSettings {
+    /// The severity of each lint rule, like `{ "self-loop": "off" }`.
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub lint: LintConfig,
+}
+
+/// Reads `null` like a missing value.
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Pulls the `mermaid` settings with `workspace/configuration`, if the client supports it.
+///
+/// The lint severities received replace the ones of the previous settings, then the
+/// diagnostics of the opened documents are published again.
+pub fn pull_configuration(state: &mut ServerState) {
+    if !state.capabilities.configuration() {
+        return;
+    }
+
+    info!("Pulling the settings of the client...");
+    configuration_request(
+        state,
+        ConfigurationParams::section("mermaid"),
+        |state, result| match result {
+            Ok(sections) => {
+                let mut lint = LintConfig::default();
+                for settings in sections {
+                    match serde_json::from_value::<Option<MermaidSettings>>(settings) {
+                        Ok(settings) => lint.merge(settings.unwrap_or_default().lint),
+                        Err(e) => error!("Invalid settings received! {:?}", e),
+                    }
+                }
+                state.lint.set_client(lint);
+                publish_all_diagnostics(state)
+            }
+            Err(e) => {
+                error!("The settings couldn't be pulled! {:?}", e);
+                vec![]
+            }
+        },
+    );
+}
+
+/// A notification sent from the client to the server to signal the change of configuration settings.
+///
+/// The lint severities of the settings replace the previous ones and override the ones of the project config file.
+/// Settings sent as `null` clear them, and clients that support `workspace/configuration` are asked for them again.
+/// The diagnostics of the opened documents should be published again afterwards.
+pub fn did_change_configuration_notification(
+    state: &mut ServerState,
+    DidChangeConfigurationParams { settings }: DidChangeConfigurationParams,
+) {
+    debug!("Updating lint config with {:?}", settings.mermaid.lint);
+    state.lint.set_client(settings.mermaid.lint);
+    pull_configuration(state);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{jsonrpc::LspId, mermaid::lint::RuleSeverity};
+
+    fn params(settings: serde_json::Value) -> DidChangeConfigurationParams {
+        serde_json::from_value(json!({ "settings": settings })).unwrap()
+    }
+
+    #[test]
+    fn did_change_configuration_replaces_client_lint() {
+        let mut state = ServerState::default();
+        state.lint.set_project(
+            serde_json::from_value(json!({ "self-loop": "warning", "unused-class": "hint" }))
+                .unwrap(),
+        );
+
+        did_change_configuration_notification(
+            &mut state,
+            params(
+                json!({ "mermaid": { "lint": { "self-loop": "off", "duplicate-edge": "error" } } }),
+            ),
+        );
+        assert_eq!(
+            state.lint.config().rules.get("self-loop"),
+            Some(&RuleSeverity::Off)
+        );
+
+        did_change_configuration_notification(
+            &mut state,
+            params(json!({ "mermaid": { "lint": { "unused-class": "error" } } })),
+        );
+        let rules = &state.lint.config().rules;
+        assert_eq!(rules.get("self-loop"), Some(&RuleSeverity::Warning));
+        assert_eq!(rules.get("unused-class"), Some(&RuleSeverity::Error));
+        assert_eq!(rules.get("duplicate-edge"), None);
+
+        did_change_configuration_notification(&mut state, params(json!(null)));
+        assert_eq!(
+            state.lint.config().rules.get("unused-class"),
+            Some(&RuleSeverity::Hint)
+        );
+        assert!(state.outgoing.is_empty());
+    }
+
+    #[test]
+    fn did_change_configuration_pulls_settings() {
+        let mut state = ServerState {
+            capabilities: serde_json::from_value(json!({ "workspace": { "configuration": true } }))
+                .unwrap(),
+            ..Default::default()
+        };
+
+        did_change_configuration_notification(&mut state, params(json!(null)));
+        let request = serde_json::to_value(&state.outgoing[0]).unwrap();
+        assert_eq!(request["method"], "workspace/configuration");
+
+        state.handle_response(
+            &LspId::from(1),
+            Ok(json!([{ "lint": { "self-loop": "off" } }])),
+        );
+        assert_eq!(
+            state.lint.config().rules.get("self-loop"),
+            Some(&RuleSeverity::Off)
+        );
+
+        did_change_configuration_notification(&mut state, params(json!(null)));
+        state.handle_response(&LspId::from(2), Ok(json!([null])));
+        assert_eq!(state.lint.config().rules.get("self-loop"), None);
+    }
+}
@@ -0,0 +1,152 @@
+use log::{debug, error, info};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    client::{register_capability_request, Registration, RegistrationParams},
+    workspace::{is_diagram_file, uri_to_path, IndexJob},
+    ServerState,
+};
+
+/// The files with diagrams, every extension known by `HostLanguage::from_path`.
+const WATCHED_FILES: &str = "**/*.{mmd,mermaid,md,markdown,html,htm,adoc,asciidoc,rst,rs}";
+
+/// Params supplied to the `workspace/didChangeWatchedFiles` method.
+#[derive(Debug, Deserialize)]
+pub struct DidChangeWatchedFilesParams {
+    /// The actual file events.
+    changes: Vec<FileEvent>,
+}
+
+/// An event describing a file change.
+#[derive(Debug, Deserialize)]
+pub struct FileEvent {
+    /// The file's URI.
+    uri: String,
+
+    /// The change type according to `FileChangeType`.
+    #[serde(rename = "type")]
+    change_type: u8,
+}
+
+/// The file event type.
+#[derive(Debug)]
+pub enum FileChangeType {
+    /// The file got created.
+    Created = 1,
+
+    /// The file got changed.
+    Changed = 2,
+
+    /// The file got deleted.
+    Deleted = 3,
+}
+
+/// Asks the client to send `workspace/didChangeWatchedFiles` for every file with diagrams,
+/// if it supports registering the watchers dynamically.
+///
+/// Clients that don't support it have to be configured to watch the files themselves.
+pub fn register_watched_files(state: &mut ServerState) {
+    if !state.capabilities.watched_files_registration() {
+        debug!("The client can't register watched files, the workspace index won't be updated.");
+        return;
+    }
+
+    info!("Registering the diagram files to watch...");
+    let registration = Registration {
+        id: "mermaid-watched-files".into(),
+        method: "workspace/didChangeWatchedFiles".into(),
+        register_options: Some(json!({ "watchers": [{ "globPattern": WATCHED_FILES }] })),
+    };
+    register_capability_request(
+        state,
+        RegistrationParams {
+            registrations: vec![registration],
+        },
+        |_, result| {
+            if let Err(e) = result {
+                error!("The watched files couldn't be registered! {:?}", e);
+            }
+            vec![]
+        },
+    );
+}
+
+/// The watched files notification is sent from the client to the server when the client detects changes to files and folders watched by the language client.
+/// It is recommended that servers register for these file system events using the registration mechanism.
+///
+/// Created and changed files with diagrams are read from disk and indexed again, deleted files are removed from the index.
+/// The files are read on the indexing thread, so slow disks or searches holding the index don't block the next messages.
+/// Opened documents are indexed anyway but `workspace/symbol` prefers their in-memory version.
+pub fn did_change_watched_files_notification(
+    state: &mut ServerState,
+    DidChangeWatchedFilesParams { changes }: DidChangeWatchedFilesParams,
+) {
+    for FileEvent { uri, change_type } in changes {
+        if !uri_to_path(&uri).is_some_and(|p| is_diagram_file(&p)) {
+            continue;
+        }
+
+        let job = match change_type == FileChangeType::Deleted as u8 {
+            true => IndexJob::Remove(uri),
+            false => IndexJob::File(uri),
+        };
+        state.indexer.send(&state.workspace, job);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::path_to_uri;
+
+    #[test]
+    fn did_change_watched_files_success() {
+        let workspace = tempfile::tempdir().unwrap();
+        let path = workspace.path().join("flow.mmd");
+        let uri = path_to_uri(&path).unwrap();
+        let mut state = ServerState::default();
+
+        std::fs::write(&path, "flowchart TD\n    A --> B\n").unwrap();
+        did_change_watched_files_notification(
+            &mut state,
+            serde_json::from_value(
+                json!({ "changes": [{ "uri": uri, "type": FileChangeType::Created as u8 }] }),
+            )
+            .unwrap(),
+        );
+        state.indexer.join();
+        assert_eq!(state.workspace.lock().unwrap().len(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+        did_change_watched_files_notification(
+            &mut state,
+            serde_json::from_value(
+                json!({ "changes": [{ "uri": uri, "type": FileChangeType::Deleted as u8 }] }),
+            )
+            .unwrap(),
+        );
+        state.indexer.join();
+        assert!(state.workspace.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn register_watched_files_success() {
+        let capabilities = json!({
+            "workspace": { "didChangeWatchedFiles": { "dynamicRegistration": true } }
+        });
+        let mut state = ServerState {
+            capabilities: serde_json::from_value(capabilities).unwrap(),
+            ..Default::default()
+        };
+
+        register_watched_files(&mut state);
+
+        let request = serde_json::to_value(&state.outgoing[0]).unwrap();
+        assert_eq!(request["method"], "client/registerCapability");
+        assert_eq!(
+            request["params"]["registrations"][0]["registerOptions"]["watchers"][0]["globPattern"],
+            WATCHED_FILES
+        );
+    }
+}
@@ -0,0 +1,5 @@
+pub mod did_change_configuration;
+pub mod did_change_watched_files;
+
+pub use did_change_configuration::*;
+pub use did_change_watched_files::*;
@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    host::EmbeddedDiagram,
+    jsonrpc::{Range, TextDocumentIdentifier, TextEdit},
+    mermaid::{
+        diagnostics, lint::suppression_fix, refactors, DiagramDiagnostic, DiagramEdit,
+        DiagramRefactor, RefactorKind,
+    },
+    notifications::text_document::Diagnostic,
+    ServerState,
+};
+
+/// The kind of the code actions that fix a diagnostic.
+pub const QUICK_FIX_KIND: &str = "quickfix";
+
+/// The kind of the code actions that move statements into a new construct.
+pub const REFACTOR_EXTRACT_KIND: &str = "refactor.extract";
+
+/// The kind of the code actions that rewrite the document.
+pub const REFACTOR_REWRITE_KIND: &str = "refactor.rewrite";
+
+/// Code Action options.
+#[derive(Debug, Serialize)]
+pub struct CodeActionOptions {
+    /// CodeActionKinds that this server may return.
+    ///
+    /// The list of kinds may be generic, such as `CodeActionKind.Refactor`,
+    /// or the server may list out every specific kind they provide.
+    #[serde(rename = "codeActionKinds")]
+    pub code_action_kinds: Vec<String>,
+}
+
+impl Default for CodeActionOptions {
+    fn default() -> Self {
+        CodeActionOptions {
+            code_action_kinds: [QUICK_FIX_KIND, REFACTOR_EXTRACT_KIND, REFACTOR_REWRITE_KIND]
+                .map(str::to_string)
+                .to_vec(),
+        }
+    }
+}
+
+/// Params for the `textDocument/codeAction` request.
+#[derive(Debug, Deserialize)]
+pub struct CodeActionParams {
+    /// The document in which the command was invoked.
+    #[serde(rename = "textDocument")]
+    pub text_document: TextDocumentIdentifier,
+
+    /// The range for which the command was invoked.
+    pub range: Range,
+
+    /// Context carrying additional information.
+    pub context: CodeActionContext,
+}
+
+/// Contains additional diagnostic information about the context in which
+/// a code action is run.
+#[derive(Debug, Deserialize)]
+pub struct CodeActionContext {
+    /// An array of diagnostics known on the client side overlapping the range
+    /// provided to the `textDocument/codeAction` request.
+    pub diagnostics: Vec<Diagnostic>,
+
+    /// Requested kind of actions to return.
+    ///
+    /// Actions not of this kind are filtered out by the client before being
+    /// shown. So servers can omit computing them.
+    pub only: Option<Vec<String>>,
+}
+
+/// A code action represents a change that can be performed in code, e.g. to fix
+/// a problem or to refactor code.
+#[derive(Debug, Serialize)]
+pub struct CodeAction {
+    /// A short, human-readable, title for this code action.
+    pub title: String,
+
+    /// The kind of the code action.
+    pub kind: String,
+
+    /// The diagnostics that this code action resolves.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
+
+    /// Marks this as a preferred action. Preferred actions are used by the
+    /// `auto fix` command and can be targeted by keybindings.
+    #[serde(rename = "isPreferred")]
+    pub is_preferred: bool,
+
+    /// The workspace edit this code action performs.
+    pub edit: WorkspaceEdit,
+}
+
+/// A workspace edit represents changes to many resources managed in the workspace.
+#[derive(Debug, Serialize)]
+pub struct WorkspaceEdit {
+    /// Holds changes to existing resources.
+    pub changes: HashMap<String, Vec<TextEdit>>,
+}
+
+impl WorkspaceEdit {
+    /// An edit of a single document.
+    pub fn new(uri: &str, edits: Vec<TextEdit>) -> Self {
+        WorkspaceEdit {
+            changes: HashMap::from([(uri.to_string(), edits)]),
+        }
+    }
+}
+
+/// Whether an action of `kind` was requested, `only` may list a parent kind like `refactor`.
+pub fn is_kind_requested(only: &Option<Vec<String>>, kind: &str) -> bool {
+    only.as_ref().is_none_or(|only| {
+        only.iter().any(|o| {
+            kind == o
+                || kind
+                    .strip_prefix(o.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    })
+}
+
+/// The text edits of the edits of a diagram, inside the document that contains it.
+fn text_edits(diagram: &EmbeddedDiagram, edits: &[DiagramEdit]) -> Vec<TextEdit> {
+    edits
+        .iter()
+        .map(|e| {
+            diagram.map.host_edit(TextEdit {
+                range: diagram.ast.cst.range(e.span),
+                new_text: e.new_text.clone(),
+            })
+        })
+        .collect()
+}
+
+/// The quick fix of a diagnostic, if it has one.
+pub fn quick_fix(
+    uri: &str,
+    diagram: &EmbeddedDiagram,
+    diagnostic: &DiagramDiagnostic,
+) -> Option<CodeAction> {
+    let fix = diagnostic.fix.as_ref()?;
+
+    Some(CodeAction {
+        title: fix.title.clone(),
+        kind: QUICK_FIX_KIND.to_string(),
+        diagnostics: vec![Diagnostic::new(diagram, diagnostic)],
+        is_preferred: true,
+        edit: WorkspaceEdit::new(uri, text_edits(diagram, &fix.edits)),
+    })
+}
+
+/// The code action that silences a lint diagnostic with a suppression comment.
+pub fn suppression_action(
+    uri: &str,
+    diagram: &EmbeddedDiagram,
+    diagnostic: &DiagramDiagnostic,
+) -> Option<CodeAction> {
+    let fix = suppression_fix(&diagram.ast.cst, diagnostic)?;
+
+    Some(CodeAction {
+        title: fix.title,
+        kind: QUICK_FIX_KIND.to_string(),
+        diagnostics: vec![Diagnostic::new(diagram, diagnostic)],
+        is_preferred: false,
+        edit: WorkspaceEdit::new(uri, text_edits(diagram, &fix.edits)),
+    })
+}
+
+/// The code action of a refactor.
+pub fn refactor_action(
+    uri: &str,
+    diagram: &EmbeddedDiagram,
+    refactor: &DiagramRefactor,
+) -> CodeAction {
+    let kind = match refactor.kind {
+        RefactorKind::Extract => REFACTOR_EXTRACT_KIND,
+        RefactorKind::Rewrite => REFACTOR_REWRITE_KIND,
+    };
+
+    CodeAction {
+        title: refactor.title.clone(),
+        kind: kind.to_string(),
+        diagnostics: vec![],
+        is_preferred: false,
+        edit: WorkspaceEdit::new(uri, text_edits(diagram, &refactor.edits)),
+    }
+}
+
+/// The code action request is sent from the client to the server to compute commands for a given text document and range.
+///
+/// The diagnostics are computed again from the document, so every diagnostic touching the range gets its quick fix.
+/// Lint diagnostics can also be silenced with a suppression comment, and the refactors of the selection follow the quick fixes.
+pub fn code_action_request(
+    state: &ServerState,
+    params: CodeActionParams,
+) -> Option<Vec<CodeAction>> {
+    info!(
+        "Computing code actions of {} at {:?}",
+        params.text_document.uri, params.range
+    );
+
+    let Some(document) = state.documents.get(&params.text_document.uri) else {
+        error!("The file {} is not opened!", params.text_document.uri);
+        return None;
+    };
+
+    let uri = &params.text_document.uri;
+    let mut actions = vec![];
+    for diagram in document.diagrams.iter() {
+        let Some(span) = diagram.span(params.range) else {
+            continue;
+        };
+        let ast = &diagram.ast;
+
+        if is_kind_requested(&params.context.only, QUICK_FIX_KIND) {
+            let diagnostics: Vec<_> = diagnostics(ast, state.lint.config())
+                .into_iter()
+                .filter(|d| d.span.touches(span))
+                .collect();
+            actions.extend(
+                diagnostics
+                    .iter()
+                    .filter_map(|d| quick_fix(uri, diagram, d)),
+            );
+            actions.extend(
+                diagnostics
+                    .iter()
+                    .filter_map(|d| suppression_action(uri, diagram, d)),
+            );
+        }
+        actions.extend(
+            refactors(ast, span)
+                .iter()
+                .map(|r| refactor_action(uri, diagram, r))
+                .filter(|a| is_kind_requested(&params.context.only, &a.kind)),
+        );
+    }
+
+    debug!("Code actions generated {:?}", actions);
+    Some(actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::HostDocument;
+
+    fn actions(content: &str, range: Range, only: Option<Vec<&str>>) -> serde_json::Value {
+        let mut state = ServerState::default();
+        state.documents.insert(
+            "file:///a.mermaid".to_string(),
+            HostDocument::mermaid(content.to_string()).into(),
+        );
+        let params = serde_json::json!({
+            "textDocument": { "uri": "file:///a.mermaid" },
+            "range": range,
+            "context": { "diagnostics": [], "only": only },
+        });
+
+        serde_json::to_value(code_action_request(
+            &state,
+            serde_json::from_value(params).unwrap(),
+        ))
+        .unwrap()
+    }
+
+    fn line_range(line: u32) -> Range {
+        Range {
+            start: crate::jsonrpc::Position { line, character: 0 },
+            end: crate::jsonrpc::Position { line, character: 1 },
+        }
+    }
+
+    #[test]
+    fn code_action_quick_fix() {
+        let result = actions("flowchat TD\n    A\n", line_range(0), None);
+
+        assert_eq!(
+            result,
+            serde_json::json!([{
+                "title": "Replace with `flowchart`",
+                "kind": "quickfix",
+                "diagnostics": [{
+                    "range": {
+                        "start": { "line": 0, "character": 0 },
+                        "end": { "line": 0, "character": 8 }
+                    },
+                    "severity": 1,
+                    "code": "unknown-diagram-type",
+                    "source": "mermaid_lsp",
+                    "message": "Unknown diagram type `flowchat`"
+                }],
+                "isPreferred": true,
+                "edit": {
+                    "changes": {
+                        "file:///a.mermaid": [{
+                            "range": {
+                                "start": { "line": 0, "character": 0 },
+                                "end": { "line": 0, "character": 8 }
+                            },
+                            "newText": "flowchart"
+                        }]
+                    }
+                }
+            }])
+        );
+    }
+
+    #[test]
+    fn code_action_suppression() {
+        let range = Range {
+            start: crate::jsonrpc::Position {
+                line: 1,
+                character: 0,
+            },
+            end: crate::jsonrpc::Position {
+                line: 2,
+                character: 0,
+            },
+        };
+        let result = actions("flowchart TD\n    A --> A\n", range, Some(vec!["quickfix"]));
+
+        assert_eq!(result[0]["title"], "Disable `self-loop` for this line");
+        assert_eq!(
+            result[0]["edit"]["changes"]["file:///a.mermaid"][0]["newText"],
+            "    %% mermaid-lsp-disable-next-line self-loop\n"
+        );
+    }
+
+    #[test]
+    fn code_action_refactors() {
+        let cursor = crate::jsonrpc::Position {
+            line: 1,
+            character: 4,
+        };
+        let range = Range {
+            start: cursor,
+            end: cursor,
+        };
+        let result = actions("flowchart TD\n    A --> B\n", range, None);
+        let kinds: Vec<_> = result
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| (a["kind"].as_str().unwrap(), a["title"].as_str().unwrap()))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                ("refactor.extract", "Extract into subgraph"),
+                ("refactor.rewrite", "Convert direction to LR")
+            ]
+        );
+        assert_eq!(
+            actions(
+                "flowchart TD\n    A --> B\n",
+                range,
+                Some(vec!["refactor.rewrite"])
+            )
+            .as_array()
+            .map(Vec::len),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn code_action_outside_range_or_kind() {
+        let content = "flowchat TD\n    A\n";
+
+        assert_eq!(actions(content, line_range(1), None), serde_json::json!([]));
+        assert_eq!(
+            actions(content, line_range(0), Some(vec!["refactor"])),
+            serde_json::json!([])
+        );
+        assert_eq!(
+            actions(content, line_range(0), Some(vec!["quickfix"]))
+                .as_array()
+                .map(Vec::len),
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    host::EmbeddedDiagram,
+    jsonrpc::{Range, TextDocumentIdentifier},
+    mermaid::{document_symbols, DiagramSymbol, DiagramSymbolKind},
+    ServerState,
+};
+
+/// Params supplied to the `textDocument/documentSymbol` method.
+#[derive(Debug, Deserialize)]
+pub struct DocumentSymbolParams {
+    /// The text document.
+    #[serde(rename = "textDocument")]
+    pub text_document: TextDocumentIdentifier,
+}
+
+/// Represents programming constructs like variables, classes, interfaces etc.
+/// that appear in a document. Document symbols can be hierarchical and they
+/// have two ranges: one that encloses its definition and one that points to its
+/// most interesting range, e.g. the range of an identifier.
+#[derive(Debug, Serialize)]
+pub struct DocumentSymbol {
+    /// The name of this symbol. Will be displayed in the user interface and
+    /// therefore must not be an empty string or a string only consisting of
+    /// white spaces.
+    pub name: String,
+
+    /// More detail for this symbol, e.g the signature of a function.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+
+    /// The kind of this symbol according to `SymbolKind`.
+    pub kind: u8,
+
+    /// The range enclosing this symbol not including leading/trailing whitespace
+    /// but everything else like comments. This information is typically used to
+    /// determine if the clients cursor is inside the symbol to reveal in the
+    /// symbol in the UI.
+    pub range: Range,
+
+    /// The range that should be selected and revealed when this symbol is being
+    /// picked, e.g. the name of a function. Must be contained by the `range`.
+    #[serde(rename = "selectionRange")]
+    pub selection_range: Range,
+
+    /// Children of this symbol, e.g. properties of a class.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<DocumentSymbol>,
+}
+
+impl DocumentSymbol {
+    /// Converts a `DiagramSymbol` and all its children to positions of the document that contains `diagram`.
+    pub fn new(diagram: &EmbeddedDiagram, symbol: &DiagramSymbol) -> Self {
+        DocumentSymbol {
+            name: symbol.name.clone(),
+            detail: symbol.detail.clone(),
+            kind: SymbolKind::from(symbol.kind) as u8,
+            range: diagram.range(symbol.span),
+            selection_range: diagram.range(symbol.selection_span),
+            children: symbol
+                .children
+                .iter()
+                .map(|c| DocumentSymbol::new(diagram, c))
+                .collect(),
+        }
+    }
+}
+
+/// A symbol kind.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum SymbolKind {
+    File = 1,
+    Module = 2,
+    Namespace = 3,
+    Package = 4,
+    Class = 5,
+    Method = 6,
+    Property = 7,
+    Field = 8,
+    Constructor = 9,
+    Enum = 10,
+    Interface = 11,
+    Function = 12,
+    Variable = 13,
+    Constant = 14,
+    String = 15,
+    Number = 16,
+    Boolean = 17,
+    Array = 18,
+    Object = 19,
+    Key = 20,
+    Null = 21,
+    EnumMember = 22,
+    Struct = 23,
+    Event = 24,
+    Operator = 25,
+    TypeParameter = 26,
+}
+
+impl From<DiagramSymbolKind> for SymbolKind {
+    fn from(value: DiagramSymbolKind) -> Self {
+        match value {
+            DiagramSymbolKind::Title => SymbolKind::String,
+            DiagramSymbolKind::Diagram => SymbolKind::Module,
+            DiagramSymbolKind::Subgraph => SymbolKind::Namespace,
+            DiagramSymbolKind::Node => SymbolKind::Object,
+            DiagramSymbolKind::Participant => SymbolKind::Interface,
+            DiagramSymbolKind::Block => SymbolKind::Struct,
+            DiagramSymbolKind::Namespace => SymbolKind::Namespace,
+            DiagramSymbolKind::Class => SymbolKind::Class,
+            DiagramSymbolKind::Attribute => SymbolKind::Field,
+            DiagramSymbolKind::Method => SymbolKind::Method,
+            DiagramSymbolKind::State => SymbolKind::EnumMember,
+            DiagramSymbolKind::Entity => SymbolKind::Struct,
+            DiagramSymbolKind::Section => SymbolKind::Package,
+            DiagramSymbolKind::Task => SymbolKind::Event,
+        }
+    }
+}
+
+/// The document symbol request is sent from the client to the server. The returned result is either
+///
+/// * `SymbolInformation[]` which is a flat list of all symbols found in a given text document.
+///
+/// * `DocumentSymbol[]` which is a hierarchy of symbols found in a given text document.
+///
+/// This server always answers with the hierarchy, every diagram symbol is nested inside the
+/// subgraph, block or section that contains it.
+pub fn document_symbol_request(
+    state: &ServerState,
+    params: DocumentSymbolParams,
+) -> Option<Vec<DocumentSymbol>> {
+    info!("Generating symbols of {}", params.text_document.uri);
+
+    let Some(document) = state.documents.get(&params.text_document.uri) else {
+        error!("The file {} is not opened!", params.text_document.uri);
+        return None;
+    };
+
+    let symbols: Vec<_> = document
+        .diagrams
+        .iter()
+        .flat_map(|diagram| {
+            document_symbols(&diagram.ast)
+                .iter()
+                .map(|s| DocumentSymbol::new(diagram, s))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    debug!("Symbols generated {:?}", symbols);
+    Some(symbols)
+}
@@ -0,0 +1,99 @@
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    jsonrpc::TextDocumentIdentifier,
+    mermaid::{folding_ranges, DiagramFold, DiagramFoldKind},
+    ServerState,
+};
+
+/// Params supplied to the `textDocument/foldingRange` method.
+#[derive(Debug, Deserialize)]
+pub struct FoldingRangeParams {
+    /// The text document.
+    #[serde(rename = "textDocument")]
+    pub text_document: TextDocumentIdentifier,
+}
+
+/// Represents a folding range. To be valid, start and end line must be bigger
+/// than zero and smaller than the number of lines in the document. Clients
+/// are free to ignore invalid ranges.
+#[derive(Debug, Serialize)]
+pub struct FoldingRange {
+    /// The zero-based start line of the range to fold. The folded area starts
+    /// after the line's last character. To be valid, the end must be zero or
+    /// larger and smaller than the number of lines in the document.
+    #[serde(rename = "startLine")]
+    pub start_line: u32,
+
+    /// The zero-based end line of the range to fold. The folded area ends with
+    /// the line's last character. To be valid, the end must be zero or larger
+    /// and smaller than the number of lines in the document.
+    #[serde(rename = "endLine")]
+    pub end_line: u32,
+
+    /// Describes the kind of the folding range such as `comment` or `region`.
+    /// The kind is used to categorize folding ranges and used by commands like
+    /// 'Fold all comments'.
+    pub kind: FoldingRangeKind,
+}
+
+impl From<DiagramFold> for FoldingRange {
+    fn from(value: DiagramFold) -> Self {
+        FoldingRange {
+            start_line: value.start_line,
+            end_line: value.end_line,
+            kind: match value.kind {
+                DiagramFoldKind::Comment => FoldingRangeKind::Comment,
+                DiagramFoldKind::Frontmatter | DiagramFoldKind::Block => FoldingRangeKind::Region,
+            },
+        }
+    }
+}
+
+/// A set of predefined range kinds.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FoldingRangeKind {
+    /// Folding range for a comment
+    Comment,
+
+    /// Folding range for imports or includes
+    Imports,
+
+    /// Folding range for a region (e.g. `#region`)
+    Region,
+}
+
+/// The folding range request is sent from the client to the server to return all folding ranges found in a given text document.
+///
+/// Folds are computed from the syntax tree: the frontmatter, every block of statements and runs of `%%` comments.
+pub fn folding_range_request(
+    state: &ServerState,
+    params: FoldingRangeParams,
+) -> Option<Vec<FoldingRange>> {
+    info!("Generating folding ranges of {}", params.text_document.uri);
+
+    let Some(document) = state.documents.get(&params.text_document.uri) else {
+        error!("The file {} is not opened!", params.text_document.uri);
+        return None;
+    };
+
+    let ranges: Vec<FoldingRange> = document
+        .diagrams
+        .iter()
+        .flat_map(|diagram| {
+            folding_ranges(&diagram.ast.cst)
+                .into_iter()
+                .map(|fold| DiagramFold {
+                    start_line: diagram.map.host_line(fold.start_line),
+                    end_line: diagram.map.host_line(fold.end_line),
+                    ..fold
+                })
+        })
+        .map(FoldingRange::from)
+        .collect();
+
+    debug!("Folding ranges generated {:?}", ranges);
+    Some(ranges)
+}
@@ -0,0 +1,157 @@
+use log::{debug, error, info};
+use serde::Deserialize;
+
+use crate::{
+    jsonrpc::{Range, TextDocumentIdentifier, TextEdit},
+    mermaid::{format_lines, split_lines, FormatOptions, SyntaxTree, TextSpan},
+    ServerState,
+};
+
+/// Params supplied to the `textDocument/formatting` method.
+#[derive(Debug, Deserialize)]
+pub struct DocumentFormattingParams {
+    /// The document to format.
+    #[serde(rename = "textDocument")]
+    pub text_document: TextDocumentIdentifier,
+
+    /// The format options.
+    pub options: FormattingOptions,
+}
+
+/// Params supplied to the `textDocument/rangeFormatting` method.
+#[derive(Debug, Deserialize)]
+pub struct DocumentRangeFormattingParams {
+    /// The document to format.
+    #[serde(rename = "textDocument")]
+    pub text_document: TextDocumentIdentifier,
+
+    /// The range to format
+    pub range: Range,
+
+    /// The format options
+    pub options: FormattingOptions,
+}
+
+/// Value-object describing what options formatting should use.
+#[derive(Debug, Deserialize)]
+pub struct FormattingOptions {
+    /// Size of a tab in spaces.
+    #[serde(rename = "tabSize")]
+    pub tab_size: u32,
+
+    /// Prefer spaces over tabs.
+    #[serde(rename = "insertSpaces")]
+    pub insert_spaces: bool,
+}
+
+impl From<FormattingOptions> for FormatOptions {
+    fn from(value: FormattingOptions) -> Self {
+        FormatOptions {
+            tab_size: value.tab_size,
+            insert_spaces: value.insert_spaces,
+        }
+    }
+}
+
+/// Computes the edits that format the lines of `tree` between `first` and `last`, both inclusive.
+///
+/// Only the lines that change get an edit.
+pub fn format_edits(
+    tree: &SyntaxTree,
+    options: &FormatOptions,
+    first: usize,
+    last: usize,
+) -> Vec<TextEdit> {
+    split_lines(tree.text(), 0)
+        .zip(format_lines(tree, options))
+        .enumerate()
+        .filter(|(i, ((_, line), formatted))| first <= *i && *i <= last && line != formatted)
+        .map(|(_, ((start, line), formatted))| TextEdit {
+            range: tree.range(TextSpan::new(start, start + line.len())),
+            new_text: formatted,
+        })
+        .collect()
+}
+
+/// The document formatting request is sent from the client to the server to format a whole document.
+///
+/// Every diagram of the document is formatted, the text around embedded diagrams is left untouched.
+pub fn formatting_request(
+    state: &ServerState,
+    params: DocumentFormattingParams,
+) -> Option<Vec<TextEdit>> {
+    info!("Formatting {}", params.text_document.uri);
+
+    let Some(document) = state.documents.get(&params.text_document.uri) else {
+        error!("The file {} is not opened!", params.text_document.uri);
+        return None;
+    };
+
+    let options = params.options.into();
+    let edits: Vec<_> = document
+        .diagrams
+        .iter()
+        .flat_map(|diagram| {
+            format_edits(&diagram.ast.cst, &options, 0, usize::MAX)
+                .into_iter()
+                .map(|e| diagram.map.host_edit(e))
+        })
+        .collect();
+
+    debug!("Formatting edits generated {:?}", edits);
+    Some(edits)
+}
+
+/// The document range formatting request is sent from the client to the server to format a given range in a document.
+///
+/// Every line touched by the range is formatted, a range that ends at the start of a line doesn't include that line.
+pub fn range_formatting_request(
+    state: &ServerState,
+    params: DocumentRangeFormattingParams,
+) -> Option<Vec<TextEdit>> {
+    info!(
+        "Formatting {} from {:?}",
+        params.text_document.uri, params.range
+    );
+
+    let Some(document) = state.documents.get(&params.text_document.uri) else {
+        error!("The file {} is not opened!", params.text_document.uri);
+        return None;
+    };
+
+    let Range { start, end } = params.range;
+    let last = match end.character == 0 && end.line > start.line {
+        true => end.line - 1,
+        false => end.line,
+    };
+    let options = params.options.into();
+    let edits: Vec<_> = document
+        .diagrams
+        .iter()
+        .filter_map(|diagram| {
+            let (first, last) = diagram.map.diagram_lines(start.line, last)?;
+            let edits = format_edits(&diagram.ast.cst, &options, first as usize, last as usize);
+            Some(edits.into_iter().map(|e| diagram.map.host_edit(e)))
+        })
+        .flatten()
+        .collect();
+
+    debug!("Formatting edits generated {:?}", edits);
+    Some(edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_edits_only_touches_range() {
+        let tree = SyntaxTree::parse("flowchart TD\nA-->B\nB-->C\n".to_string());
+
+        let edits = format_edits(&tree, &FormatOptions::default(), 2, 2);
+
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range, tree.range(TextSpan::new(19, 25)));
+        assert_eq!(edits[0].new_text, "    B --> C\n");
+    }
+}
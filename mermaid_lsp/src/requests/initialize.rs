@@ -1,7 +1,12 @@
 use log::{debug, error, info};
 use serde::{Deserialize, Serialize};
 
-use crate::jsonrpc::{ErrorCodes, LspId, ResponseError, ServerResponse};
+use super::{CodeActionOptions, DocumentOnTypeFormattingOptions};
+use crate::{
+    mermaid::lint::LintConfig,
+    workspace::{uri_to_path, IndexJob},
+    ServerState,
+};
 
 #[derive(Debug)]
 pub enum InitializeRequestErrors {
@@ -14,10 +19,54 @@ pub struct InitializeRequestParams {
     ///
     /// @since 3.15.0
     #[serde(rename = "clientInfo")]
-    client_info: Option<AppInfo>,
+    pub client_info: Option<AppInfo>,
+
+    /// The rootUri of the workspace. Is null if no
+    /// folder is open. If both `rootPath` and `rootUri` are set
+    /// `rootUri` wins.
+    ///
+    /// @deprecated in favour of `workspaceFolders`
+    #[serde(rename = "rootUri")]
+    pub root_uri: Option<String>,
 
     /// The capabilities provided by the client (editor or tool)
-    capabilities: ClientCapabilities,
+    pub capabilities: ClientCapabilities,
+
+    /// The workspace folders configured in the client when the server starts.
+    /// This property is only available if the client supports workspace folders.
+    /// It can be `null` if the client supports workspace folders but none are
+    /// configured.
+    ///
+    /// @since 3.6.0
+    #[serde(rename = "workspaceFolders")]
+    pub workspace_folders: Option<Vec<WorkspaceFolder>>,
+}
+
+/// A workspace folder inside a client.
+#[derive(Debug, Deserialize)]
+pub struct WorkspaceFolder {
+    /// The associated URI for this workspace folder.
+    pub uri: String,
+
+    /// The name of the workspace folder. Used to refer to this
+    /// workspace folder in the user interface.
+    pub name: String,
+}
+
+impl InitializeRequestParams {
+    /// The paths of every folder of the workspace, `workspaceFolders` wins over `rootUri`.
+    pub fn workspace_paths(&self) -> Vec<std::path::PathBuf> {
+        match &self.workspace_folders {
+            Some(folders) if !folders.is_empty() => {
+                folders.iter().filter_map(|f| uri_to_path(&f.uri)).collect()
+            }
+            _ => self
+                .root_uri
+                .iter()
+                .filter_map(|u| uri_to_path(u))
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -29,19 +78,54 @@ pub struct AppInfo {
     version: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct ClientCapabilities {
     /// Workspace specific client capabilities.
-    workspace: Option<WorkspaceCapabilities>,
+    pub workspace: Option<WorkspaceCapabilities>,
+}
+
+impl ClientCapabilities {
+    /// Whether the client answers `workspace/configuration` requests.
+    pub fn configuration(&self) -> bool {
+        self.workspace
+            .as_ref()
+            .and_then(|w| w.configuration)
+            .unwrap_or_default()
+    }
+
+    /// Whether the client lets the server register the files it wants to watch.
+    pub fn watched_files_registration(&self) -> bool {
+        self.workspace
+            .as_ref()
+            .and_then(|w| w.did_change_watched_files.as_ref())
+            .and_then(|c| c.dynamic_registration)
+            .unwrap_or_default()
+    }
 }
 
 /// Workspace specific client capabilities.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct WorkspaceCapabilities {
     /// The client supports applying batch edits to the workspace by supporting the request
     /// 'workspace/applyEdit'
     #[serde(rename = "applyEdit")]
-    apply_edit: Option<bool>,
+    pub apply_edit: Option<bool>,
+
+    /// The client supports `workspace/configuration` requests.
+    ///
+    /// @since 3.6.0
+    pub configuration: Option<bool>,
+
+    /// Capabilities specific to the `workspace/didChangeWatchedFiles` notification.
+    #[serde(rename = "didChangeWatchedFiles")]
+    pub did_change_watched_files: Option<DynamicRegistrationCapabilities>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DynamicRegistrationCapabilities {
+    /// Whether the capability supports dynamic registration.
+    #[serde(rename = "dynamicRegistration")]
+    pub dynamic_registration: Option<bool>,
 }
 
 #[derive(Debug, Serialize)]
@@ -62,6 +146,34 @@ pub struct ServerCapabilities {
     /// `TextDocumentSyncKind.None`.
     #[serde(rename = "textDocumentSync")]
     text_document_sync: u8,
+
+    /// The server provides document symbol support.
+    #[serde(rename = "documentSymbolProvider")]
+    document_symbol_provider: bool,
+
+    /// The server provides workspace symbol support.
+    #[serde(rename = "workspaceSymbolProvider")]
+    workspace_symbol_provider: bool,
+
+    /// The server provides folding provider support.
+    #[serde(rename = "foldingRangeProvider")]
+    folding_range_provider: bool,
+
+    /// The server provides document formatting.
+    #[serde(rename = "documentFormattingProvider")]
+    document_formatting_provider: bool,
+
+    /// The server provides document range formatting.
+    #[serde(rename = "documentRangeFormattingProvider")]
+    document_range_formatting_provider: bool,
+
+    /// The server provides document formatting on typing.
+    #[serde(rename = "documentOnTypeFormattingProvider")]
+    document_on_type_formatting_provider: DocumentOnTypeFormattingOptions,
+
+    /// The server provides code actions.
+    #[serde(rename = "codeActionProvider")]
+    code_action_provider: CodeActionOptions,
 }
 
 /// Defines how the host (editor) should sync document changes to the language
@@ -90,39 +202,34 @@ pub enum TextDocumentSyncKind {
 /// Until the server has responded to the initialize request with an InitializeResult, the client must not send any additional requests or notifications to the server. In addition the server is not allowed to send any requests or notifications to the client until it has responded with an InitializeResult, with the exception that during the initialize request the server is allowed to send the notifications window/showMessage, window/logMessage and telemetry/event as well as the window/showMessageRequest request to the client. In case the client sets up a progress token in the initialize params (e.g. property workDoneToken) the server is also allowed to use that token (and only that token) using the $/progress notification sent from the server to the client.
 ///
 /// The initialize request may only be sent once.
-pub fn initialize_request(id: LspId, params: Option<serde_json::Value>) -> ServerResponse {
-    if params.is_none() {
-        error!("No initialization params supplied!");
-        return ServerResponse::new_error(
-            Some(id),
-            ResponseError::new(
-                ErrorCodes::InvalidParams,
-                "No initialization params supplied!".into(),
-            ),
-        );
-    }
-
-    let params: InitializeRequestParams = match serde_json::from_value(params.unwrap()) {
-        Ok(v) => v,
-        Err(e) => {
-            error!(
-                "An error occurred while trying to parse initialize request params {:?}",
-                e
-            );
-            return ServerResponse::new_error(
-                Some(id),
-                ResponseError::new(
-                    ErrorCodes::InvalidParams,
-                    "Invalid params supplied to initialize request!".into(),
-                ),
-            );
-        }
-    };
+///
+/// The workspace folders are indexed on a background thread, so `workspace/symbol` can find diagrams that aren't opened.
+/// The lint config is read from the project config file of every folder.
+pub fn initialize_request(
+    state: &mut ServerState,
+    params: InitializeRequestParams,
+) -> InitializeResult {
     info!(
         "Successfully parsed params for `initialize` request! {:?}",
         params
     );
 
+    let folders = params.workspace_paths();
+    let mut project_lint = LintConfig::default();
+    for folder in folders.iter() {
+        match LintConfig::from_folder(folder) {
+            Ok(config) => project_lint.merge(config),
+            Err(e) => error!("The config of {:?} couldn't be read {:?}", folder, e),
+        }
+    }
+    state.lint.set_project(project_lint);
+    if !folders.is_empty() {
+        state
+            .indexer
+            .send(&state.workspace, IndexJob::Folders(folders));
+    }
+    state.capabilities = params.capabilities;
+
     debug!("Generating response...");
     let server_result = InitializeResult {
         server_info: AppInfo {
@@ -130,14 +237,17 @@ pub fn initialize_request(id: LspId, params: Option<serde_json::Value>) -> Serve
             version: Some(env!("CARGO_PKG_VERSION").to_string()),
         },
         capabilities: ServerCapabilities {
-            text_document_sync: TextDocumentSyncKind::Full as u8,
+            text_document_sync: TextDocumentSyncKind::Incremental as u8,
+            document_symbol_provider: true,
+            workspace_symbol_provider: true,
+            folding_range_provider: true,
+            document_formatting_provider: true,
+            document_range_formatting_provider: true,
+            document_on_type_formatting_provider: DocumentOnTypeFormattingOptions::default(),
+            code_action_provider: CodeActionOptions::default(),
         },
     };
 
     debug!("Response generated {:?}", server_result);
-    ServerResponse::new_result(
-        Some(id),
-        serde_json::to_value(server_result)
-            .expect("Server message couldn't be serialized into a value!"),
-    )
+    server_result
 }
@@ -1,5 +1,17 @@
+mod code_action;
+mod document_symbol;
+mod folding_range;
+mod formatting;
 mod initialize;
+mod on_type_formatting;
 mod shutdown;
+mod workspace_symbol;
 
+pub use code_action::*;
+pub use document_symbol::*;
+pub use folding_range::*;
+pub use formatting::*;
 pub use initialize::*;
+pub use on_type_formatting::*;
 pub use shutdown::*;
+pub use workspace_symbol::*;
@@ -0,0 +1,186 @@
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    jsonrpc::{Position, Range, TextDocumentIdentifier, TextEdit},
+    mermaid::{indentation_at, FormatOptions, SyntaxTree},
+    ServerState,
+};
+
+use super::FormattingOptions;
+
+/// The character that triggers on type formatting.
+pub const ON_TYPE_FIRST_TRIGGER: &str = "\n";
+
+/// More characters that trigger on type formatting, `d` completes the `end` keyword.
+pub const ON_TYPE_MORE_TRIGGERS: [&str; 1] = ["d"];
+
+/// Format document on type options.
+#[derive(Debug, Serialize)]
+pub struct DocumentOnTypeFormattingOptions {
+    /// A character on which formatting should be triggered, like `{`.
+    #[serde(rename = "firstTriggerCharacter")]
+    pub first_trigger_character: String,
+
+    /// More trigger characters.
+    #[serde(rename = "moreTriggerCharacter")]
+    pub more_trigger_character: Vec<String>,
+}
+
+impl Default for DocumentOnTypeFormattingOptions {
+    fn default() -> Self {
+        DocumentOnTypeFormattingOptions {
+            first_trigger_character: ON_TYPE_FIRST_TRIGGER.to_string(),
+            more_trigger_character: ON_TYPE_MORE_TRIGGERS.map(str::to_string).to_vec(),
+        }
+    }
+}
+
+/// Params supplied to the `textDocument/onTypeFormatting` method.
+#[derive(Debug, Deserialize)]
+pub struct DocumentOnTypeFormattingParams {
+    /// The document to format.
+    #[serde(rename = "textDocument")]
+    pub text_document: TextDocumentIdentifier,
+
+    /// The position around which the on type formatting should happen.
+    /// This is not necessarily the exact position where the character denoted
+    /// by the property `ch` got typed.
+    pub position: Position,
+
+    /// The character that has been typed that triggered the formatting
+    /// on type request. That is not necessarily the last character that
+    /// got inserted into the document since the client could auto insert
+    /// characters as well (e.g. like automatic brace completion).
+    pub ch: String,
+
+    /// The formatting options.
+    pub options: FormattingOptions,
+}
+
+/// Computes the edits after typing `ch` with the cursor at `position`.
+///
+/// A new line is indented to the depth of the block it's inside, and a line
+/// that was just completed as `end` is aligned with the line that opened its block.
+pub fn on_type_edits(
+    tree: &SyntaxTree,
+    options: &FormatOptions,
+    position: Position,
+    ch: &str,
+) -> Vec<TextEdit> {
+    let line = position.line as usize;
+    let start = tree.line_index().line_start(line);
+    let content = start
+        .map(|start| {
+            let rest = &tree.text()[start..];
+            rest.split_once('\n').map_or(rest, |(line, _)| line)
+        })
+        .unwrap_or_default();
+
+    let applies = match ch {
+        ON_TYPE_FIRST_TRIGGER => true,
+        _ if ON_TYPE_MORE_TRIGGERS.contains(&ch) => content.trim() == "end",
+        _ => false,
+    };
+    if !applies {
+        return vec![];
+    }
+
+    let Some(indentation) = indentation_at(tree, options, line) else {
+        return vec![];
+    };
+    let current = &content[..content.len() - content.trim_start().len()];
+    if current == indentation {
+        return vec![];
+    }
+
+    let line_start = Position {
+        line: position.line,
+        character: 0,
+    };
+    vec![TextEdit {
+        range: Range {
+            start: line_start,
+            end: Position {
+                line: position.line,
+                character: current.encode_utf16().count() as u32,
+            },
+        },
+        new_text: indentation,
+    }]
+}
+
+/// The document on type formatting request is sent from the client to the server to format parts of the document during typing.
+pub fn on_type_formatting_request(
+    state: &ServerState,
+    params: DocumentOnTypeFormattingParams,
+) -> Option<Vec<TextEdit>> {
+    info!(
+        "Formatting {} after typing {:?}",
+        params.text_document.uri, params.ch
+    );
+
+    let Some(document) = state.documents.get(&params.text_document.uri) else {
+        error!("The file {} is not opened!", params.text_document.uri);
+        return None;
+    };
+
+    let edits: Vec<_> = match document.diagram_at(params.position) {
+        Some((diagram, position)) => on_type_edits(
+            &diagram.ast.cst,
+            &params.options.into(),
+            position,
+            &params.ch,
+        )
+        .into_iter()
+        .map(|e| diagram.map.host_edit(e))
+        .collect(),
+        None => vec![],
+    };
+
+    debug!("On type formatting edits generated {:?}", edits);
+    Some(edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit_text(text: &str, line: u32, ch: &str) -> Option<String> {
+        let tree = SyntaxTree::parse(text.to_string());
+        let position = Position { line, character: 0 };
+
+        let edits = on_type_edits(&tree, &FormatOptions::default(), position, ch);
+
+        edits.first().map(|e| e.new_text.clone())
+    }
+
+    #[test]
+    fn on_type_newline_indents_blocks() {
+        assert_eq!(
+            edit_text("flowchart TD\n    subgraph one\n", 2, "\n"),
+            Some("        ".to_string())
+        );
+        assert_eq!(
+            edit_text("sequenceDiagram\n    loop Forever\n  \n    end\n", 2, "\n"),
+            Some("        ".to_string())
+        );
+        assert_eq!(
+            edit_text("stateDiagram-v2\n    state X {\n\n    }\n", 2, "\n"),
+            Some("        ".to_string())
+        );
+        assert_eq!(
+            edit_text("classDiagram\n    class Duck {\n", 2, "\n"),
+            Some("        ".to_string())
+        );
+        assert_eq!(edit_text("flowchart TD\n    A\n    ", 2, "\n"), None);
+    }
+
+    #[test]
+    fn on_type_end_dedents() {
+        let text = "flowchart TD\n    subgraph one\n        A\n        end\n";
+
+        assert_eq!(edit_text(text, 3, "d"), Some("    ".to_string()));
+        assert_eq!(edit_text(text, 2, "d"), None);
+    }
+}
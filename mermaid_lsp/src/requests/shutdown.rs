@@ -1,11 +1,11 @@
 use log::debug;
 
-use crate::jsonrpc::{LspId, ServerResponse};
+use crate::ServerState;
 
 /// The shutdown request is sent from the client to the server. It asks the server to shut down, but to not exit (otherwise the response might not be delivered correctly to the client). There is a separate exit notification that asks the server to exit. Clients must not send any notifications other than exit or requests to a server to which they have sent a shutdown request. Clients should also wait with sending the exit notification until they have received a response from the shutdown request.
 ///
 /// If a server receives requests after a shutdown request those requests should error with InvalidRequest.
-pub fn shutdown_request(id: LspId) -> ServerResponse {
-    debug!("Shutting down the LSP with id: {:?}!", id);
-    ServerResponse::new_result(Some(id), serde_json::Value::Null)
+pub fn shutdown_request(state: &mut ServerState, _params: ()) {
+    debug!("Shutting down the LSP!");
+    state.shutdown = true;
 }
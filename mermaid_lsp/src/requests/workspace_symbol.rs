@@ -0,0 +1,91 @@
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    jsonrpc::Location,
+    workspace::{index_document, matches_query, IndexedSymbol, WorkspaceIndex},
+    ServerState,
+};
+
+use super::SymbolKind;
+
+/// Params supplied to the `workspace/symbol` method.
+#[derive(Debug, Deserialize)]
+pub struct WorkspaceSymbolParams {
+    /// A query string to filter symbols by. Clients may send an empty
+    /// string here to request all symbols.
+    pub query: String,
+}
+
+/// Represents information about programming constructs like variables, classes,
+/// interfaces etc.
+#[derive(Debug, Serialize)]
+pub struct SymbolInformation {
+    /// The name of this symbol.
+    pub name: String,
+
+    /// The kind of this symbol according to `SymbolKind`.
+    pub kind: u8,
+
+    /// The location of this symbol.
+    pub location: Location,
+
+    /// The name of the symbol containing this symbol. This information is for
+    /// user interface purposes (e.g. to render a qualifier in the user interface
+    /// if necessary). It can't be used to re-infer a hierarchy for the document
+    /// symbols.
+    #[serde(rename = "containerName", skip_serializing_if = "Option::is_none")]
+    pub container_name: Option<String>,
+}
+
+impl SymbolInformation {
+    /// Creates the information of a symbol found in the file `uri`.
+    pub fn new(uri: &str, symbol: &IndexedSymbol) -> Self {
+        SymbolInformation {
+            name: symbol.name.clone(),
+            kind: SymbolKind::from(symbol.kind) as u8,
+            location: Location {
+                uri: uri.to_string(),
+                range: symbol.range,
+            },
+            container_name: symbol.container_name.clone(),
+        }
+    }
+}
+
+/// The workspace symbol request is sent from the client to the server to list project-wide symbols matching the query string.
+///
+/// Opened documents are searched in their current state, the rest of the Mermaid files of the workspace
+/// are searched through the index built in the background.
+pub fn workspace_symbol_request(
+    state: &ServerState,
+    WorkspaceSymbolParams { query }: WorkspaceSymbolParams,
+) -> Vec<SymbolInformation> {
+    info!("Searching workspace symbols that match {:?}", query);
+
+    let mut symbols: Vec<SymbolInformation> = vec![];
+    for (uri, document) in state.documents.iter() {
+        symbols.extend(
+            index_document(document)
+                .iter()
+                .filter(|s| matches_query(&s.name, &query))
+                .map(|s| SymbolInformation::new(uri, s)),
+        );
+    }
+
+    let index = WorkspaceIndex::lock(&state.workspace);
+    for (uri, file_symbols) in index.files() {
+        if state.documents.contains_key(uri) {
+            continue;
+        }
+        symbols.extend(
+            file_symbols
+                .iter()
+                .filter(|s| matches_query(&s.name, &query))
+                .map(|s| SymbolInformation::new(uri, s)),
+        );
+    }
+
+    debug!("Found {} symbols", symbols.len());
+    symbols
+}
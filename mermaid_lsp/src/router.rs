@@ -0,0 +1,385 @@
+use std::{collections::HashMap, sync::Arc};
+
+use log::{debug, error, info, warn};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    host::HostDocument,
+    jsonrpc::{ErrorCodes, LspId, ResponseError, ServerNotification, ServerResponse},
+    ServerState,
+};
+
+/// The prefix of the methods that are specific to a protocol implementation.
+/// Servers are free to ignore the notifications that start with it.
+const IMPLEMENTATION_PREFIX: &str = "$/";
+
+/// A request handler that only reads the state, it takes care of the conversion from and to JSON.
+type ReadHandler = Arc<
+    dyn Fn(&ServerState, serde_json::Value) -> Result<serde_json::Value, ResponseError>
+        + Send
+        + Sync,
+>;
+
+/// A request handler that changes the state, it takes care of the conversion from and to JSON.
+type WriteHandler =
+    Box<dyn Fn(&mut ServerState, serde_json::Value) -> Result<serde_json::Value, ResponseError>>;
+
+enum RequestHandler {
+    Read(ReadHandler),
+    Write(WriteHandler),
+}
+
+/// A notification handler that takes care of the conversion from JSON.
+type NotificationHandler = Box<
+    dyn Fn(&mut ServerState, serde_json::Value) -> Result<Vec<ServerNotification>, RouterErrors>,
+>;
+
+#[derive(Debug)]
+pub enum RouterErrors {
+    InvalidParams(serde_json::Error),
+}
+
+/// Dispatches the messages of the client to the handler registered for their method.
+///
+/// Handlers get their params already deserialized into the type they expect, params that
+/// don't match it are answered with an `InvalidParams` error before the handler runs.
+/// Missing params are read as `null`, so handlers without params can take `()`.
+#[derive(Default)]
+pub struct Router {
+    requests: HashMap<&'static str, RequestHandler>,
+    notifications: HashMap<&'static str, NotificationHandler>,
+}
+
+/// What to do with a request after finding its handler.
+pub enum Dispatch {
+    /// The request was already answered.
+    Respond(ServerResponse),
+    /// The request only reads the state, so it can be answered from another thread.
+    Spawn(RequestJob),
+}
+
+/// A request that only reads the state, ready to run on another thread with a snapshot of it.
+pub struct RequestJob {
+    pub id: LspId,
+    pub method: String,
+    /// The document the request is about, from its `textDocument` param.
+    pub uri: Option<String>,
+    state: Box<ServerState>,
+    params: serde_json::Value,
+    handler: ReadHandler,
+}
+
+impl RequestJob {
+    /// The version of the document the request is about, as the snapshot saw it.
+    ///
+    /// Documents are copied when they change while a snapshot holds them, so a different
+    /// `Arc` means the document changed since.
+    pub fn document(&self) -> Option<Arc<HostDocument>> {
+        self.state.documents.get(self.uri.as_ref()?).cloned()
+    }
+
+    /// Answers the request with the result of its handler.
+    pub fn run(self) -> ServerResponse {
+        debug!("Running {} request with id: {}", self.method, self.id);
+        match (self.handler)(&self.state, self.params) {
+            Ok(result) => ServerResponse::new_result(Some(self.id), result),
+            Err(error) => ServerResponse::new_error(Some(self.id), error),
+        }
+    }
+}
+
+/// Deserializes the params of a request into the type its handler expects.
+fn parse_params<P: DeserializeOwned>(
+    method: &str,
+    params: serde_json::Value,
+) -> Result<P, ResponseError> {
+    serde_json::from_value(params).map_err(|e| {
+        error!("Invalid params supplied to {} request! {:?}", method, e);
+        ResponseError::new(
+            ErrorCodes::InvalidParams,
+            format!("Invalid params supplied to {} request: {}", method, e),
+        )
+    })
+}
+
+/// Serializes the result of a request handler.
+fn serialize_result<R: Serialize>(
+    method: &str,
+    result: R,
+) -> Result<serde_json::Value, ResponseError> {
+    serde_json::to_value(result).map_err(|e| {
+        error!("The result of {} couldn't be serialized! {:?}", method, e);
+        ResponseError::new(
+            ErrorCodes::InternalError,
+            format!("The result of {} couldn't be serialized: {}", method, e),
+        )
+    })
+}
+
+/// The URI of the document a request is about, if it has a `textDocument` param.
+fn document_uri(params: &serde_json::Value) -> Option<String> {
+    let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+    Some(uri.to_string())
+}
+
+impl Router {
+    /// Registers the handler of a request that only reads the state.
+    ///
+    /// These requests run on a snapshot of the state, possibly on another thread.
+    pub fn request<P, R, F>(mut self, method: &'static str, handler: F) -> Self
+    where
+        P: DeserializeOwned,
+        R: Serialize,
+        F: Fn(&ServerState, P) -> R + Send + Sync + 'static,
+    {
+        let handler: ReadHandler = Arc::new(move |state, params| {
+            serialize_result(method, handler(state, parse_params(method, params)?))
+        });
+        self.requests.insert(method, RequestHandler::Read(handler));
+        self
+    }
+
+    /// Registers the handler of a request that changes the state.
+    ///
+    /// These requests run in order with the notifications.
+    pub fn request_mut<P, R, F>(mut self, method: &'static str, handler: F) -> Self
+    where
+        P: DeserializeOwned,
+        R: Serialize,
+        F: Fn(&mut ServerState, P) -> R + 'static,
+    {
+        let handler: WriteHandler = Box::new(move |state, params| {
+            serialize_result(method, handler(state, parse_params(method, params)?))
+        });
+        self.requests.insert(method, RequestHandler::Write(handler));
+        self
+    }
+
+    /// Registers the handler of a notification, it returns the notifications to send back.
+    pub fn notification<P, F>(mut self, method: &'static str, handler: F) -> Self
+    where
+        P: DeserializeOwned,
+        F: Fn(&mut ServerState, P) -> Vec<ServerNotification> + 'static,
+    {
+        let handler: NotificationHandler = Box::new(move |state, params| {
+            let params = serde_json::from_value(params).map_err(RouterErrors::InvalidParams)?;
+            Ok(handler(state, params))
+        });
+        self.notifications.insert(method, handler);
+        self
+    }
+
+    /// Answers a request that changes the state, and prepares the rest to run on a snapshot.
+    ///
+    /// Unknown requests are answered with a `MethodNotFound` error, even the ones that start with `$/`.
+    pub fn dispatch(
+        &self,
+        state: &mut ServerState,
+        id: LspId,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Dispatch {
+        let params = params.unwrap_or_default();
+        match self.requests.get(method) {
+            Some(RequestHandler::Write(handler)) => {
+                info!("Handling {} request with id: {}", method, id);
+                let response = match handler(state, params) {
+                    Ok(result) => ServerResponse::new_result(Some(id), result),
+                    Err(error) => ServerResponse::new_error(Some(id), error),
+                };
+                Dispatch::Respond(response)
+            }
+            Some(RequestHandler::Read(handler)) => {
+                info!("Spawning {} request with id: {}", method, id);
+                Dispatch::Spawn(RequestJob {
+                    id,
+                    method: method.to_string(),
+                    uri: document_uri(&params),
+                    state: Box::new(state.snapshot()),
+                    params,
+                    handler: handler.clone(),
+                })
+            }
+            None => {
+                warn!("Unknown request {} received!", method);
+                Dispatch::Respond(ServerResponse::new_error(
+                    Some(id),
+                    ResponseError::new(
+                        ErrorCodes::MethodNotFound,
+                        format!("The method {} is not supported!", method),
+                    ),
+                ))
+            }
+        }
+    }
+
+    /// Answers a request with the result of its handler, on this thread.
+    pub fn handle_request(
+        &self,
+        state: &mut ServerState,
+        id: LspId,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> ServerResponse {
+        match self.dispatch(state, id, method, params) {
+            Dispatch::Respond(response) => response,
+            Dispatch::Spawn(job) => job.run(),
+        }
+    }
+
+    /// Runs the handler of a notification, and returns the notifications it wants to send back.
+    ///
+    /// Notifications can't be answered, so unknown ones and the ones with invalid params are
+    /// only logged. Unknown notifications that start with `$/` are expected and ignored silently.
+    pub fn handle_notification(
+        &self,
+        state: &mut ServerState,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Vec<ServerNotification> {
+        let Some(handler) = self.notifications.get(method) else {
+            match method.starts_with(IMPLEMENTATION_PREFIX) {
+                true => debug!("Ignoring {} notification", method),
+                false => warn!(
+                    "Unimplemented notification {} received! Ignoring...",
+                    method
+                ),
+            }
+            return vec![];
+        };
+
+        info!("Handling {} notification", method);
+        match handler(state, params.unwrap_or_default()) {
+            Ok(notifications) => notifications,
+            Err(e) => {
+                error!("An error ocurred while handling {}: {:?}", method, e);
+                vec![]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct EchoParams {
+        text: String,
+    }
+
+    fn router() -> Router {
+        Router::default()
+            .request("test/echo", |_, params: EchoParams| params.text)
+            .request("test/noParams", |_, _: ()| 1)
+            .request("test/documents", |state, _: serde_json::Value| {
+                state.documents.len()
+            })
+            .request_mut("test/shutdown", |state, _: ()| state.shutdown = true)
+            .notification("test/open", |state, params: EchoParams| {
+                state.documents.insert(
+                    params.text.clone(),
+                    crate::host::HostDocument::mermaid(params.text).into(),
+                );
+                vec![ServerNotification::new("test/opened", json!(null))]
+            })
+    }
+
+    fn response(response: ServerResponse) -> serde_json::Value {
+        serde_json::to_value(response).unwrap()
+    }
+
+    #[test]
+    fn router_requests() {
+        let router = router();
+        let mut state = ServerState::default();
+
+        let echo = router.handle_request(
+            &mut state,
+            LspId::from(1),
+            "test/echo",
+            Some(json!({ "text": "hi" })),
+        );
+        assert_eq!(response(echo)["result"], "hi");
+
+        let no_params = router.handle_request(&mut state, LspId::from(2), "test/noParams", None);
+        assert_eq!(response(no_params)["result"], 1);
+
+        let invalid = response(router.handle_request(
+            &mut state,
+            LspId::from(3),
+            "test/echo",
+            Some(json!({ "text": 1 })),
+        ));
+        assert_eq!(invalid["id"], 3);
+        assert_eq!(invalid["error"]["code"], ErrorCodes::InvalidParams as i32);
+
+        let missing = response(router.handle_request(
+            &mut state,
+            LspId::String("4".into()),
+            "test/echo",
+            None,
+        ));
+        assert_eq!(missing["error"]["code"], ErrorCodes::InvalidParams as i32);
+
+        let unknown =
+            response(router.handle_request(&mut state, LspId::from(5), "$/unknown", None));
+        assert_eq!(unknown["id"], 5);
+        assert_eq!(unknown["error"]["code"], ErrorCodes::MethodNotFound as i32);
+    }
+
+    #[test]
+    fn router_notifications() {
+        let router = router();
+        let mut state = ServerState::default();
+
+        let sent =
+            router.handle_notification(&mut state, "test/open", Some(json!({ "text": "pie" })));
+        assert_eq!(sent.len(), 1);
+        assert!(state.documents.contains_key("pie"));
+
+        assert!(router
+            .handle_notification(&mut state, "test/open", Some(json!({})))
+            .is_empty());
+        assert!(router
+            .handle_notification(&mut state, "$/setTrace", Some(json!({ "value": "off" })))
+            .is_empty());
+        assert!(router
+            .handle_notification(&mut state, "test/unknown", None)
+            .is_empty());
+        assert_eq!(state.documents.len(), 1);
+    }
+
+    #[test]
+    fn router_dispatch() {
+        let router = router();
+        let mut state = ServerState::default();
+        router.handle_notification(&mut state, "test/open", Some(json!({ "text": "pie" })));
+
+        let Dispatch::Spawn(job) = router.dispatch(
+            &mut state,
+            LspId::from(1),
+            "test/documents",
+            Some(json!({ "textDocument": { "uri": "pie" } })),
+        ) else {
+            panic!("Read requests should be spawned!");
+        };
+        assert_eq!(job.uri.as_deref(), Some("pie"));
+        assert!(Arc::ptr_eq(
+            &job.document().unwrap(),
+            &state.documents["pie"]
+        ));
+        assert_eq!(response(job.run())["result"], 1);
+
+        let Dispatch::Respond(shutdown) =
+            router.dispatch(&mut state, LspId::from(2), "test/shutdown", None)
+        else {
+            panic!("Write requests should be answered right away!");
+        };
+        assert_eq!(response(shutdown)["id"], 2);
+        assert!(state.shutdown);
+    }
+}
@@ -0,0 +1,122 @@
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    jsonrpc::{Position, Range},
+    mermaid::{self, lint::LintConfig, Completion, FormatOptions, MermaidAST, SyntaxTree},
+};
+
+/// A problem of a diagram, shaped like the diagnostics of the LSP.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub range: Range,
+    /// 1 for errors, 2 for warnings, 3 for information and 4 for hints.
+    pub severity: u8,
+    pub code: &'static str,
+    pub message: String,
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn diagram_diagnostics(text: String, config: &LintConfig) -> Vec<Diagnostic> {
+    let ast = MermaidAST::from_content(text);
+    mermaid::diagnostics(&ast, config)
+        .into_iter()
+        .map(|d| Diagnostic {
+            range: ast.cst.range(d.span),
+            severity: match d.severity {
+                mermaid::DiagnosticSeverity::Error => 1,
+                mermaid::DiagnosticSeverity::Warning => 2,
+                mermaid::DiagnosticSeverity::Information => 3,
+                mermaid::DiagnosticSeverity::Hint => 4,
+            },
+            code: d.code.as_str(),
+            message: d.message,
+        })
+        .collect()
+}
+
+fn format_diagram(text: String, tab_size: Option<u32>, insert_spaces: Option<bool>) -> String {
+    let defaults = FormatOptions::default();
+    let options = FormatOptions {
+        tab_size: tab_size.unwrap_or(defaults.tab_size),
+        insert_spaces: insert_spaces.unwrap_or(defaults.insert_spaces),
+    };
+    mermaid::format(&SyntaxTree::parse(text), &options)
+}
+
+fn complete_diagram(text: String, line: u32, character: u32) -> Vec<Completion> {
+    let ast = MermaidAST::from_content(text);
+    let offset = ast.cst.offset(Position { line, character });
+    mermaid::completions(&ast, offset)
+}
+
+/// Parses a diagram into the same AST the `ast` command prints.
+#[wasm_bindgen]
+pub fn parse(text: String) -> Result<JsValue, JsError> {
+    to_js(&MermaidAST::from_content(text))
+}
+
+/// Finds the problems of a diagram, `config` has the severity of the lint rules like the
+/// `lint` section of `.mermaid-lsp.json`, the defaults are used without it.
+#[wasm_bindgen]
+pub fn diagnostics(text: String, config: JsValue) -> Result<JsValue, JsError> {
+    let config: LintConfig = match config.is_undefined() || config.is_null() {
+        true => LintConfig::default(),
+        false => serde_wasm_bindgen::from_value(config)
+            .map_err(|e| JsError::new(&format!("Invalid lint config: {}", e)))?,
+    };
+    to_js(&diagram_diagnostics(text, &config))
+}
+
+/// Formats a diagram, indenting with 4 spaces unless told otherwise.
+#[wasm_bindgen]
+pub fn format(text: String, tab_size: Option<u32>, insert_spaces: Option<bool>) -> String {
+    format_diagram(text, tab_size, insert_spaces)
+}
+
+/// The words that can be inserted at a zero-based position, the character is counted in UTF-16
+/// code units like JavaScript strings.
+#[wasm_bindgen]
+pub fn complete(text: String, line: u32, character: u32) -> Result<JsValue, JsError> {
+    to_js(&complete_diagram(text, line, character))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mermaid::CompletionKind;
+
+    use super::*;
+
+    #[test]
+    fn wasm_api() {
+        let diagnostics = diagram_diagnostics(
+            "flowchart TD\n    subgraph one\n".to_string(),
+            &LintConfig::default(),
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "unclosed-block");
+        assert_eq!(diagnostics[0].severity, 1);
+        assert_eq!(
+            diagnostics[0].range.start,
+            Position {
+                line: 1,
+                character: 4
+            }
+        );
+
+        assert_eq!(
+            format_diagram("flowchart TD\nA --> B\n".to_string(), Some(2), None),
+            "flowchart TD\n  A --> B\n"
+        );
+
+        let completions = complete_diagram("flowchart TD\n    A --> B\n    ".to_string(), 2, 4);
+        assert!(completions
+            .iter()
+            .any(|c| c.label == "B" && c.kind == CompletionKind::Symbol));
+    }
+}
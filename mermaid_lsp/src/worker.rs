@@ -0,0 +1,151 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crossbeam_channel::Sender;
+use log::{debug, info};
+
+use crate::{
+    jsonrpc::{LspId, ServerResponse},
+    logging,
+    router::RequestJob,
+};
+
+/// A request waiting for a worker, with the flag that cancels it.
+struct Task {
+    job: RequestJob,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// The threads that answer the requests that only read the state, so a slow request doesn't
+/// hold back the rest of the messages.
+///
+/// The responses are sent with the id of their request, in the order the requests finish.
+/// The threads stop once the pool is dropped and the queued requests are done.
+pub struct WorkerPool {
+    tasks: Sender<Task>,
+}
+
+impl WorkerPool {
+    /// Starts `size` workers, at least one.
+    ///
+    /// The warnings of the workers go to the client of the thread that starts them.
+    pub fn new(size: usize, responses: Sender<(LspId, ServerResponse)>) -> Self {
+        let (tasks, receiver) = crossbeam_channel::unbounded::<Task>();
+        let client = logging::client_messages();
+        for worker in 0..size.max(1) {
+            let receiver = receiver.clone();
+            let responses = responses.clone();
+            let client = client.clone();
+            std::thread::spawn(move || {
+                logging::forward_to_client(client);
+                for Task { job, cancelled } in receiver {
+                    // Requests cancelled while queued were already answered.
+                    if cancelled.load(Ordering::Acquire) {
+                        debug!("Skipping cancelled request with id: {}", job.id);
+                        continue;
+                    }
+
+                    let id = job.id.clone();
+                    if responses.send((id, job.run())).is_err() {
+                        break;
+                    }
+                }
+                info!("Worker {} is done!", worker);
+            });
+        }
+
+        WorkerPool { tasks }
+    }
+
+    /// Queues a request, the flag it returns skips it if it didn't start yet.
+    pub fn spawn(&self, job: RequestJob) -> Arc<AtomicBool> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let task = Task {
+            job,
+            cancelled: cancelled.clone(),
+        };
+        // The workers only stop after the pool is dropped.
+        let _ = self.tasks.send(task);
+        cancelled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        router::{Dispatch, Router},
+        ServerState,
+    };
+
+    use super::*;
+
+    fn job(router: &Router, state: &mut ServerState, id: i64) -> RequestJob {
+        match router.dispatch(state, LspId::from(id), "test/count", None) {
+            Dispatch::Spawn(job) => job,
+            Dispatch::Respond(_) => panic!("Read requests should be spawned!"),
+        }
+    }
+
+    #[test]
+    fn worker_pool() {
+        let router = Router::default().request("test/count", |state: &ServerState, _: ()| {
+            state.documents.len()
+        });
+        let mut state = ServerState::default();
+        let (sender, responses) = crossbeam_channel::unbounded();
+        let pool = WorkerPool::new(2, sender);
+
+        let first = job(&router, &mut state, 1);
+        state.documents.insert(
+            "a".into(),
+            crate::host::HostDocument::mermaid("pie".into()).into(),
+        );
+        let second = job(&router, &mut state, 2);
+
+        pool.spawn(first);
+        pool.spawn(second);
+        drop(pool);
+
+        let mut answered: Vec<_> = responses
+            .iter()
+            .map(|(id, response)| {
+                (
+                    id,
+                    serde_json::to_value(response).unwrap()["result"].clone(),
+                )
+            })
+            .collect();
+        answered.sort_by_key(|(id, _)| id.to_string());
+        assert_eq!(
+            answered,
+            vec![(LspId::from(1), json!(0)), (LspId::from(2), json!(1))]
+        );
+    }
+
+    #[test]
+    fn worker_pool_cancellation() {
+        let mut state = ServerState::default();
+        let (sender, responses) = crossbeam_channel::unbounded();
+        let pool = WorkerPool::new(1, sender);
+
+        // The only worker is busy with the first request until the lock is released.
+        let lock = Arc::new(std::sync::Mutex::new(()));
+        let guard = lock.lock().unwrap();
+        let router = Router::default().request("test/count", {
+            let lock = lock.clone();
+            move |_: &ServerState, _: ()| lock.lock().map(|_| 0).unwrap_or(0)
+        });
+        pool.spawn(job(&router, &mut state, 1));
+        pool.spawn(job(&router, &mut state, 2))
+            .store(true, Ordering::Release);
+        drop(guard);
+        drop(pool);
+
+        let ids: Vec<_> = responses.iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec![LspId::from(1)]);
+    }
+}
@@ -0,0 +1,344 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    thread::JoinHandle,
+};
+
+use crossbeam_channel::Sender;
+use log::{debug, error, info, warn};
+use url::Url;
+use walkdir::WalkDir;
+
+use crate::{
+    host::{EmbeddedDiagram, HostDocument, HostLanguage},
+    jsonrpc::Range,
+    logging,
+    mermaid::{document_symbols, DiagramSymbol, DiagramSymbolKind},
+};
+
+/// Directories that are never walked while indexing the workspace.
+const IGNORED_DIRECTORIES: [&str; 3] = ["node_modules", "target", ".git"];
+
+/// A symbol of some file of the workspace, ready to be searched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedSymbol {
+    pub name: String,
+    pub kind: DiagramSymbolKind,
+
+    /// The name of the symbol that contains this one, like the subgraph of a node.
+    pub container_name: Option<String>,
+
+    /// The range of the symbol inside its file.
+    pub range: Range,
+}
+
+/// The symbols of every file of the workspace with diagrams, indexed by URI.
+#[derive(Debug, Default)]
+pub struct WorkspaceIndex {
+    files: HashMap<String, Vec<IndexedSymbol>>,
+}
+
+impl WorkspaceIndex {
+    /// Locks a shared index.
+    ///
+    /// A thread that panicked while holding the lock only leaves a file half indexed at worst,
+    /// so the index keeps being used instead of taking the whole server down.
+    pub fn lock(index: &Mutex<WorkspaceIndex>) -> MutexGuard<'_, WorkspaceIndex> {
+        index.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Replaces the symbols stored for `uri`.
+    pub fn insert(&mut self, uri: String, symbols: Vec<IndexedSymbol>) {
+        self.files.insert(uri, symbols);
+    }
+
+    /// Removes the symbols of `uri`, used when the file is deleted.
+    pub fn remove(&mut self, uri: &str) {
+        self.files.remove(uri);
+    }
+
+    /// The amount of files indexed.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Checks if no file has been indexed.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Iterates over the indexed files and their symbols.
+    pub fn files(&self) -> impl Iterator<Item = (&String, &Vec<IndexedSymbol>)> {
+        self.files.iter()
+    }
+}
+
+/// Flattens the outline of every diagram of a document into searchable symbols.
+///
+/// The diagram symbol itself is skipped, searching for `flowchart TD` is not useful.
+pub fn index_document(document: &HostDocument) -> Vec<IndexedSymbol> {
+    fn visit(
+        diagram: &EmbeddedDiagram,
+        symbol: &DiagramSymbol,
+        container_name: Option<&str>,
+        symbols: &mut Vec<IndexedSymbol>,
+    ) {
+        let is_diagram = symbol.kind == DiagramSymbolKind::Diagram;
+        if !is_diagram {
+            symbols.push(IndexedSymbol {
+                name: symbol.name.clone(),
+                kind: symbol.kind,
+                container_name: container_name.map(str::to_string),
+                range: diagram.range(symbol.selection_span),
+            });
+        }
+
+        let container_name = if is_diagram {
+            None
+        } else {
+            Some(symbol.name.as_str())
+        };
+        for child in symbol.children.iter() {
+            visit(diagram, child, container_name, symbols);
+        }
+    }
+
+    let mut symbols = vec![];
+    for diagram in document.diagrams.iter() {
+        for symbol in document_symbols(&diagram.ast).iter() {
+            visit(diagram, symbol, None, &mut symbols);
+        }
+    }
+    symbols
+}
+
+/// Reads a file from disk and stores its symbols under `uri`.
+pub fn index_file(index: &Mutex<WorkspaceIndex>, uri: &str) -> std::io::Result<()> {
+    let path = uri_to_path(uri).ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "The URI is not a file!")
+    })?;
+    let language = HostLanguage::from_path(&path).unwrap_or_default();
+    let content = std::fs::read_to_string(path)?;
+    let symbols = index_document(&HostDocument::new(language, content));
+
+    WorkspaceIndex::lock(index).insert(uri.to_string(), symbols);
+    Ok(())
+}
+
+/// Walks all `folders` and indexes every file with diagrams inside them.
+pub fn index_folders(index: &Mutex<WorkspaceIndex>, folders: &[PathBuf]) {
+    let files = folders.iter().flat_map(|folder| {
+        WalkDir::new(folder)
+            .into_iter()
+            .filter_entry(|e| {
+                !(e.file_type().is_dir() && IGNORED_DIRECTORIES.iter().any(|d| e.file_name() == *d))
+            })
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file() && is_diagram_file(e.path()))
+    });
+
+    for file in files {
+        let Some(uri) = path_to_uri(file.path()) else {
+            continue;
+        };
+        debug!("Indexing {}", uri);
+        if let Err(e) = index_file(index, &uri) {
+            error!("The file {} couldn't be indexed! {:?}", uri, e);
+        }
+    }
+}
+
+/// Work for the thread that keeps the workspace index up to date.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexJob {
+    /// Walk the folders and index every file with diagrams inside them.
+    Folders(Vec<PathBuf>),
+
+    /// Read a file from disk and index it again.
+    File(String),
+
+    /// Forget a file that was deleted.
+    Remove(String),
+}
+
+/// The thread that reads and indexes the files of the workspace, so the server can keep
+/// answering messages while it waits for the disk or for the lock of the index.
+///
+/// The thread is started with the first job, the jobs run in the order they are sent.
+/// It stops once the indexer is dropped and the queued jobs are done.
+#[derive(Debug, Default)]
+pub struct Indexer {
+    thread: Option<(Sender<IndexJob>, JoinHandle<()>)>,
+}
+
+impl Indexer {
+    /// Queues a job that updates `index`.
+    pub fn send(&mut self, index: &Arc<Mutex<WorkspaceIndex>>, job: IndexJob) {
+        // A thread that panicked is started again, only the job that broke it is lost.
+        if self.thread.as_ref().is_some_and(|(_, t)| t.is_finished()) {
+            warn!("The indexing thread stopped, starting it again...");
+            self.thread = None;
+        }
+
+        let (jobs, _) = self
+            .thread
+            .get_or_insert_with(|| spawn_indexer(index.clone()));
+        if jobs.send(job).is_err() {
+            error!("The indexing thread stopped before receiving the job!");
+        }
+    }
+
+    /// Waits until every queued job is done.
+    pub fn join(&mut self) {
+        if let Some((jobs, thread)) = self.thread.take() {
+            drop(jobs);
+            if thread.join().is_err() {
+                error!("The indexing thread panicked!");
+            }
+        }
+    }
+}
+
+/// Starts the thread that runs the jobs of an [`Indexer`], its warnings go to the client of the current thread.
+fn spawn_indexer(index: Arc<Mutex<WorkspaceIndex>>) -> (Sender<IndexJob>, JoinHandle<()>) {
+    let (jobs, receiver) = crossbeam_channel::unbounded::<IndexJob>();
+    let client = logging::client_messages();
+    let thread = std::thread::spawn(move || {
+        logging::forward_to_client(client);
+        for job in receiver {
+            match job {
+                IndexJob::Folders(folders) => {
+                    info!("Indexing workspace folders {:?}...", folders);
+                    index_folders(&index, &folders);
+                    info!(
+                        "Workspace indexed! {} files found.",
+                        WorkspaceIndex::lock(&index).len()
+                    );
+                }
+                IndexJob::File(uri) => {
+                    debug!("Indexing {} again", uri);
+                    if let Err(e) = index_file(&index, &uri) {
+                        error!("The file {} couldn't be indexed! {:?}", uri, e);
+                    }
+                }
+                IndexJob::Remove(uri) => {
+                    info!("Removing {} from the workspace index", uri);
+                    WorkspaceIndex::lock(&index).remove(&uri);
+                }
+            }
+        }
+        debug!("The indexing thread is done!");
+    });
+
+    (jobs, thread)
+}
+
+/// Checks if a path has the extension of a Mermaid file or of a file that can embed diagrams.
+pub fn is_diagram_file(path: &Path) -> bool {
+    HostLanguage::from_path(path).is_some()
+}
+
+/// Converts a `file://` URI into a path.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+/// Converts an absolute path into a `file://` URI.
+pub fn path_to_uri(path: &Path) -> Option<String> {
+    Url::from_file_path(path).ok().map(String::from)
+}
+
+/// Checks if the characters of `query` appear in order inside `name`, ignoring case.
+///
+/// An empty query matches every symbol.
+pub fn matches_query(name: &str, query: &str) -> bool {
+    let mut name = name.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|q| name.any(|n| n == q))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_query_success() {
+        assert!(matches_query("LoginService", ""));
+        assert!(matches_query("LoginService", "lserv"));
+        assert!(matches_query("LoginService", "LOGIN"));
+        assert!(!matches_query("LoginService", "servicel"));
+    }
+
+    #[test]
+    fn indexer_recovers_from_poisoned_index() {
+        let index = Arc::new(Mutex::new(WorkspaceIndex::default()));
+        let poisoned = index.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoned.lock().unwrap();
+            panic!("Poisoning the index");
+        })
+        .join();
+        assert!(index.is_poisoned());
+
+        let mut indexer = Indexer::default();
+        indexer.send(&index, IndexJob::Remove("file:///a.mmd".into()));
+        indexer.join();
+
+        assert!(WorkspaceIndex::lock(&index).is_empty());
+    }
+
+    #[test]
+    fn index_folders_success() {
+        let workspace = tempfile::tempdir().unwrap();
+        let nested = workspace.path().join("docs");
+        std::fs::create_dir(&nested).unwrap();
+        std::fs::write(
+            nested.join("flow.mmd"),
+            "flowchart TD\n    subgraph auth\n        Login --> Home\n    end\n",
+        )
+        .unwrap();
+        std::fs::write(
+            workspace.path().join("seq.mermaid"),
+            "sequenceDiagram\n    Alice->>Bob: Hi\n",
+        )
+        .unwrap();
+        std::fs::write(
+            workspace.path().join("README.md"),
+            "# Docs\n\n```mermaid\nflowchart LR\n    Docs --> Site\n```\n",
+        )
+        .unwrap();
+        std::fs::write(workspace.path().join("notes.txt"), "Not a diagram").unwrap();
+        let ignored = workspace.path().join("node_modules");
+        std::fs::create_dir(&ignored).unwrap();
+        std::fs::write(ignored.join("ignored.mmd"), "flowchart TD\n    Ignored\n").unwrap();
+
+        let index = Mutex::new(WorkspaceIndex::default());
+        index_folders(&index, &[workspace.path().to_path_buf()]);
+
+        let index = index.into_inner().unwrap();
+        assert_eq!(index.len(), 3);
+
+        let uri = path_to_uri(&nested.join("flow.mmd")).unwrap();
+        let (_, symbols) = index.files().find(|(u, _)| **u == uri).unwrap();
+        let symbols: Vec<_> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.container_name.as_deref()))
+            .collect();
+        assert_eq!(
+            symbols,
+            vec![
+                ("auth", None),
+                ("Login", Some("auth")),
+                ("Home", Some("auth"))
+            ]
+        );
+
+        let uri = path_to_uri(&workspace.path().join("README.md")).unwrap();
+        let (_, symbols) = index.files().find(|(u, _)| **u == uri).unwrap();
+        assert_eq!(symbols[0].name, "Docs");
+        assert_eq!(symbols[0].range.start.line, 4);
+    }
+}